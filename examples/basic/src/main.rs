@@ -2,6 +2,7 @@ use opentelemetry::api::{
     Context, CorrelationContextExt, Gauge, GaugeHandle, Key, Measure, MeasureHandle, Meter,
     MetricOptions, TraceContextExt, Tracer,
 };
+use opentelemetry::exporter::metrics::stdout;
 use opentelemetry::{global, sdk};
 
 fn init_tracer() -> thrift::Result<()> {
@@ -33,6 +34,8 @@ fn init_tracer() -> thrift::Result<()> {
 fn main() -> thrift::Result<()> {
     init_tracer()?;
     let meter = sdk::Meter::new("ex_com_basic");
+    let exporter = stdout::Builder::default().with_pretty_print(true).init();
+    let mut controller = sdk::PushController::builder(meter.clone(), exporter).build();
 
     let foo_key = Key::new("ex.com/foo");
     let bar_key = Key::new("ex.com/bar");
@@ -86,5 +89,8 @@ fn main() -> thrift::Result<()> {
         });
     });
 
+    // Export the recorded metrics before exiting
+    controller.shutdown();
+
     Ok(())
 }
@@ -1,15 +1,16 @@
 use opentelemetry::api::{Span, Tracer};
 use opentelemetry::{global, sdk};
+use std::io;
 use std::thread;
 use std::time::Duration;
 
-fn init_tracer() {
+fn init_tracer() -> io::Result<()> {
     let exporter = opentelemetry_zipkin::Exporter::from_config(
         opentelemetry_zipkin::ExporterConfig::builder()
             .with_service_name("trace-demo".to_owned())
             .with_service_endpoint("127.0.0.7:9411".parse().unwrap())
             .build(),
-    );
+    )?;
 
     // For the demonstration, use `Sampler::Always` sampler to sample all traces. In a production
     // application, use `Sampler::Parent` or `Sampler::Probability` with a desired probability.
@@ -21,6 +22,8 @@ fn init_tracer() {
         })
         .build();
     global::set_provider(provider);
+
+    Ok(())
 }
 
 fn bar() {
@@ -30,8 +33,8 @@ fn bar() {
     span.end()
 }
 
-fn main() {
-    init_tracer();
+fn main() -> io::Result<()> {
+    init_tracer()?;
     let tracer = global::tracer("component-main");
 
     tracer.in_span("foo", |_cx| {
@@ -39,4 +42,6 @@ fn main() {
         bar();
         thread::sleep(Duration::from_millis(6));
     });
+
+    Ok(())
 }
@@ -1,71 +1,269 @@
 //! # HTTP Jaeger Collector Client
 use crate::thrift::jaeger;
-use crate::transport::THttpChannel;
-use std::fmt;
-use thrift::protocol::{TBinaryInputProtocol, TBinaryOutputProtocol};
-use thrift::transport::{ReadHalf, TIoChannel, WriteHalf};
-use thrift::{protocol, TThriftClient};
-
-/// `CollectorSyncClientHttp` implements the `TCollectorSyncClient` interface over HTTP
-pub(crate) struct CollectorSyncClientHttp {
-    client: jaeger::CollectorSyncClient<
-        TBinaryInputProtocol<ReadHalf<THttpChannel>>,
-        TBinaryOutputProtocol<WriteHalf<THttpChannel>>,
-    >,
-}
+use futures::future::BoxFuture;
+use opentelemetry::exporter::trace;
+use std::time::Duration;
+use thrift::protocol::TBinaryOutputProtocol;
 
-impl fmt::Debug for CollectorSyncClientHttp {
-    /// Debug info
-    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
-        fmt.debug_struct("CollectorSyncClientHttp")
-            .field("client", &"CollectorSyncClient")
-            .finish()
-    }
+/// Content type of thrift binary encoded batches
+static THRIFT_BINARY_CONTENT_TYPE: &str = "application/vnd.apache.thrift.binary";
+/// Some arbitrary default timeout to avoid hanging forever
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// `CollectorHttpClient` submits batches to the Jaeger collector over HTTP,
+/// either blocking or without blocking the calling thread.
+#[derive(Debug)]
+pub(crate) struct CollectorHttpClient {
+    endpoint: String,
+    credentials: Option<(String, String)>,
+    client: reqwest::Client,
+    // Requests are driven by the client's own runtime so that batches can be
+    // submitted from any thread and any executor.
+    runtime: tokio::runtime::Runtime,
 }
 
-impl CollectorSyncClientHttp {
+impl CollectorHttpClient {
     /// Create a new HTTP collector client
     pub(crate) fn new<T: Into<String>>(
         endpoint: T,
         username: Option<String>,
         password: Option<String>,
     ) -> thrift::Result<Self> {
-        let transport = crate::transport::THttpChannel::new(endpoint, username, password)?;
-        let (read, write) = transport.split()?;
-        let client = jaeger::CollectorSyncClient::new(
-            protocol::TBinaryInputProtocol::new(read, false),
-            protocol::TBinaryOutputProtocol::new(write, true),
-        );
+        let client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .map_err(|err| thrift::Error::from(err.to_string()))?;
+        let runtime = tokio::runtime::Builder::new()
+            .threaded_scheduler()
+            .core_threads(1)
+            .thread_name("opentelemetry-jaeger")
+            .enable_all()
+            .build()
+            .map_err(|err| thrift::Error::from(err.to_string()))?;
+
+        Ok(CollectorHttpClient {
+            endpoint: endpoint.into(),
+            credentials: username
+                .and_then(|username| password.map(|password| (username, password))),
+            client,
+            runtime,
+        })
+    }
 
-        Ok(CollectorSyncClientHttp { client })
+    /// Submit a Jaeger batch, blocking until the collector responds.
+    pub(crate) fn submit_batch(&self, batch: jaeger::Batch) -> trace::ExportResult {
+        futures::executor::block_on(self.submit_batch_async(batch))
+    }
+
+    /// Submit a Jaeger batch without blocking the calling thread.
+    pub(crate) fn submit_batch_async(
+        &self,
+        batch: jaeger::Batch,
+    ) -> BoxFuture<'static, trace::ExportResult> {
+        let body = match serialize_batch(batch) {
+            Ok(body) => body,
+            Err(_) => {
+                return Box::pin(futures::future::ready(
+                    trace::ExportResult::FailedNotRetryable,
+                ))
+            }
+        };
+        let mut request = self
+            .client
+            .post(&self.endpoint)
+            .header(reqwest::header::CONTENT_TYPE, THRIFT_BINARY_CONTENT_TYPE)
+            .body(body);
+        if let Some((username, password)) = self.credentials.as_ref() {
+            request = request.basic_auth(username, Some(password));
+        }
+
+        let handle = self.runtime.spawn(async move {
+            match request.send().await {
+                Ok(response) => export_result(response.status()),
+                Err(_) => trace::ExportResult::FailedRetryable,
+            }
+        });
+        Box::pin(async move {
+            handle
+                .await
+                .unwrap_or(trace::ExportResult::FailedNotRetryable)
+        })
     }
 }
 
-impl jaeger::TCollectorSyncClient for CollectorSyncClientHttp {
-    /// Submit list of Jaeger batches
-    fn submit_batches(
-        &mut self,
-        batches: Vec<jaeger::Batch>,
-    ) -> thrift::Result<Vec<jaeger::BatchSubmitResponse>> {
-        // Ordinarily this would delegate to the client via `self.client.submit_batches(batches)`,
-        // but the collector **does not actually accept a list of batches**. This is contrary to the
-        // [definition of the service](https://github.com/jaegertracing/jaeger-idl/blob/d4063e359bc52eca57cdbeb92a179fd3aa0250d6/thrift/jaeger.thrift#L83-L85).
-        // Instead, it accepts a single `jaeger::Batch` for consistency with tchannel intake.
-        //
-        // ¯\_(ツ)_/¯
-        //
-        // Details: https://github.com/jaegertracing/jaeger/blob/530e1f11508d7ee31307b9d70317cf6581faa5b7/cmd/collector/app/http_handler.go#L83-L88
-        Ok(batches
-            .into_iter()
-            .map(|batch| {
-                let proto = self.client.o_prot_mut();
-                let ok = batch
-                    .write_to_out_protocol(proto)
-                    .and_then(|_| proto.flush())
-                    .is_ok();
-
-                jaeger::BatchSubmitResponse { ok }
-            })
-            .collect())
+/// Encode a batch with the thrift binary protocol.
+///
+/// The collector's HTTP endpoint accepts a single `jaeger::Batch` rather than
+/// the list of batches in the [definition of the service], for consistency
+/// with tchannel intake.
+///
+/// [definition of the service]: https://github.com/jaegertracing/jaeger-idl/blob/d4063e359bc52eca57cdbeb92a179fd3aa0250d6/thrift/jaeger.thrift#L83-L85
+fn serialize_batch(batch: jaeger::Batch) -> thrift::Result<Vec<u8>> {
+    let mut buffer = Vec::new();
+    {
+        let mut protocol = TBinaryOutputProtocol::new(&mut buffer, true);
+        batch.write_to_out_protocol(&mut protocol)?;
+    }
+
+    Ok(buffer)
+}
+
+/// Maps collector response statuses to export results, retrying only
+/// statuses that indicate a transient failure.
+fn export_result(status: reqwest::StatusCode) -> trace::ExportResult {
+    match status {
+        status if status.is_success() => trace::ExportResult::Success,
+        reqwest::StatusCode::TOO_MANY_REQUESTS
+        | reqwest::StatusCode::BAD_GATEWAY
+        | reqwest::StatusCode::SERVICE_UNAVAILABLE
+        | reqwest::StatusCode::GATEWAY_TIMEOUT => trace::ExportResult::FailedRetryable,
+        _ => trace::ExportResult::FailedNotRetryable,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use thrift::protocol::TBinaryInputProtocol;
+
+    #[derive(Debug)]
+    struct RecordedRequest {
+        path: String,
+        headers: Vec<(String, String)>,
+        body: Vec<u8>,
+    }
+
+    impl RecordedRequest {
+        fn header(&self, name: &str) -> Option<&str> {
+            self.headers
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.as_str())
+        }
+    }
+
+    /// Starts a stub collector replying to every request with `status`,
+    /// returning its endpoint and the requests it has received.
+    fn start_collector(status: u16) -> (String, Arc<Mutex<Vec<RecordedRequest>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}/api/traces", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let path = request_line.split(' ').nth(1).unwrap().to_string();
+
+                let mut headers = Vec::new();
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    let line = line.trim_end();
+                    if line.is_empty() {
+                        break;
+                    }
+                    let mut parts = line.splitn(2, ':');
+                    let key = parts.next().unwrap().trim().to_string();
+                    let value = parts.next().unwrap().trim().to_string();
+                    headers.push((key, value));
+                }
+
+                let len = headers
+                    .iter()
+                    .find(|(key, _)| key.eq_ignore_ascii_case("content-length"))
+                    .map(|(_, value)| value.parse().unwrap())
+                    .unwrap_or(0);
+                let mut body = vec![0; len];
+                reader.read_exact(&mut body).unwrap();
+                recorded.lock().unwrap().push(RecordedRequest {
+                    path,
+                    headers,
+                    body,
+                });
+
+                write!(
+                    stream,
+                    "HTTP/1.1 {} Stub\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    status
+                )
+                .unwrap();
+            }
+        });
+
+        (endpoint, requests)
+    }
+
+    fn batch() -> jaeger::Batch {
+        jaeger::Batch::new(jaeger::Process::new("test".to_string(), None), Vec::new())
+    }
+
+    #[test]
+    fn submits_batches_without_blocking() {
+        let (endpoint, requests) = start_collector(202);
+        let client = CollectorHttpClient::new(
+            endpoint,
+            Some("user".to_string()),
+            Some("secret".to_string()),
+        )
+        .unwrap();
+
+        let result = futures::executor::block_on(client.submit_batch_async(batch()));
+        assert_eq!(result, trace::ExportResult::Success);
+
+        let requests = requests.lock().unwrap();
+        let request = &requests[0];
+        assert_eq!(request.path, "/api/traces");
+        assert_eq!(
+            request.header("content-type"),
+            Some(THRIFT_BINARY_CONTENT_TYPE)
+        );
+        // base64 of `user:secret`
+        assert_eq!(
+            request.header("authorization"),
+            Some("Basic dXNlcjpzZWNyZXQ=")
+        );
+        let mut protocol = TBinaryInputProtocol::new(request.body.as_slice(), true);
+        let batch = jaeger::Batch::read_from_in_protocol(&mut protocol).unwrap();
+        assert_eq!(batch.process.service_name, "test");
+    }
+
+    #[test]
+    fn maps_collector_errors() {
+        for (status, expected) in [
+            (503, trace::ExportResult::FailedRetryable),
+            (400, trace::ExportResult::FailedNotRetryable),
+        ] {
+            let (endpoint, _) = start_collector(status);
+            let client = CollectorHttpClient::new(endpoint, None, None).unwrap();
+            let result = futures::executor::block_on(client.submit_batch_async(batch()));
+            assert_eq!(result, expected, "status {}", status);
+        }
+    }
+
+    #[test]
+    fn retries_only_transient_statuses() {
+        for (status, expected) in [
+            (200, trace::ExportResult::Success),
+            (202, trace::ExportResult::Success),
+            (429, trace::ExportResult::FailedRetryable),
+            (502, trace::ExportResult::FailedRetryable),
+            (503, trace::ExportResult::FailedRetryable),
+            (504, trace::ExportResult::FailedRetryable),
+            (400, trace::ExportResult::FailedNotRetryable),
+            (404, trace::ExportResult::FailedNotRetryable),
+            (413, trace::ExportResult::FailedNotRetryable),
+            (500, trace::ExportResult::FailedNotRetryable),
+        ] {
+            let status = reqwest::StatusCode::from_u16(status).unwrap();
+            assert_eq!(export_result(status), expected, "status {}", status);
+        }
     }
 }
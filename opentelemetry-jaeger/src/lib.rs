@@ -46,6 +46,8 @@
 //! If you want to skip the agent and submit spans directly to a Jaeger collector,
 //! you can enable the optional `collector_client` feature for this crate. This
 //! example expects a Jaeger collector running on `http://localhost:14268`.
+//! Batches are submitted from the collector client's own Tokio runtime, so
+//! the exporter can be used from any executor.
 //!
 //! ```toml
 //! [dependencies]
@@ -89,19 +91,50 @@
 //! }
 //! ```
 //!
+//! When used with a [`BatchSpanProcessor`], batches are submitted to the
+//! collector without blocking the executor driving the processor.
+//!
+//! ### Jaeger Remote Sampling
+//!
+//! With the optional `remote_sampler` feature, the [`RemoteSampler`] samples
+//! spans with the sampling strategy of the service managed by Jaeger, polled
+//! from the agent's sampling endpoint on `http://localhost:5778/sampling`.
+//!
+//! ```rust,ignore
+//! // Note that this requires the `remote_sampler` feature.
+//!
+//! use opentelemetry::sdk;
+//!
+//! let sampler = opentelemetry_jaeger::RemoteSampler::builder("trace-demo").build();
+//! let provider = sdk::Provider::builder()
+//!     .with_config(sdk::Config {
+//!         default_sampler: Box::new(sampler),
+//!         ..Default::default()
+//!     })
+//!     .build();
+//! ```
+//!
 //! [Jaeger Docs]: https://www.jaegertracing.io/docs/
+//! [`BatchSpanProcessor`]: ../opentelemetry/sdk/trace/span_processor/struct.BatchSpanProcessor.html
 //! [`with_collector_endpoint`]: struct.Builder.html#with_collector_endpoint
+//! [`RemoteSampler`]: struct.RemoteSampler.html
 #![deny(missing_docs, unreachable_pub, missing_debug_implementations)]
 #![cfg_attr(test, deny(warnings))]
 mod agent;
 #[cfg(feature = "collector_client")]
 mod collector;
+#[cfg(feature = "remote_sampler")]
+mod sampler;
 #[allow(clippy::all, unreachable_pub, dead_code)]
 mod thrift;
 pub(crate) mod transport;
 mod uploader;
 
+#[cfg(feature = "remote_sampler")]
+pub use sampler::{RemoteSampler, RemoteSamplerBuilder};
+
 use self::thrift::jaeger;
+use futures::future::BoxFuture;
 use opentelemetry::{api, exporter::trace, sdk};
 use std::sync::{Arc, Mutex};
 use std::{
@@ -169,6 +202,24 @@ impl trace::SpanExporter for Exporter {
     fn shutdown(&self) {}
 }
 
+impl trace::AsyncSpanExporter for Exporter {
+    /// Export spans to Jaeger without blocking on collector requests
+    fn export(&self, batch: Vec<Arc<trace::SpanData>>) -> BoxFuture<'static, trace::ExportResult> {
+        match self.uploader.lock() {
+            Ok(mut uploader) => {
+                let jaeger_spans = batch.into_iter().map(Into::into).collect();
+                uploader.upload_async(jaeger::Batch::new(self.process.clone(), jaeger_spans))
+            }
+            Err(_) => Box::pin(futures::future::ready(
+                trace::ExportResult::FailedNotRetryable,
+            )),
+        }
+    }
+
+    /// Ignored for now.
+    fn shutdown(&self) {}
+}
+
 /// Jaeger exporter builder
 #[derive(Debug)]
 pub struct Builder<T: net::ToSocketAddrs> {
@@ -272,7 +323,7 @@ impl<T: net::ToSocketAddrs> Builder<T> {
             let agent = agent::AgentSyncClientUDP::new(self.agent_endpoint.unwrap(), None)?;
             Ok((self.process, uploader::BatchUploader::Agent(agent)))
         } else if self.collector_endpoint.is_some() {
-            let collector = collector::CollectorSyncClientHttp::new(
+            let collector = collector::CollectorHttpClient::new(
                 self.collector_endpoint.unwrap(),
                 self.collector_username,
                 self.collector_password,
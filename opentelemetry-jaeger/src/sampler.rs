@@ -0,0 +1,518 @@
+//! # Jaeger Remote Sampler
+//!
+//! Polls the sampling strategy of a service from the sampling endpoint of a
+//! Jaeger agent, and samples spans with it.
+use opentelemetry::{api, sdk};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::{mpsc, Arc, Mutex, RwLock};
+use std::thread;
+use std::time::Duration;
+
+/// Default sampling endpoint of the Jaeger agent.
+static DEFAULT_SAMPLING_ENDPOINT: &str = "http://localhost:5778/sampling";
+/// Default interval between two polls of the sampling strategy.
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(60);
+/// Default sampling probability until a strategy is polled, as in Jaeger clients.
+const DEFAULT_SAMPLING_PROBABILITY: f64 = 0.001;
+/// Some arbitrary default timeout to avoid hanging forever
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A sampler applying the sampling strategy of a service, polled from a
+/// Jaeger agent.
+///
+/// Probabilistic, rate limiting and per-operation strategies are supported.
+/// Spans are sampled with the default sampler until a strategy is polled. The
+/// last polled strategy is kept while the sampling endpoint is unreachable or
+/// returns an invalid strategy.
+///
+/// ```no_run
+/// use opentelemetry::sdk;
+/// use std::time::Duration;
+///
+/// let sampler = opentelemetry_jaeger::RemoteSampler::builder("trace-demo")
+///     .with_endpoint("http://localhost:5778/sampling")
+///     .with_poll_interval(Duration::from_secs(30))
+///     .with_default_sampler(sdk::Sampler::Probability(0.01))
+///     .build();
+/// let provider = sdk::Provider::builder()
+///     .with_config(sdk::Config {
+///         default_sampler: Box::new(sampler),
+///         ..Default::default()
+///     })
+///     .build();
+/// ```
+#[derive(Debug)]
+pub struct RemoteSampler {
+    state: Arc<RwLock<State>>,
+    // Disconnected when the sampler is dropped, stopping the poller
+    _shutdown_sender: Mutex<mpsc::Sender<()>>,
+}
+
+impl RemoteSampler {
+    /// Create a new builder for a sampler polling the strategy of
+    /// `service_name`.
+    pub fn builder<S: Into<String>>(service_name: S) -> RemoteSamplerBuilder {
+        RemoteSamplerBuilder {
+            service_name: service_name.into(),
+            endpoint: DEFAULT_SAMPLING_ENDPOINT.to_string(),
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            default_sampler: Box::new(sdk::Sampler::Probability(DEFAULT_SAMPLING_PROBABILITY)),
+        }
+    }
+}
+
+impl api::Sampler for RemoteSampler {
+    fn should_sample(
+        &self,
+        parent_context: Option<&api::SpanContext>,
+        trace_id: api::TraceId,
+        name: &str,
+        span_kind: &api::SpanKind,
+        attributes: &[api::KeyValue],
+        links: &[api::Link],
+    ) -> api::SamplingResult {
+        let sample = |sampler: &dyn api::Sampler| {
+            sampler.should_sample(parent_context, trace_id, name, span_kind, attributes, links)
+        };
+        match self.state.read() {
+            Ok(state) => match &state.strategy {
+                Some((_, strategy)) => match strategy {
+                    Strategy::Sampler(sampler) => sample(sampler),
+                    Strategy::PerOperation {
+                        operations,
+                        default,
+                    } => sample(operations.get(name).unwrap_or(default)),
+                },
+                None => sample(state.default_sampler.as_ref()),
+            },
+            Err(_) => api::SamplingResult {
+                decision: api::SamplingDecision::NotRecord,
+                attributes: Vec::new(),
+            },
+        }
+    }
+}
+
+/// Builder for configuring a `RemoteSampler`.
+#[derive(Debug)]
+pub struct RemoteSamplerBuilder {
+    service_name: String,
+    endpoint: String,
+    poll_interval: Duration,
+    default_sampler: Box<dyn api::Sampler>,
+}
+
+impl RemoteSamplerBuilder {
+    /// Assign the sampling endpoint of the agent. Defaults to
+    /// `http://localhost:5778/sampling`.
+    pub fn with_endpoint<S: Into<String>>(self, endpoint: S) -> Self {
+        RemoteSamplerBuilder {
+            endpoint: endpoint.into(),
+            ..self
+        }
+    }
+
+    /// Assign the interval between two polls of the sampling strategy.
+    /// Defaults to 60 seconds.
+    pub fn with_poll_interval(self, poll_interval: Duration) -> Self {
+        RemoteSamplerBuilder {
+            poll_interval,
+            ..self
+        }
+    }
+
+    /// Assign the sampler used until a strategy is polled. Defaults to a
+    /// probability of 0.001.
+    pub fn with_default_sampler<T: api::Sampler + 'static>(self, default_sampler: T) -> Self {
+        RemoteSamplerBuilder {
+            default_sampler: Box::new(default_sampler),
+            ..self
+        }
+    }
+
+    /// Create the sampler, polling the strategy on a background thread until
+    /// it is dropped.
+    pub fn build(self) -> RemoteSampler {
+        let (shutdown_sender, shutdown_receiver) = mpsc::channel::<()>();
+        let state = Arc::new(RwLock::new(State {
+            strategy: None,
+            default_sampler: self.default_sampler,
+        }));
+
+        let poller_state = state.clone();
+        let RemoteSamplerBuilder {
+            service_name,
+            endpoint,
+            poll_interval,
+            ..
+        } = self;
+        let _ = thread::Builder::new()
+            .name("opentelemetry-jaeger-sampling".to_string())
+            .spawn(move || {
+                // Build the blocking client on this thread, outside of any async runtime
+                let client = match reqwest::blocking::Client::builder()
+                    .timeout(REQUEST_TIMEOUT)
+                    .build()
+                {
+                    Ok(client) => client,
+                    Err(_) => return,
+                };
+                loop {
+                    let response = poll(&client, &endpoint, &service_name);
+                    if let Ok(mut state) = poller_state.write() {
+                        state.update(response);
+                    }
+                    match shutdown_receiver.recv_timeout(poll_interval) {
+                        Err(mpsc::RecvTimeoutError::Timeout) => continue,
+                        // The sampler is gone
+                        _ => break,
+                    }
+                }
+            });
+
+        RemoteSampler {
+            state,
+            _shutdown_sender: Mutex::new(shutdown_sender),
+        }
+    }
+}
+
+/// The polled strategy, with the response it was created from, and the
+/// default sampler.
+#[derive(Debug)]
+struct State {
+    strategy: Option<(SamplingStrategyResponse, Strategy)>,
+    default_sampler: Box<dyn api::Sampler>,
+}
+
+impl State {
+    /// Apply a polled response, keeping the current samplers and their rate
+    /// limits if the strategy did not change, or if the poll failed or
+    /// returned an invalid strategy.
+    fn update(&mut self, response: Option<SamplingStrategyResponse>) {
+        let response = match response {
+            Some(response) => response,
+            None => return,
+        };
+        if let Some((current, _)) = &self.strategy {
+            if *current == response {
+                return;
+            }
+        }
+        if let Some(strategy) = Strategy::from_response(&response) {
+            self.strategy = Some((response, strategy));
+        }
+    }
+}
+
+/// Fetch the sampling strategy of a service, `None` if the endpoint is
+/// unreachable or the response invalid.
+fn poll(
+    client: &reqwest::blocking::Client,
+    endpoint: &str,
+    service_name: &str,
+) -> Option<SamplingStrategyResponse> {
+    let response = client
+        .get(endpoint)
+        .query(&[("service", service_name)])
+        .send()
+        .ok()?;
+    if !response.status().is_success() {
+        return None;
+    }
+
+    serde_json::from_slice(&response.bytes().ok()?).ok()
+}
+
+/// The samplers of a polled strategy.
+#[derive(Debug)]
+enum Strategy {
+    Sampler(sdk::Sampler),
+    PerOperation {
+        operations: HashMap<String, GuaranteedThroughputSampler>,
+        default: GuaranteedThroughputSampler,
+    },
+}
+
+impl Strategy {
+    fn from_response(response: &SamplingStrategyResponse) -> Option<Self> {
+        if let Some(operation_sampling) = &response.operation_sampling {
+            let lower_bound = operation_sampling.default_lower_bound_traces_per_second;
+            let operations = operation_sampling
+                .per_operation_strategies
+                .iter()
+                .map(|strategy| {
+                    let sampler = GuaranteedThroughputSampler::new(
+                        strategy.probabilistic_sampling.sampling_rate,
+                        lower_bound,
+                    );
+                    (strategy.operation.clone(), sampler)
+                })
+                .collect();
+            let default = GuaranteedThroughputSampler::new(
+                operation_sampling.default_sampling_probability,
+                lower_bound,
+            );
+            Some(Strategy::PerOperation {
+                operations,
+                default,
+            })
+        } else if let Some(rate_limiting) = &response.rate_limiting_sampling {
+            Some(Strategy::Sampler(sdk::Sampler::RateLimited(
+                sdk::RateLimitingSampler::new(rate_limiting.max_traces_per_second),
+            )))
+        } else {
+            response
+                .probabilistic_sampling
+                .as_ref()
+                .map(|probabilistic| {
+                    Strategy::Sampler(sdk::Sampler::Probability(probabilistic.sampling_rate))
+                })
+        }
+    }
+}
+
+/// Samples an operation with a probability, and at least a minimum number of
+/// traces per second.
+#[derive(Debug)]
+struct GuaranteedThroughputSampler {
+    probability: sdk::Sampler,
+    lower_bound: sdk::RateLimitingSampler,
+}
+
+impl GuaranteedThroughputSampler {
+    fn new(sampling_rate: f64, lower_bound_traces_per_second: f64) -> Self {
+        GuaranteedThroughputSampler {
+            probability: sdk::Sampler::Probability(sampling_rate),
+            lower_bound: sdk::RateLimitingSampler::new(lower_bound_traces_per_second),
+        }
+    }
+}
+
+impl api::Sampler for GuaranteedThroughputSampler {
+    fn should_sample(
+        &self,
+        parent_context: Option<&api::SpanContext>,
+        trace_id: api::TraceId,
+        name: &str,
+        span_kind: &api::SpanKind,
+        attributes: &[api::KeyValue],
+        links: &[api::Link],
+    ) -> api::SamplingResult {
+        let result = self.probability.should_sample(
+            parent_context,
+            trace_id,
+            name,
+            span_kind,
+            attributes,
+            links,
+        );
+        if result.decision == api::SamplingDecision::RecordAndSampled {
+            result
+        } else {
+            self.lower_bound.should_sample(
+                parent_context,
+                trace_id,
+                name,
+                span_kind,
+                attributes,
+                links,
+            )
+        }
+    }
+}
+
+/// The sampling strategy served by the agent, as defined in [sampling.thrift].
+///
+/// [sampling.thrift]: https://github.com/jaegertracing/jaeger-idl/blob/master/thrift/sampling.thrift
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct SamplingStrategyResponse {
+    probabilistic_sampling: Option<ProbabilisticSamplingStrategy>,
+    rate_limiting_sampling: Option<RateLimitingSamplingStrategy>,
+    operation_sampling: Option<PerOperationSamplingStrategies>,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct ProbabilisticSamplingStrategy {
+    sampling_rate: f64,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct RateLimitingSamplingStrategy {
+    max_traces_per_second: f64,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct OperationSamplingStrategy {
+    operation: String,
+    probabilistic_sampling: ProbabilisticSamplingStrategy,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct PerOperationSamplingStrategies {
+    default_sampling_probability: f64,
+    #[serde(default)]
+    default_lower_bound_traces_per_second: f64,
+    #[serde(default)]
+    per_operation_strategies: Vec<OperationSamplingStrategy>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    fn is_sampled(sampler: &dyn api::Sampler, name: &str) -> bool {
+        sampler
+            .should_sample(
+                None,
+                api::TraceId::from_u128(1),
+                name,
+                &api::SpanKind::Internal,
+                &[],
+                &[],
+            )
+            .decision
+            == api::SamplingDecision::RecordAndSampled
+    }
+
+    fn state(response: &str) -> State {
+        let mut state = State {
+            strategy: None,
+            default_sampler: Box::new(sdk::Sampler::Never),
+        };
+        state.update(serde_json::from_str(response).ok());
+        state
+    }
+
+    /// Serve `body` to the first `requests` requests, then stop listening,
+    /// returning the endpoint.
+    fn stub_server(body: &'static str, requests: usize) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}/sampling", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            for stream in listener.incoming().take(requests) {
+                let mut stream = stream.unwrap();
+                let mut request = [0; 1024];
+                let _ = stream.read(&mut request);
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+            }
+        });
+
+        endpoint
+    }
+
+    #[test]
+    fn parses_strategies() {
+        let probabilistic = state(
+            r#"{"strategyType":"PROBABILISTIC","probabilisticSampling":{"samplingRate":1.0}}"#,
+        );
+        match probabilistic.strategy {
+            Some((_, Strategy::Sampler(sdk::Sampler::Probability(rate)))) => {
+                assert_eq!(rate, 1.0)
+            }
+            other => panic!("unexpected strategy {:?}", other),
+        }
+
+        let rate_limiting = state(
+            r#"{"strategyType":"RATE_LIMITING","rateLimitingSampling":{"maxTracesPerSecond":2}}"#,
+        );
+        match rate_limiting.strategy {
+            Some((_, Strategy::Sampler(sdk::Sampler::RateLimited(_)))) => {}
+            other => panic!("unexpected strategy {:?}", other),
+        }
+
+        let per_operation = state(
+            r#"{
+                "strategyType": "PROBABILISTIC",
+                "operationSampling": {
+                    "defaultSamplingProbability": 0.0,
+                    "defaultLowerBoundTracesPerSecond": 0.0,
+                    "perOperationStrategies": [
+                        {"operation": "/checkout", "probabilisticSampling": {"samplingRate": 1.0}}
+                    ]
+                }
+            }"#,
+        );
+        match &per_operation.strategy {
+            Some((_, Strategy::PerOperation { operations, .. })) => {
+                assert!(is_sampled(&operations["/checkout"], "/checkout"))
+            }
+            other => panic!("unexpected strategy {:?}", other),
+        }
+
+        let mut invalid = state(r#"{"unknown": true}"#);
+        assert!(invalid.strategy.is_none());
+
+        // Failed polls and invalid strategies keep the current strategy
+        let mut probabilistic = probabilistic;
+        probabilistic.update(None);
+        probabilistic.update(serde_json::from_str(r#"{"unknown": true}"#).ok());
+        assert!(probabilistic.strategy.is_some());
+        invalid.update(None);
+        assert!(invalid.strategy.is_none());
+    }
+
+    #[test]
+    fn guaranteed_throughput() {
+        let sampler = GuaranteedThroughputSampler::new(0.0, 2.0);
+        let sampled = (0..10).filter(|_| is_sampled(&sampler, "op")).count();
+        assert_eq!(sampled, 2);
+    }
+
+    #[test]
+    fn applies_polled_strategy() {
+        let endpoint = stub_server(
+            r#"{"operationSampling":{"defaultSamplingProbability":0.0,"perOperationStrategies":[{"operation":"/checkout","probabilisticSampling":{"samplingRate":1.0}}]}}"#,
+            usize::MAX,
+        );
+        let sampler = RemoteSampler::builder("test")
+            .with_endpoint(endpoint)
+            .with_default_sampler(sdk::Sampler::Always)
+            .build();
+
+        for _ in 0..100 {
+            if !is_sampled(&sampler, "/healthz") {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(!is_sampled(&sampler, "/healthz"));
+        assert!(is_sampled(&sampler, "/checkout"));
+    }
+
+    #[test]
+    fn keeps_strategy_when_endpoint_goes_away() {
+        // The server stops listening after the first poll
+        let endpoint = stub_server(r#"{"probabilisticSampling":{"samplingRate":0.0}}"#, 1);
+        let sampler = RemoteSampler::builder("test")
+            .with_endpoint(endpoint)
+            .with_poll_interval(Duration::from_millis(10))
+            .with_default_sampler(sdk::Sampler::Always)
+            .build();
+
+        for _ in 0..100 {
+            if !is_sampled(&sampler, "/healthz") {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(!is_sampled(&sampler, "/healthz"));
+
+        // Later polls fail, and spans are still sampled with the strategy
+        thread::sleep(Duration::from_millis(100));
+        assert!(!is_sampled(&sampler, "/healthz"));
+    }
+}
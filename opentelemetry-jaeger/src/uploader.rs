@@ -1,7 +1,8 @@
 //! # Jaeger Span Uploader
-use crate::{agent, jaeger, thrift::agent::TAgentSyncClient};
 #[cfg(feature = "collector_client")]
-use crate::{collector, thrift::jaeger::TCollectorSyncClient};
+use crate::collector;
+use crate::{agent, jaeger, thrift::agent::TAgentSyncClient};
+use futures::future::BoxFuture;
 use opentelemetry::exporter::trace;
 
 /// Uploads a batch of spans to Jaeger
@@ -9,9 +10,9 @@ use opentelemetry::exporter::trace;
 pub(crate) enum BatchUploader {
     /// Agent sync client
     Agent(agent::AgentSyncClientUDP),
-    /// Collector sync client
+    /// Collector HTTP client
     #[cfg(feature = "collector_client")]
-    Collector(Box<collector::CollectorSyncClientHttp>),
+    Collector(Box<collector::CollectorHttpClient>),
 }
 
 impl BatchUploader {
@@ -24,11 +25,20 @@ impl BatchUploader {
                 Err(_) => trace::ExportResult::FailedNotRetryable,
             },
             #[cfg(feature = "collector_client")]
-            BatchUploader::Collector(collector) => match collector.submit_batches(vec![batch]) {
-                Ok(_) => trace::ExportResult::Success,
-                // TODO determine if the error is retryable
-                Err(_) => trace::ExportResult::FailedNotRetryable,
-            },
+            BatchUploader::Collector(collector) => collector.submit_batch(batch),
+        }
+    }
+
+    /// Emit a jaeger batch for the given uploader without blocking on HTTP
+    /// requests. Agent batches are sent over UDP and complete immediately.
+    pub(crate) fn upload_async(
+        &mut self,
+        batch: jaeger::Batch,
+    ) -> BoxFuture<'static, trace::ExportResult> {
+        match self {
+            BatchUploader::Agent(_) => Box::pin(futures::future::ready(self.upload(batch))),
+            #[cfg(feature = "collector_client")]
+            BatchUploader::Collector(collector) => collector.submit_batch_async(batch),
         }
     }
 }
@@ -0,0 +1,7 @@
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    tonic_build::configure().compile(
+        &["proto/opentelemetry/proto/collector/trace/v1/trace_service.proto"],
+        &["proto"],
+    )?;
+    Ok(())
+}
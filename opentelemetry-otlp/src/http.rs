@@ -0,0 +1,459 @@
+//! # OTLP/HTTP Exporter
+//!
+//! Sends OTLP export requests to the `/v1/traces` path of an OTLP/HTTP
+//! receiver, encoded either as binary protobuf or as JSON.
+//!
+//! ```rust,no_run
+//! use opentelemetry_otlp::http::{Compression, Encoding, Exporter};
+//!
+//! fn main() -> Result<(), opentelemetry_otlp::Error> {
+//!     let exporter = Exporter::builder()
+//!         .with_endpoint("http://localhost:55681")
+//!         .with_encoding(Encoding::Json)
+//!         .with_compression(Compression::Gzip)
+//!         .init()?;
+//!
+//!     Ok(())
+//! }
+//! ```
+use crate::{json, transform, Error, ExportRuntime, DEFAULT_EXPORT_TIMEOUT};
+use flate2::{write::GzEncoder, Compression as GzLevel};
+use futures::future::BoxFuture;
+use http::header::{HeaderMap, HeaderValue, CONTENT_ENCODING, CONTENT_TYPE};
+use opentelemetry::exporter::trace;
+use prost::Message;
+use std::io::Write;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Default OTLP/HTTP receiver endpoint if none is provided.
+static DEFAULT_COLLECTOR_ENDPOINT: &str = "http://localhost:55681";
+/// Path that trace export requests are sent to.
+static TRACES_PATH: &str = "/v1/traces";
+
+/// Encoding of the export request body.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Encoding {
+    /// Binary protobuf, sent as `application/x-protobuf`.
+    Protobuf,
+    /// Protobuf JSON mapping, sent as `application/json`.
+    Json,
+}
+
+impl Encoding {
+    fn content_type(self) -> &'static str {
+        match self {
+            Encoding::Protobuf => "application/x-protobuf",
+            Encoding::Json => "application/json",
+        }
+    }
+}
+
+/// Compression applied to the export request body.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Compression {
+    /// Gzip compression, sent with `Content-Encoding: gzip`.
+    Gzip,
+}
+
+/// OTLP/HTTP span exporter
+#[derive(Debug)]
+pub struct Exporter {
+    client: reqwest::Client,
+    endpoint: String,
+    headers: HeaderMap,
+    encoding: Encoding,
+    compression: Option<Compression>,
+    timeout: Duration,
+    runtime: ExportRuntime,
+}
+
+impl Exporter {
+    /// Create a new exporter builder.
+    pub fn builder() -> Builder {
+        Builder::default()
+    }
+
+    /// Encode and, if configured, compress a batch of spans.
+    fn body(&self, batch: Vec<Arc<trace::SpanData>>) -> std::io::Result<Vec<u8>> {
+        let request = transform::export_request(batch);
+        let body = match self.encoding {
+            Encoding::Protobuf => {
+                let mut buf = Vec::with_capacity(request.encoded_len());
+                request
+                    .encode(&mut buf)
+                    .map_err(|_| std::io::Error::from(std::io::ErrorKind::InvalidData))?;
+                buf
+            }
+            Encoding::Json => json::encode(&request),
+        };
+
+        match self.compression {
+            Some(Compression::Gzip) => {
+                let mut encoder = GzEncoder::new(Vec::new(), GzLevel::default());
+                encoder.write_all(&body)?;
+                encoder.finish()
+            }
+            None => Ok(body),
+        }
+    }
+
+    /// Spawn an export request on the exporter's runtime, or return `None`
+    /// once the exporter is shut down.
+    fn spawn_export(
+        &self,
+        batch: Vec<Arc<trace::SpanData>>,
+    ) -> Option<tokio::task::JoinHandle<trace::ExportResult>> {
+        let body = self.body(batch);
+
+        let mut headers = self.headers.clone();
+        headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static(self.encoding.content_type()),
+        );
+        if let Some(Compression::Gzip) = self.compression {
+            headers.insert(CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+        }
+
+        let request = self
+            .client
+            .post(&self.endpoint)
+            .headers(headers)
+            .timeout(self.timeout);
+
+        // Like the gRPC exporter, requests are driven by the exporter's own
+        // runtime so that export can be called from any thread.
+        self.runtime.spawn(async move {
+            let body = match body {
+                Ok(body) => body,
+                Err(_) => return trace::ExportResult::FailedNotRetryable,
+            };
+            match request.body(body).send().await {
+                Ok(response) => export_result(response.status()),
+                Err(_) => trace::ExportResult::FailedRetryable,
+            }
+        })
+    }
+}
+
+impl trace::SpanExporter for Exporter {
+    /// Export spans to the OTLP/HTTP receiver.
+    fn export(&self, batch: Vec<Arc<trace::SpanData>>) -> trace::ExportResult {
+        match self.spawn_export(batch) {
+            Some(handle) => futures::executor::block_on(handle)
+                .unwrap_or(trace::ExportResult::FailedNotRetryable),
+            None => trace::ExportResult::FailedNotRetryable,
+        }
+    }
+
+    /// Stops the exporter's runtime. Later exports fail.
+    fn shutdown(&self) {
+        self.runtime.shutdown();
+    }
+}
+
+impl trace::AsyncSpanExporter for Exporter {
+    /// Export spans to the OTLP/HTTP receiver without blocking the calling
+    /// thread.
+    fn export(&self, batch: Vec<Arc<trace::SpanData>>) -> BoxFuture<'static, trace::ExportResult> {
+        let handle = self.spawn_export(batch);
+        Box::pin(async move {
+            match handle {
+                Some(handle) => handle
+                    .await
+                    .unwrap_or(trace::ExportResult::FailedNotRetryable),
+                None => trace::ExportResult::FailedNotRetryable,
+            }
+        })
+    }
+
+    /// Stops the exporter's runtime. Later exports fail.
+    fn shutdown(&self) {
+        self.runtime.shutdown();
+    }
+}
+
+/// Maps HTTP response statuses to export results, retrying only the statuses
+/// that the OTLP specification lists as transient.
+fn export_result(status: http::StatusCode) -> trace::ExportResult {
+    match status {
+        status if status.is_success() => trace::ExportResult::Success,
+        http::StatusCode::TOO_MANY_REQUESTS
+        | http::StatusCode::BAD_GATEWAY
+        | http::StatusCode::SERVICE_UNAVAILABLE
+        | http::StatusCode::GATEWAY_TIMEOUT => trace::ExportResult::FailedRetryable,
+        _ => trace::ExportResult::FailedNotRetryable,
+    }
+}
+
+/// OTLP/HTTP exporter builder
+#[derive(Debug)]
+pub struct Builder {
+    endpoint: String,
+    headers: HeaderMap,
+    encoding: Encoding,
+    compression: Option<Compression>,
+    timeout: Duration,
+}
+
+impl Default for Builder {
+    /// Return the default Exporter Builder.
+    fn default() -> Self {
+        Builder {
+            endpoint: DEFAULT_COLLECTOR_ENDPOINT.to_string(),
+            headers: HeaderMap::new(),
+            encoding: Encoding::Protobuf,
+            compression: None,
+            timeout: DEFAULT_EXPORT_TIMEOUT,
+        }
+    }
+}
+
+impl Builder {
+    /// Assign the OTLP/HTTP receiver base endpoint, e.g.
+    /// `http://localhost:55681`. Spans are sent to its `/v1/traces` path.
+    pub fn with_endpoint<S: Into<String>>(self, endpoint: S) -> Self {
+        Builder {
+            endpoint: endpoint.into(),
+            ..self
+        }
+    }
+
+    /// Assign the HTTP headers sent with every export request.
+    pub fn with_headers(self, headers: HeaderMap) -> Self {
+        Builder { headers, ..self }
+    }
+
+    /// Assign the request body encoding, defaults to `Encoding::Protobuf`.
+    pub fn with_encoding(self, encoding: Encoding) -> Self {
+        Builder { encoding, ..self }
+    }
+
+    /// Assign the request body compression, defaults to no compression.
+    pub fn with_compression(self, compression: Compression) -> Self {
+        Builder {
+            compression: Some(compression),
+            ..self
+        }
+    }
+
+    /// Assign the maximum duration of a single export.
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        Builder { timeout, ..self }
+    }
+
+    /// Create a new exporter from the builder.
+    pub fn init(self) -> Result<Exporter, Error> {
+        let endpoint = format!("{}{}", self.endpoint.trim_end_matches('/'), TRACES_PATH);
+        endpoint.parse::<http::Uri>()?;
+        let client = reqwest::Client::builder().build()?;
+        let runtime = ExportRuntime::new("opentelemetry-otlp-http")?;
+
+        Ok(Exporter {
+            client,
+            endpoint,
+            headers: self.headers,
+            encoding: self.encoding,
+            compression: self.compression,
+            timeout: self.timeout,
+            runtime,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::collector::trace::v1::ExportTraceServiceRequest;
+    use crate::testing::{service_resource, span_data};
+    use flate2::read::GzDecoder;
+    use opentelemetry::exporter::trace::SpanExporter;
+    use std::io::{BufRead, BufReader, Read};
+    use std::net::TcpListener;
+    use std::sync::Mutex;
+
+    const SPAN_ID: u64 = 0x00f0_67aa_0ba9_02b7;
+
+    #[derive(Debug)]
+    struct RecordedRequest {
+        path: String,
+        headers: Vec<(String, String)>,
+        body: Vec<u8>,
+    }
+
+    impl RecordedRequest {
+        fn header(&self, name: &str) -> Option<&str> {
+            self.headers
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.as_str())
+        }
+    }
+
+    /// Starts a stub HTTP receiver replying to every request with `status`,
+    /// returning its endpoint and the requests it has received.
+    fn start_receiver(status: u16) -> (String, Arc<Mutex<Vec<RecordedRequest>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let path = request_line.split(' ').nth(1).unwrap().to_string();
+
+                let mut headers = Vec::new();
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    let line = line.trim_end();
+                    if line.is_empty() {
+                        break;
+                    }
+                    let mut parts = line.splitn(2, ':');
+                    let key = parts.next().unwrap().trim().to_string();
+                    let value = parts.next().unwrap().trim().to_string();
+                    headers.push((key, value));
+                }
+
+                let len = headers
+                    .iter()
+                    .find(|(key, _)| key.eq_ignore_ascii_case("content-length"))
+                    .map(|(_, value)| value.parse().unwrap())
+                    .unwrap_or(0);
+                let mut body = vec![0; len];
+                reader.read_exact(&mut body).unwrap();
+                recorded.lock().unwrap().push(RecordedRequest {
+                    path,
+                    headers,
+                    body,
+                });
+
+                write!(
+                    stream,
+                    "HTTP/1.1 {} Stub\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    status
+                )
+                .unwrap();
+            }
+        });
+
+        (endpoint, requests)
+    }
+
+    #[test]
+    fn exports_gzipped_protobuf() {
+        let (endpoint, requests) = start_receiver(200);
+        let mut headers = HeaderMap::new();
+        headers.insert("api-key", HeaderValue::from_static("secret"));
+        let exporter = Exporter::builder()
+            .with_endpoint(endpoint)
+            .with_headers(headers)
+            .with_compression(Compression::Gzip)
+            .init()
+            .unwrap();
+
+        assert_eq!(
+            exporter.export(vec![span_data(service_resource(), SPAN_ID)]),
+            trace::ExportResult::Success
+        );
+
+        let requests = requests.lock().unwrap();
+        let request = &requests[0];
+        assert_eq!(request.path, "/v1/traces");
+        assert_eq!(request.header("api-key"), Some("secret"));
+        assert_eq!(
+            request.header("content-type"),
+            Some("application/x-protobuf")
+        );
+        assert_eq!(request.header("content-encoding"), Some("gzip"));
+
+        let mut body = Vec::new();
+        GzDecoder::new(request.body.as_slice())
+            .read_to_end(&mut body)
+            .unwrap();
+        let decoded = ExportTraceServiceRequest::decode(body.as_slice()).unwrap();
+        let span = &decoded.resource_spans[0].instrumentation_library_spans[0].spans[0];
+        assert_eq!(span.name, "GET /");
+        assert_eq!(span.attributes.len(), 2);
+    }
+
+    #[test]
+    fn exports_json() {
+        let (endpoint, requests) = start_receiver(200);
+        let exporter = Exporter::builder()
+            .with_endpoint(endpoint)
+            .with_encoding(Encoding::Json)
+            .init()
+            .unwrap();
+
+        assert_eq!(
+            exporter.export(vec![span_data(service_resource(), SPAN_ID)]),
+            trace::ExportResult::Success
+        );
+
+        let requests = requests.lock().unwrap();
+        let request = &requests[0];
+        assert_eq!(request.header("content-type"), Some("application/json"));
+        assert_eq!(request.header("content-encoding"), None);
+
+        let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+        let resource_spans = &body["resourceSpans"][0];
+        assert_eq!(
+            resource_spans["resource"]["attributes"][0]["stringValue"],
+            "test"
+        );
+        let span = &resource_spans["instrumentationLibrarySpans"][0]["spans"][0];
+        assert_eq!(span["traceId"], "0af7651916cd43dd8448eb211c80319c");
+        assert_eq!(span["spanId"], "00f067aa0ba902b7");
+        assert_eq!(span["parentSpanId"], "");
+        assert_eq!(span["kind"], 3);
+        let status_code = span["attributes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|attribute| attribute["key"] == "http.status_code")
+            .unwrap();
+        assert_eq!(status_code["intValue"], "200");
+        assert!(status_code.get("stringValue").is_none());
+    }
+
+    #[test]
+    fn maps_receiver_errors() {
+        let (endpoint, _) = start_receiver(503);
+        let exporter = Exporter::builder().with_endpoint(endpoint).init().unwrap();
+        assert_eq!(
+            exporter.export(vec![span_data(service_resource(), SPAN_ID)]),
+            trace::ExportResult::FailedRetryable
+        );
+
+        let (endpoint, _) = start_receiver(400);
+        let exporter = Exporter::builder().with_endpoint(endpoint).init().unwrap();
+        assert_eq!(
+            exporter.export(vec![span_data(service_resource(), SPAN_ID)]),
+            trace::ExportResult::FailedNotRetryable
+        );
+
+        assert_eq!(
+            export_result(http::StatusCode::TOO_MANY_REQUESTS),
+            trace::ExportResult::FailedRetryable
+        );
+    }
+
+    #[test]
+    fn fails_exports_after_shutdown() {
+        let (endpoint, requests) = start_receiver(200);
+        let exporter = Exporter::builder().with_endpoint(endpoint).init().unwrap();
+        exporter.shutdown();
+
+        assert_eq!(
+            exporter.export(vec![span_data(service_resource(), SPAN_ID)]),
+            trace::ExportResult::FailedNotRetryable
+        );
+        assert!(requests.lock().unwrap().is_empty());
+    }
+}
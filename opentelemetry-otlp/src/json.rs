@@ -0,0 +1,135 @@
+//! # OTLP JSON Encoding
+//!
+//! Encodes OTLP protobuf messages using the [proto3 JSON mapping] with the
+//! OTLP specific exceptions: field names are `lowerCamelCase`, enums are
+//! encoded as integers and trace / span ids are hex encoded rather than
+//! base64 encoded.
+//!
+//! [proto3 JSON mapping]: https://developers.google.com/protocol-buffers/docs/proto3#json
+use crate::proto::{collector, common, resource, trace as otlp};
+use serde_json::{json, Value};
+
+/// Encode an export request as a JSON document.
+pub(crate) fn encode(request: &collector::trace::v1::ExportTraceServiceRequest) -> Vec<u8> {
+    let resource_spans = request
+        .resource_spans
+        .iter()
+        .map(resource_spans)
+        .collect::<Vec<_>>();
+
+    json!({ "resourceSpans": resource_spans })
+        .to_string()
+        .into_bytes()
+}
+
+fn resource_spans(resource_spans: &otlp::v1::ResourceSpans) -> Value {
+    let mut value = json!({
+        "instrumentationLibrarySpans": resource_spans
+            .instrumentation_library_spans
+            .iter()
+            .map(instrumentation_library_spans)
+            .collect::<Vec<_>>(),
+    });
+    if let Some(resource) = resource_spans.resource.as_ref() {
+        value["resource"] = self::resource(resource);
+    }
+
+    value
+}
+
+fn resource(resource: &resource::v1::Resource) -> Value {
+    json!({
+        "attributes": attributes(&resource.attributes),
+        "droppedAttributesCount": resource.dropped_attributes_count,
+    })
+}
+
+fn instrumentation_library_spans(library_spans: &otlp::v1::InstrumentationLibrarySpans) -> Value {
+    let mut value = json!({
+        "spans": library_spans.spans.iter().map(span).collect::<Vec<_>>(),
+    });
+    if let Some(library) = library_spans.instrumentation_library.as_ref() {
+        value["instrumentationLibrary"] = json!({
+            "name": library.name,
+            "version": library.version,
+        });
+    }
+
+    value
+}
+
+fn span(span: &otlp::v1::Span) -> Value {
+    let mut value = json!({
+        "traceId": hex(&span.trace_id),
+        "spanId": hex(&span.span_id),
+        "traceState": span.trace_state,
+        "parentSpanId": hex(&span.parent_span_id),
+        "name": span.name,
+        "kind": span.kind,
+        "startTimeUnixNano": span.start_time_unix_nano.to_string(),
+        "endTimeUnixNano": span.end_time_unix_nano.to_string(),
+        "attributes": attributes(&span.attributes),
+        "droppedAttributesCount": span.dropped_attributes_count,
+        "events": span.events.iter().map(event).collect::<Vec<_>>(),
+        "droppedEventsCount": span.dropped_events_count,
+        "links": span.links.iter().map(link).collect::<Vec<_>>(),
+        "droppedLinksCount": span.dropped_links_count,
+    });
+    if let Some(status) = span.status.as_ref() {
+        value["status"] = json!({
+            "code": status.code,
+            "message": status.message,
+        });
+    }
+
+    value
+}
+
+fn event(event: &otlp::v1::span::Event) -> Value {
+    json!({
+        "timeUnixNano": event.time_unix_nano.to_string(),
+        "name": event.name,
+        "attributes": attributes(&event.attributes),
+        "droppedAttributesCount": event.dropped_attributes_count,
+    })
+}
+
+fn link(link: &otlp::v1::span::Link) -> Value {
+    json!({
+        "traceId": hex(&link.trace_id),
+        "spanId": hex(&link.span_id),
+        "traceState": link.trace_state,
+        "attributes": attributes(&link.attributes),
+        "droppedAttributesCount": link.dropped_attributes_count,
+    })
+}
+
+fn attributes(attributes: &[common::v1::AttributeKeyValue]) -> Value {
+    use common::v1::attribute_key_value::ValueType;
+
+    attributes
+        .iter()
+        .map(|attribute| {
+            let mut value = json!({
+                "key": attribute.key,
+                "type": attribute.r#type,
+            });
+            match ValueType::from_i32(attribute.r#type) {
+                Some(ValueType::Int) => {
+                    value["intValue"] = Value::String(attribute.int_value.to_string())
+                }
+                Some(ValueType::Double) => value["doubleValue"] = json!(attribute.double_value),
+                Some(ValueType::Bool) => value["boolValue"] = json!(attribute.bool_value),
+                Some(ValueType::String) | None => {
+                    value["stringValue"] = json!(attribute.string_value)
+                }
+            }
+
+            value
+        })
+        .collect()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
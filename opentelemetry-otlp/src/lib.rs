@@ -0,0 +1,500 @@
+//! # OpenTelemetry OTLP Exporter
+//!
+//! Collects OpenTelemetry spans and reports them to an [OpenTelemetry
+//! Collector] (or any other receiver) using the native [OTLP] protocol over
+//! gRPC, or over HTTP with the `http_exporter` feature enabled.
+//!
+//! ### OTLP Exporter Example
+//!
+//! This example expects a collector with the OTLP receiver listening on
+//! `localhost:55680`.
+//!
+//! ```rust,no_run
+//! use opentelemetry::{global, sdk};
+//! use std::time::Duration;
+//!
+//! fn init_tracer() -> Result<(), opentelemetry_otlp::Error> {
+//!     let exporter = opentelemetry_otlp::Exporter::builder()
+//!         .with_endpoint("http://localhost:55680")
+//!         .with_timeout(Duration::from_secs(5))
+//!         .init()?;
+//!     let provider = sdk::Provider::builder()
+//!         .with_simple_exporter(exporter)
+//!         .with_config(sdk::Config {
+//!             default_sampler: Box::new(sdk::Sampler::Always),
+//!             ..Default::default()
+//!         })
+//!         .build();
+//!     global::set_provider(provider);
+//!
+//!     Ok(())
+//! }
+//!
+//! fn main() -> Result<(), opentelemetry_otlp::Error> {
+//!     init_tracer()?;
+//!     // Use configured tracer
+//!     Ok(())
+//! }
+//! ```
+//!
+//! ### OTLP/HTTP Exporter Example
+//!
+//! With the `http_exporter` feature enabled, spans can also be posted to the
+//! `/v1/traces` path of an OTLP/HTTP receiver as protobuf or JSON.
+//!
+//! ```rust,ignore
+//! let exporter = opentelemetry_otlp::http::Exporter::builder()
+//!     .with_endpoint("http://localhost:55681")
+//!     .with_encoding(opentelemetry_otlp::http::Encoding::Json)
+//!     .init()?;
+//! ```
+//!
+//! [OpenTelemetry Collector]: https://github.com/open-telemetry/opentelemetry-collector
+//! [OTLP]: https://github.com/open-telemetry/opentelemetry-proto
+#![deny(missing_docs, unreachable_pub, missing_debug_implementations)]
+#![cfg_attr(test, deny(warnings))]
+
+#[allow(clippy::all, unreachable_pub, dead_code)]
+mod proto;
+#[cfg(test)]
+mod testing;
+mod transform;
+
+#[cfg(feature = "http_exporter")]
+pub mod http;
+#[cfg(feature = "http_exporter")]
+mod json;
+
+use self::proto::collector::trace::v1::trace_service_client::TraceServiceClient;
+use futures::{future::BoxFuture, lock::Mutex};
+use opentelemetry::exporter::trace;
+use std::fmt;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::Duration;
+use tonic::{
+    metadata::MetadataMap,
+    transport::{Channel, Endpoint},
+};
+
+/// Default OTLP receiver endpoint if none is provided.
+static DEFAULT_COLLECTOR_ENDPOINT: &str = "http://localhost:55680";
+/// Default maximum duration of a single export, including connecting.
+const DEFAULT_EXPORT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// OTLP span exporter
+#[derive(Debug)]
+pub struct Exporter {
+    endpoint: Endpoint,
+    metadata: MetadataMap,
+    timeout: Duration,
+    client: Arc<Mutex<Option<TraceServiceClient<Channel>>>>,
+    runtime: ExportRuntime,
+}
+
+impl Exporter {
+    /// Create a new exporter builder.
+    pub fn builder() -> Builder {
+        Builder::default()
+    }
+
+    /// Spawn an export request on the exporter's runtime, or `None` if the
+    /// exporter is shut down.
+    fn spawn_export(
+        &self,
+        batch: Vec<Arc<trace::SpanData>>,
+    ) -> Option<tokio::task::JoinHandle<trace::ExportResult>> {
+        let mut request = tonic::Request::new(transform::export_request(batch));
+        *request.metadata_mut() = self.metadata.clone();
+
+        let endpoint = self.endpoint.clone();
+        let client = self.client.clone();
+        let timeout = self.timeout;
+
+        // The request is driven by the exporter's own runtime so that export
+        // can be called from threads that are not running a tokio reactor.
+        self.runtime.spawn(async move {
+            let send = async move {
+                let mut client = client.lock().await;
+                if client.is_none() {
+                    match endpoint.connect().await {
+                        Ok(channel) => *client = Some(TraceServiceClient::new(channel)),
+                        Err(_) => return trace::ExportResult::FailedRetryable,
+                    }
+                }
+
+                match client.as_mut() {
+                    Some(client) => match client.export(request).await {
+                        Ok(_) => trace::ExportResult::Success,
+                        Err(status) => export_result(status.code()),
+                    },
+                    None => trace::ExportResult::FailedRetryable,
+                }
+            };
+
+            tokio::time::timeout(timeout, send)
+                .await
+                .unwrap_or(trace::ExportResult::FailedRetryable)
+        })
+    }
+
+    /// Drop the connection and stop the runtime, failing later exports.
+    fn shutdown(&self) {
+        if self.runtime.shutdown() {
+            futures::executor::block_on(self.client.lock()).take();
+        }
+    }
+}
+
+/// The runtime driving the export requests of an exporter, so that exports
+/// can be called from threads that are not running a tokio reactor.
+#[derive(Debug)]
+pub(crate) struct ExportRuntime {
+    handle: tokio::runtime::Handle,
+    // Taken and stopped on shutdown
+    runtime: StdMutex<Option<tokio::runtime::Runtime>>,
+    is_shutdown: AtomicBool,
+}
+
+impl ExportRuntime {
+    pub(crate) fn new(thread_name: &str) -> std::io::Result<Self> {
+        let runtime = tokio::runtime::Builder::new()
+            .threaded_scheduler()
+            .core_threads(1)
+            .thread_name(thread_name)
+            .enable_all()
+            .build()?;
+
+        Ok(ExportRuntime {
+            handle: runtime.handle().clone(),
+            runtime: StdMutex::new(Some(runtime)),
+            is_shutdown: AtomicBool::new(false),
+        })
+    }
+
+    /// Spawn an export request, or return `None` once shut down.
+    pub(crate) fn spawn<F>(&self, export: F) -> Option<tokio::task::JoinHandle<trace::ExportResult>>
+    where
+        F: Future<Output = trace::ExportResult> + Send + 'static,
+    {
+        if self.is_shutdown.load(Ordering::SeqCst) {
+            None
+        } else {
+            Some(self.handle.spawn(export))
+        }
+    }
+
+    /// Stop the runtime without waiting for pending requests. Returns `false`
+    /// if it was already shut down.
+    pub(crate) fn shutdown(&self) -> bool {
+        if self.is_shutdown.swap(true, Ordering::SeqCst) {
+            return false;
+        }
+        if let Some(runtime) = self
+            .runtime
+            .lock()
+            .ok()
+            .and_then(|mut runtime| runtime.take())
+        {
+            runtime.shutdown_background();
+        }
+
+        true
+    }
+}
+
+impl trace::SpanExporter for Exporter {
+    /// Export spans to the OTLP receiver.
+    fn export(&self, batch: Vec<Arc<trace::SpanData>>) -> trace::ExportResult {
+        match self.spawn_export(batch) {
+            Some(handle) => futures::executor::block_on(handle)
+                .unwrap_or(trace::ExportResult::FailedNotRetryable),
+            None => trace::ExportResult::FailedNotRetryable,
+        }
+    }
+
+    /// Drops the connection to the OTLP receiver and stops the exporter's
+    /// runtime. Later exports fail.
+    fn shutdown(&self) {
+        Exporter::shutdown(self)
+    }
+}
+
+impl trace::AsyncSpanExporter for Exporter {
+    /// Export spans to the OTLP receiver without blocking the calling thread.
+    fn export(&self, batch: Vec<Arc<trace::SpanData>>) -> BoxFuture<'static, trace::ExportResult> {
+        let handle = self.spawn_export(batch);
+        Box::pin(async move {
+            match handle {
+                Some(handle) => handle
+                    .await
+                    .unwrap_or(trace::ExportResult::FailedNotRetryable),
+                None => trace::ExportResult::FailedNotRetryable,
+            }
+        })
+    }
+
+    /// Drops the connection to the OTLP receiver and stops the exporter's
+    /// runtime. Later exports fail.
+    fn shutdown(&self) {
+        Exporter::shutdown(self)
+    }
+}
+
+/// Maps gRPC response codes to export results, retrying only the codes that
+/// the OTLP specification lists as transient.
+fn export_result(code: tonic::Code) -> trace::ExportResult {
+    match code {
+        tonic::Code::Ok => trace::ExportResult::Success,
+        tonic::Code::Cancelled
+        | tonic::Code::DeadlineExceeded
+        | tonic::Code::ResourceExhausted
+        | tonic::Code::Aborted
+        | tonic::Code::OutOfRange
+        | tonic::Code::Unavailable
+        | tonic::Code::DataLoss => trace::ExportResult::FailedRetryable,
+        _ => trace::ExportResult::FailedNotRetryable,
+    }
+}
+
+/// OTLP exporter builder
+#[derive(Debug)]
+pub struct Builder {
+    endpoint: String,
+    metadata: MetadataMap,
+    timeout: Duration,
+}
+
+impl Default for Builder {
+    /// Return the default Exporter Builder.
+    fn default() -> Self {
+        Builder {
+            endpoint: DEFAULT_COLLECTOR_ENDPOINT.to_string(),
+            metadata: MetadataMap::new(),
+            timeout: DEFAULT_EXPORT_TIMEOUT,
+        }
+    }
+}
+
+impl Builder {
+    /// Assign the OTLP receiver endpoint, e.g. `http://localhost:55680`.
+    pub fn with_endpoint<S: Into<String>>(self, endpoint: S) -> Self {
+        Builder {
+            endpoint: endpoint.into(),
+            ..self
+        }
+    }
+
+    /// Assign the gRPC metadata sent with every export request. The exporter
+    /// is built on the `tonic` feature of `opentelemetry`, so `MetadataMap`
+    /// is an `api::Carrier` that propagators can inject into.
+    pub fn with_metadata(self, metadata: MetadataMap) -> Self {
+        Builder { metadata, ..self }
+    }
+
+    /// Assign the maximum duration of a single export.
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        Builder { timeout, ..self }
+    }
+
+    /// Create a new exporter from the builder.
+    ///
+    /// The connection to the receiver is established lazily on first export.
+    pub fn init(self) -> Result<Exporter, Error> {
+        let endpoint = Endpoint::from_shared(self.endpoint)?;
+        let runtime = ExportRuntime::new("opentelemetry-otlp")?;
+
+        Ok(Exporter {
+            endpoint,
+            metadata: self.metadata,
+            timeout: self.timeout,
+            client: Arc::new(Mutex::new(None)),
+            runtime,
+        })
+    }
+}
+
+/// Errors that can occur while initializing an `Exporter`.
+#[derive(Debug)]
+pub enum Error {
+    /// The configured endpoint is not a valid URI.
+    InvalidEndpoint(::http::uri::InvalidUri),
+    /// The runtime driving export requests could not be started.
+    Io(std::io::Error),
+    /// The HTTP client could not be created.
+    #[cfg(feature = "http_exporter")]
+    HttpClient(reqwest::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidEndpoint(err) => write!(f, "invalid OTLP endpoint: {}", err),
+            Error::Io(err) => write!(f, "could not start OTLP exporter runtime: {}", err),
+            #[cfg(feature = "http_exporter")]
+            Error::HttpClient(err) => write!(f, "could not create OTLP HTTP client: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::InvalidEndpoint(err) => Some(err),
+            Error::Io(err) => Some(err),
+            #[cfg(feature = "http_exporter")]
+            Error::HttpClient(err) => Some(err),
+        }
+    }
+}
+
+impl From<::http::uri::InvalidUri> for Error {
+    fn from(err: ::http::uri::InvalidUri) -> Self {
+        Error::InvalidEndpoint(err)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+#[cfg(feature = "http_exporter")]
+impl From<reqwest::Error> for Error {
+    fn from(err: reqwest::Error) -> Self {
+        Error::HttpClient(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::collector::trace::v1::{
+        trace_service_server::{TraceService, TraceServiceServer},
+        ExportTraceServiceRequest, ExportTraceServiceResponse,
+    };
+    use crate::testing::{service_resource, span_data};
+    use opentelemetry::{api, exporter::trace::SpanExporter, sdk};
+
+    #[derive(Debug, Default)]
+    struct MockCollector {
+        requests: Arc<StdMutex<Vec<(MetadataMap, ExportTraceServiceRequest)>>>,
+        status: Option<tonic::Code>,
+    }
+
+    #[tonic::async_trait]
+    impl TraceService for MockCollector {
+        async fn export(
+            &self,
+            request: tonic::Request<ExportTraceServiceRequest>,
+        ) -> Result<tonic::Response<ExportTraceServiceResponse>, tonic::Status> {
+            if let Some(code) = self.status {
+                return Err(tonic::Status::new(code, "mock failure"));
+            }
+            let metadata = request.metadata().clone();
+            self.requests
+                .lock()
+                .unwrap()
+                .push((metadata, request.into_inner()));
+
+            Ok(tonic::Response::new(ExportTraceServiceResponse {}))
+        }
+    }
+
+    /// Starts the mock collector on a separate runtime, returning its endpoint.
+    fn start_collector(runtime: &mut tokio::runtime::Runtime, collector: MockCollector) -> String {
+        let mut listener =
+            runtime.block_on(async { tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap() });
+        let addr = listener.local_addr().unwrap();
+        runtime.spawn(async move {
+            tonic::transport::Server::builder()
+                .add_service(TraceServiceServer::new(collector))
+                .serve_with_incoming(listener.incoming())
+                .await
+                .unwrap()
+        });
+
+        format!("http://{}", addr)
+    }
+
+    #[test]
+    fn exports_to_mock_collector() {
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        let collector = MockCollector::default();
+        let requests = collector.requests.clone();
+        let endpoint = start_collector(&mut runtime, collector);
+
+        // Metadata is a carrier through the `tonic` feature of `opentelemetry`
+        let mut metadata = MetadataMap::new();
+        api::Carrier::set(&mut metadata, "api-key", "secret".to_string());
+        let exporter = Exporter::builder()
+            .with_endpoint(endpoint)
+            .with_metadata(metadata)
+            .init()
+            .unwrap();
+
+        let service = service_resource();
+        let other = Arc::new(sdk::Resource::default());
+        let result = exporter.export(vec![
+            span_data(service.clone(), 1),
+            span_data(other, 2),
+            span_data(service, 3),
+        ]);
+        assert_eq!(result, trace::ExportResult::Success);
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 1);
+        let (metadata, request) = &requests[0];
+        assert_eq!(metadata.get("api-key").unwrap(), "secret");
+
+        // Spans are grouped by resource
+        assert_eq!(request.resource_spans.len(), 2);
+        let resource_spans = &request.resource_spans[0];
+        let resource = resource_spans.resource.as_ref().unwrap();
+        assert_eq!(resource.attributes[0].key, "service.name");
+        assert_eq!(resource.attributes[0].string_value, "test");
+        let spans = &resource_spans.instrumentation_library_spans[0].spans;
+        assert_eq!(spans.len(), 2);
+
+        let span = &spans[0];
+        assert_eq!(
+            span.trace_id,
+            0x0af7_6519_16cd_43dd_8448_eb21_1c80_319c_u128
+                .to_be_bytes()
+                .to_vec()
+        );
+        assert_eq!(span.span_id, 1u64.to_be_bytes().to_vec());
+        assert!(span.parent_span_id.is_empty());
+        assert_eq!(span.name, "GET /");
+        assert_eq!(span.attributes.len(), 2);
+        assert_eq!(span.events[0].name, "event");
+        let status = span.status.as_ref().unwrap();
+        assert_eq!(status.code, api::StatusCode::NotFound as i32);
+        assert_eq!(status.message, "not found");
+    }
+
+    #[test]
+    fn maps_collector_errors() {
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        let endpoint = start_collector(
+            &mut runtime,
+            MockCollector {
+                status: Some(tonic::Code::Unavailable),
+                ..Default::default()
+            },
+        );
+        let exporter = Exporter::builder().with_endpoint(endpoint).init().unwrap();
+        let resource = Arc::new(sdk::Resource::default());
+
+        assert_eq!(
+            exporter.export(vec![span_data(resource, 1)]),
+            trace::ExportResult::FailedRetryable
+        );
+        assert_eq!(
+            export_result(tonic::Code::InvalidArgument),
+            trace::ExportResult::FailedNotRetryable
+        );
+    }
+}
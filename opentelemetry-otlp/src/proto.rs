@@ -0,0 +1,32 @@
+//! OTLP protobuf messages and gRPC services generated by `tonic-build`.
+pub(crate) mod opentelemetry {
+    pub(crate) mod proto {
+        pub(crate) mod common {
+            pub(crate) mod v1 {
+                tonic::include_proto!("opentelemetry.proto.common.v1");
+            }
+        }
+
+        pub(crate) mod resource {
+            pub(crate) mod v1 {
+                tonic::include_proto!("opentelemetry.proto.resource.v1");
+            }
+        }
+
+        pub(crate) mod trace {
+            pub(crate) mod v1 {
+                tonic::include_proto!("opentelemetry.proto.trace.v1");
+            }
+        }
+
+        pub(crate) mod collector {
+            pub(crate) mod trace {
+                pub(crate) mod v1 {
+                    tonic::include_proto!("opentelemetry.proto.collector.trace.v1");
+                }
+            }
+        }
+    }
+}
+
+pub(crate) use self::opentelemetry::proto::*;
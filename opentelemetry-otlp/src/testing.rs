@@ -0,0 +1,41 @@
+//! Spans shared by the tests of the exporters.
+use opentelemetry::{api, exporter::trace::SpanData, sdk};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+/// The resource of the `test` service.
+pub(crate) fn service_resource() -> Arc<sdk::Resource> {
+    Arc::new(sdk::Resource::new(vec![api::KeyValue::new(
+        "service.name",
+        "test",
+    )]))
+}
+
+/// A client span of a failed request, with two attributes and an event.
+pub(crate) fn span_data(resource: Arc<sdk::Resource>, span_id: u64) -> Arc<SpanData> {
+    let mut attributes = sdk::EvictedHashMap::new(8);
+    attributes.insert(api::Key::new("http.method").string("GET"));
+    attributes.insert(api::Key::new("http.status_code").u64(200));
+    let mut message_events = sdk::EvictedQueue::new(8);
+    message_events.append_vec(&mut vec![api::Event::with_name("event".to_string())]);
+
+    Arc::new(SpanData {
+        span_context: api::SpanContext::new(
+            api::TraceId::from_u128(0x0af7_6519_16cd_43dd_8448_eb21_1c80_319c),
+            api::SpanId::from_u64(span_id),
+            api::TRACE_FLAG_SAMPLED,
+            false,
+        ),
+        parent_span_id: api::SpanId::invalid(),
+        span_kind: api::SpanKind::Client,
+        name: "GET /".to_string(),
+        start_time: SystemTime::now(),
+        end_time: SystemTime::now(),
+        attributes,
+        message_events,
+        links: sdk::EvictedQueue::new(8),
+        status_code: api::StatusCode::NotFound,
+        status_message: "not found".to_string(),
+        resource,
+    })
+}
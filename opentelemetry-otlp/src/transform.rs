@@ -0,0 +1,176 @@
+//! # OTLP Span Transform
+//!
+//! Converts `SpanData` produced by the SDK into OTLP protobuf messages.
+use crate::proto::{collector, common, resource, trace as otlp};
+use opentelemetry::{api, exporter::trace, sdk};
+use std::convert::TryFrom;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+/// Build an `ExportTraceServiceRequest` from a batch of spans, grouping spans
+/// that share the same `Resource`.
+pub(crate) fn export_request(
+    batch: Vec<Arc<trace::SpanData>>,
+) -> collector::trace::v1::ExportTraceServiceRequest {
+    let mut grouped: Vec<(Arc<sdk::Resource>, Vec<otlp::v1::Span>)> = Vec::new();
+    for span_data in batch {
+        let span = otlp::v1::Span::from(span_data.as_ref());
+        match grouped
+            .iter_mut()
+            .find(|(resource, _)| resource == &span_data.resource)
+        {
+            Some((_, spans)) => spans.push(span),
+            None => grouped.push((span_data.resource.clone(), vec![span])),
+        }
+    }
+
+    collector::trace::v1::ExportTraceServiceRequest {
+        resource_spans: grouped
+            .into_iter()
+            .map(|(resource, spans)| otlp::v1::ResourceSpans {
+                resource: Some(resource.as_ref().into()),
+                instrumentation_library_spans: vec![otlp::v1::InstrumentationLibrarySpans {
+                    instrumentation_library: None,
+                    spans,
+                }],
+            })
+            .collect(),
+    }
+}
+
+impl From<api::KeyValue> for common::v1::AttributeKeyValue {
+    fn from(kv: api::KeyValue) -> Self {
+        use common::v1::attribute_key_value::ValueType;
+
+        let mut attribute = common::v1::AttributeKeyValue {
+            key: kv.key.into(),
+            ..Default::default()
+        };
+        match kv.value {
+            api::Value::Bool(b) => {
+                attribute.r#type = ValueType::Bool as i32;
+                attribute.bool_value = b;
+            }
+            api::Value::I64(i) => {
+                attribute.r#type = ValueType::Int as i32;
+                attribute.int_value = i;
+            }
+            api::Value::U64(u) => match i64::try_from(u) {
+                Ok(i) => {
+                    attribute.r#type = ValueType::Int as i32;
+                    attribute.int_value = i;
+                }
+                // OTLP only has signed integers, keep large values lossless as strings
+                Err(_) => {
+                    attribute.r#type = ValueType::String as i32;
+                    attribute.string_value = u.to_string();
+                }
+            },
+            api::Value::F64(f) => {
+                attribute.r#type = ValueType::Double as i32;
+                attribute.double_value = f;
+            }
+            value @ api::Value::String(_) | value @ api::Value::Bytes(_) => {
+                attribute.r#type = ValueType::String as i32;
+                attribute.string_value = value.into();
+            }
+        }
+
+        attribute
+    }
+}
+
+impl From<&sdk::Resource> for resource::v1::Resource {
+    fn from(resource: &sdk::Resource) -> Self {
+        resource::v1::Resource {
+            attributes: resource
+                .iter()
+                .map(|(k, v)| api::KeyValue::new(k.clone(), v.clone()).into())
+                .collect(),
+            dropped_attributes_count: 0,
+        }
+    }
+}
+
+impl From<&api::Event> for otlp::v1::span::Event {
+    fn from(event: &api::Event) -> Self {
+        otlp::v1::span::Event {
+            time_unix_nano: to_nanos(event.timestamp),
+            name: event.name.clone(),
+            attributes: event.attributes.iter().cloned().map(Into::into).collect(),
+            dropped_attributes_count: 0,
+        }
+    }
+}
+
+impl From<&api::Link> for otlp::v1::span::Link {
+    fn from(link: &api::Link) -> Self {
+        let span_context = link.span_context();
+        otlp::v1::span::Link {
+            trace_id: trace_id_bytes(span_context.trace_id()),
+            span_id: span_id_bytes(span_context.span_id()),
+            trace_state: span_context.trace_state().header(),
+            attributes: link.attributes().iter().cloned().map(Into::into).collect(),
+            dropped_attributes_count: 0,
+        }
+    }
+}
+
+impl From<&trace::SpanData> for otlp::v1::Span {
+    /// Convert spans to OTLP protobuf spans for exporting.
+    fn from(span_data: &trace::SpanData) -> Self {
+        otlp::v1::Span {
+            trace_id: trace_id_bytes(span_data.span_context.trace_id()),
+            span_id: span_id_bytes(span_data.span_context.span_id()),
+            trace_state: span_data.span_context.trace_state().header(),
+            parent_span_id: if span_data.parent_span_id == api::SpanId::invalid() {
+                Vec::new()
+            } else {
+                span_id_bytes(span_data.parent_span_id)
+            },
+            name: span_data.name.clone(),
+            kind: span_kind(&span_data.span_kind) as i32,
+            start_time_unix_nano: to_nanos(span_data.start_time),
+            end_time_unix_nano: to_nanos(span_data.end_time),
+            attributes: span_data
+                .attributes
+                .iter()
+                .map(|(k, v)| api::KeyValue::new(k.clone(), v.clone()).into())
+                .collect(),
+            dropped_attributes_count: span_data.attributes.dropped_count(),
+            events: span_data.message_events.iter().map(Into::into).collect(),
+            dropped_events_count: span_data.message_events.dropped_count(),
+            links: span_data.links.iter().map(Into::into).collect(),
+            dropped_links_count: span_data.links.dropped_count(),
+            status: Some(otlp::v1::Status {
+                // `api::StatusCode` shares the canonical gRPC numbering used by OTLP.
+                code: span_data.status_code.clone() as i32,
+                message: span_data.status_message.clone(),
+            }),
+        }
+    }
+}
+
+fn span_kind(kind: &api::SpanKind) -> otlp::v1::span::SpanKind {
+    match kind {
+        api::SpanKind::Client => otlp::v1::span::SpanKind::Client,
+        api::SpanKind::Server => otlp::v1::span::SpanKind::Server,
+        api::SpanKind::Producer => otlp::v1::span::SpanKind::Producer,
+        api::SpanKind::Consumer => otlp::v1::span::SpanKind::Consumer,
+        api::SpanKind::Internal => otlp::v1::span::SpanKind::Internal,
+    }
+}
+
+fn trace_id_bytes(trace_id: api::TraceId) -> Vec<u8> {
+    trace_id.to_u128().to_be_bytes().to_vec()
+}
+
+fn span_id_bytes(span_id: api::SpanId) -> Vec<u8> {
+    span_id.to_u64().to_be_bytes().to_vec()
+}
+
+fn to_nanos(time: SystemTime) -> u64 {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_else(|_| Duration::from_secs(0))
+        .as_nanos() as u64
+}
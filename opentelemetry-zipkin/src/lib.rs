@@ -11,14 +11,15 @@
 //! ```rust,no_run
 //! use opentelemetry::{api::Key, global, sdk};
 //! use opentelemetry_zipkin::ExporterConfig;
+//! use std::io;
 //! use std::net::{SocketAddr, IpAddr, Ipv4Addr};
 //!
-//! fn init_tracer() {
+//! fn init_tracer() -> io::Result<()> {
 //!     let exporter = opentelemetry_zipkin::Exporter::from_config(
 //!        ExporterConfig::builder()
 //!            .with_service_name("opentelemetry-backend".to_owned())
 //!            .with_service_endpoint(SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080))
-//!            .build());
+//!            .build())?;
 //!     let provider = sdk::Provider::builder()
 //!         .with_simple_exporter(exporter)
 //!         .with_config(sdk::Config {
@@ -28,9 +29,18 @@
 //!         .build();
 //!
 //!     global::set_provider(provider);
+//!
+//!     Ok(())
 //! }
 //! ```
 //!
+//! The exporter also implements [`AsyncSpanExporter`], so when it is used with
+//! a [`BatchSpanProcessor`] spans are uploaded without blocking the executor.
+//! Requests are sent from the exporter's own Tokio runtime, so any executor,
+//! such as `async-std`, can drive the processor.
+//!
+//! [`AsyncSpanExporter`]: ../opentelemetry/exporter/trace/trait.AsyncSpanExporter.html
+//! [`BatchSpanProcessor`]: ../opentelemetry/sdk/trace/span_processor/struct.BatchSpanProcessor.html
 #![deny(missing_docs, unreachable_pub, missing_debug_implementations)]
 #![cfg_attr(test, deny(warnings))]
 
@@ -40,10 +50,12 @@ extern crate typed_builder;
 mod model;
 mod uploader;
 
+use futures::future::BoxFuture;
 use model::{annotation, endpoint, span};
 use opentelemetry::api;
 use opentelemetry::exporter::trace;
 use std::collections::HashMap;
+use std::io;
 use std::net;
 use std::sync::Arc;
 use std::time::{Duration, SystemTime};
@@ -146,15 +158,16 @@ impl ExporterConfigBuilder {
 }
 
 impl Exporter {
-    /// Creates new `Exporter` from a given `ExporterConfig`.
-    pub fn from_config(config: ExporterConfig) -> Self {
-        Exporter {
-            config: config.clone(),
+    /// Creates new `Exporter` from a given `ExporterConfig`, failing if the
+    /// HTTP client or the runtime driving its requests cannot be built.
+    pub fn from_config(config: ExporterConfig) -> io::Result<Self> {
+        Ok(Exporter {
             uploader: uploader::Uploader::new(
-                config.collector_endpoint,
+                config.collector_endpoint.clone(),
                 uploader::UploaderFormat::HTTP,
-            ),
-        }
+            )?,
+            config,
+        })
     }
 }
 
@@ -171,6 +184,19 @@ impl trace::SpanExporter for Exporter {
     fn shutdown(&self) {}
 }
 
+impl trace::AsyncSpanExporter for Exporter {
+    /// Export spans to Zipkin collector without blocking the calling thread.
+    fn export(&self, batch: Vec<Arc<trace::SpanData>>) -> BoxFuture<'static, trace::ExportResult> {
+        let zipkin_spans: Vec<span::Span> = batch
+            .into_iter()
+            .map(|span| into_zipkin_span(&self.config, span))
+            .collect();
+        self.uploader.upload_async(span::ListOfSpans(zipkin_spans))
+    }
+
+    fn shutdown(&self) {}
+}
+
 /// Converts `api::Event` into an `annotation::Annotation`
 impl Into<annotation::Annotation> for api::Event {
     fn into(self) -> annotation::Annotation {
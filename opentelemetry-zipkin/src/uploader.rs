@@ -1,9 +1,14 @@
 //! # Zipkin Span Exporter
 use crate::model::span::ListOfSpans;
+use futures::future::BoxFuture;
 use opentelemetry::exporter::trace;
+use std::io;
+use std::time::Duration;
 
 /// Default v2 HTTP Zipkin API route for recording spans
 static API_V2_COLLECTOR_ROUTE: &str = "/api/v2/spans";
+/// Some arbitrary default timeout to avoid hanging forever
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
 
 #[derive(Clone, Debug)]
 pub(crate) enum UploaderFormat {
@@ -12,46 +17,206 @@ pub(crate) enum UploaderFormat {
 
 #[derive(Debug)]
 pub(crate) struct Uploader {
-    client: reqwest::blocking::Client,
+    client: reqwest::Client,
     collector_endpoint: String,
     format: UploaderFormat,
+    // Requests are driven by the uploader's own runtime so that spans can be
+    // uploaded from any thread and any executor.
+    runtime: tokio::runtime::Runtime,
 }
 
 impl Uploader {
-    pub(crate) fn new(collector_endpoint: String, format: UploaderFormat) -> Self {
-        Uploader {
+    pub(crate) fn new(collector_endpoint: String, format: UploaderFormat) -> io::Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .map_err(io::Error::other)?;
+        let runtime = tokio::runtime::Builder::new()
+            .threaded_scheduler()
+            .core_threads(1)
+            .thread_name("opentelemetry-zipkin")
+            .enable_all()
+            .build()?;
+
+        Ok(Uploader {
             format,
-            client: reqwest::blocking::Client::new(),
+            client,
             collector_endpoint: format!("http://{}{}", collector_endpoint, API_V2_COLLECTOR_ROUTE),
-        }
+            runtime,
+        })
     }
 
-    /// Upload a `ListOfSpans` to the designated Zipkin collector
+    /// Upload a `ListOfSpans` to the designated Zipkin collector, blocking
+    /// until the collector responds.
     pub(crate) fn upload(&self, spans: ListOfSpans) -> trace::ExportResult {
+        futures::executor::block_on(self.upload_async(spans))
+    }
+
+    /// Upload a `ListOfSpans` to the designated Zipkin collector without
+    /// blocking the calling thread.
+    pub(crate) fn upload_async(
+        &self,
+        spans: ListOfSpans,
+    ) -> BoxFuture<'static, trace::ExportResult> {
         match self.format {
             UploaderFormat::HTTP => self.upload_http(spans),
         }
     }
 
-    fn upload_http(&self, spans: ListOfSpans) -> trace::ExportResult {
+    fn upload_http(&self, spans: ListOfSpans) -> BoxFuture<'static, trace::ExportResult> {
         let zipkin_span_json = match serde_json::to_string(&spans) {
             Ok(json) => json,
-            Err(_) => return trace::ExportResult::FailedNotRetryable,
+            Err(_) => {
+                return Box::pin(futures::future::ready(
+                    trace::ExportResult::FailedNotRetryable,
+                ))
+            }
         };
 
-        let resp = self
+        let request = self
             .client
             .post(&self.collector_endpoint)
             .header(reqwest::header::CONTENT_TYPE, "application/json")
-            .body(zipkin_span_json)
-            .send();
+            .body(zipkin_span_json);
 
-        if let Ok(response) = resp {
-            if response.status().is_success() {
-                return trace::ExportResult::Success;
+        let handle = self.runtime.spawn(async move {
+            match request.send().await {
+                Ok(response) => export_result(response.status()),
+                Err(_) => trace::ExportResult::FailedRetryable,
             }
+        });
+        Box::pin(async move {
+            handle
+                .await
+                .unwrap_or(trace::ExportResult::FailedNotRetryable)
+        })
+    }
+}
+
+/// Maps collector response statuses to export results, retrying only
+/// statuses that indicate a transient failure.
+fn export_result(status: reqwest::StatusCode) -> trace::ExportResult {
+    match status {
+        status if status.is_success() => trace::ExportResult::Success,
+        reqwest::StatusCode::TOO_MANY_REQUESTS
+        | reqwest::StatusCode::BAD_GATEWAY
+        | reqwest::StatusCode::SERVICE_UNAVAILABLE
+        | reqwest::StatusCode::GATEWAY_TIMEOUT => trace::ExportResult::FailedRetryable,
+        _ => trace::ExportResult::FailedNotRetryable,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::span::Span;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    #[derive(Debug)]
+    struct RecordedRequest {
+        path: String,
+        body: Vec<u8>,
+    }
+
+    /// Starts a stub collector replying to every request with `status`,
+    /// returning its endpoint and the requests it has received.
+    fn start_collector(status: u16) -> (String, Arc<Mutex<Vec<RecordedRequest>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = listener.local_addr().unwrap().to_string();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let path = request_line.split(' ').nth(1).unwrap().to_string();
+
+                let mut len = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    let line = line.trim_end();
+                    if line.is_empty() {
+                        break;
+                    }
+                    let mut parts = line.splitn(2, ':');
+                    if parts.next().unwrap().eq_ignore_ascii_case("content-length") {
+                        len = parts.next().unwrap().trim().parse().unwrap();
+                    }
+                }
+                let mut body = vec![0; len];
+                reader.read_exact(&mut body).unwrap();
+                recorded
+                    .lock()
+                    .unwrap()
+                    .push(RecordedRequest { path, body });
+
+                write!(
+                    stream,
+                    "HTTP/1.1 {} Stub\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    status
+                )
+                .unwrap();
+            }
+        });
+
+        (endpoint, requests)
+    }
+
+    fn spans() -> ListOfSpans {
+        ListOfSpans(vec![Span::builder().name("span".to_string()).build()])
+    }
+
+    #[test]
+    fn uploads_spans_without_blocking() {
+        let (endpoint, requests) = start_collector(202);
+        let uploader = Uploader::new(endpoint, UploaderFormat::HTTP).unwrap();
+
+        let result = futures::executor::block_on(uploader.upload_async(spans()));
+        assert_eq!(result, trace::ExportResult::Success);
+
+        let requests = requests.lock().unwrap();
+        let request = &requests[0];
+        assert_eq!(request.path, API_V2_COLLECTOR_ROUTE);
+        let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+        assert_eq!(body[0]["name"], "span");
+    }
+
+    #[test]
+    fn maps_collector_errors() {
+        for (status, expected) in [
+            (503, trace::ExportResult::FailedRetryable),
+            (400, trace::ExportResult::FailedNotRetryable),
+        ] {
+            let (endpoint, _) = start_collector(status);
+            let uploader = Uploader::new(endpoint, UploaderFormat::HTTP).unwrap();
+            let result = futures::executor::block_on(uploader.upload_async(spans()));
+            assert_eq!(result, expected, "status {}", status);
         }
+    }
 
-        trace::ExportResult::FailedRetryable
+    #[test]
+    fn retries_only_transient_statuses() {
+        for (status, expected) in [
+            (200, trace::ExportResult::Success),
+            (202, trace::ExportResult::Success),
+            (429, trace::ExportResult::FailedRetryable),
+            (502, trace::ExportResult::FailedRetryable),
+            (503, trace::ExportResult::FailedRetryable),
+            (504, trace::ExportResult::FailedRetryable),
+            (400, trace::ExportResult::FailedNotRetryable),
+            (404, trace::ExportResult::FailedNotRetryable),
+            (413, trace::ExportResult::FailedNotRetryable),
+            (500, trace::ExportResult::FailedNotRetryable),
+        ] {
+            let status = reqwest::StatusCode::from_u16(status).unwrap();
+            assert_eq!(export_result(status), expected, "status {}", status);
+        }
     }
 }
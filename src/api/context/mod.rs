@@ -7,6 +7,8 @@
 //!
 //! [`Context`]s are immutable, and their write operations result in the creation
 //! of a new context containing the original values and the new specified values.
+//! Contexts are `Send` and `Sync`, so they can be moved to other threads or
+//! tasks, and all values stored in them must be `Send` and `Sync` as well.
 //!
 //! ## Context state
 //!
@@ -68,7 +70,7 @@ use std::cell::RefCell;
 use std::collections::HashMap;
 use std::fmt;
 use std::hash::{BuildHasherDefault, Hasher};
-use std::rc::Rc;
+use std::sync::Arc;
 
 pub mod propagation;
 
@@ -80,7 +82,7 @@ thread_local! {
 /// An execution-scoped collection of values.
 #[derive(Clone, Default)]
 pub struct Context {
-    entries: HashMap<TypeId, Rc<dyn Any>, BuildHasherDefault<IdHasher>>,
+    entries: HashMap<TypeId, Arc<dyn Any + Send + Sync>, BuildHasherDefault<IdHasher>>,
 }
 
 impl Context {
@@ -115,6 +117,28 @@ impl Context {
         get_current(|cx| cx.clone())
     }
 
+    /// Applies a function to a reference to the current thread's context,
+    /// without cloning it.
+    ///
+    /// Note: This function will panic if the closure attaches another
+    /// context, as the current context is borrowed while it runs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use opentelemetry::api::Context;
+    ///
+    /// #[derive(Debug, PartialEq)]
+    /// struct ValueA(&'static str);
+    ///
+    /// let _guard = Context::new().with_value(ValueA("a")).attach();
+    /// let value = Context::map_current(|cx| cx.get::<ValueA>().map(|value| value.0));
+    /// assert_eq!(value, Some("a"));
+    /// ```
+    pub fn map_current<T, F: FnMut(&Context) -> T>(f: F) -> T {
+        get_current(f)
+    }
+
     /// Returns a clone of the current thread's context with the given value.
     ///
     /// This is a more efficient form of `Context::current().with_value(value)`
@@ -141,11 +165,11 @@ impl Context {
     /// assert_eq!(all_current_and_b.get::<ValueA>(), Some(&ValueA("a")));
     /// assert_eq!(all_current_and_b.get::<ValueB>(), Some(&ValueB(42)));
     /// ```
-    pub fn current_with_value<T: 'static>(value: T) -> Self {
+    pub fn current_with_value<T: 'static + Send + Sync>(value: T) -> Self {
         let mut new_context = Context::current();
         new_context
             .entries
-            .insert(TypeId::of::<T>(), Rc::new(value));
+            .insert(TypeId::of::<T>(), Arc::new(value));
 
         new_context
     }
@@ -174,7 +198,7 @@ impl Context {
     pub fn get<T: 'static>(&self) -> Option<&T> {
         self.entries
             .get(&TypeId::of::<T>())
-            .and_then(|rc| (&*rc).downcast_ref())
+            .and_then(|entry| (**entry).downcast_ref())
     }
 
     /// Returns a copy of the context with the new value included.
@@ -204,11 +228,11 @@ impl Context {
     /// assert_eq!(cx_with_a_and_b.get::<ValueA>(), Some(&ValueA("a")));
     /// assert_eq!(cx_with_a_and_b.get::<ValueB>(), Some(&ValueB(42)));
     /// ```
-    pub fn with_value<T: 'static>(&self, value: T) -> Self {
+    pub fn with_value<T: 'static + Send + Sync>(&self, value: T) -> Self {
         let mut new_context = self.clone();
         new_context
             .entries
-            .insert(TypeId::of::<T>(), Rc::new(value));
+            .insert(TypeId::of::<T>(), Arc::new(value));
 
         new_context
     }
@@ -378,4 +402,17 @@ mod tests {
         assert_eq!(current.get(), Some(&ValueA("a")));
         assert_eq!(current.get::<ValueB>(), None);
     }
+
+    #[test]
+    fn contexts_cross_threads() {
+        #[derive(Debug, PartialEq)]
+        struct ValueA(&'static str);
+        let cx = Context::new().with_value(ValueA("a"));
+
+        let handle = std::thread::spawn(move || {
+            let _guard = cx.attach();
+            Context::current().get::<ValueA>() == Some(&ValueA("a"))
+        });
+        assert!(handle.join().unwrap());
+    }
 }
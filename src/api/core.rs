@@ -196,7 +196,7 @@ impl KeyValue {
 }
 
 /// Units denote underlying data units tracked by `Meter`s.
-#[derive(Default, Debug)]
+#[derive(Clone, Default, Debug, PartialEq)]
 pub struct Unit(String);
 
 impl Unit {
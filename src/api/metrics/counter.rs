@@ -38,6 +38,12 @@ where
     fn add(&self, value: T, label_set: &LS) {
         self.record_one(value.into(), label_set)
     }
+
+    /// As `add`, but returns an error if the value is rejected by the
+    /// instrument.
+    fn try_add(&self, value: T, label_set: &LS) -> metrics::Result<()> {
+        self.try_record_one(value.into(), label_set)
+    }
 }
 
 /// `CounterHandle` is a handle for `Counter` instances.
@@ -52,4 +58,10 @@ where
     fn add(&self, value: T) {
         self.record_one(value.into())
     }
+
+    /// As `add`, but returns an error if the value is rejected by the
+    /// instrument.
+    fn try_add(&self, value: T) -> metrics::Result<()> {
+        self.try_record_one(value.into())
+    }
 }
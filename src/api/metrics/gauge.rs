@@ -48,6 +48,12 @@ where
     fn set(&self, value: T, label_set: &LS) {
         self.record_one(value.into(), label_set)
     }
+
+    /// As `set`, but returns an error if the value is rejected by the
+    /// instrument.
+    fn try_set(&self, value: T, label_set: &LS) -> metrics::Result<()> {
+        self.try_record_one(value.into(), label_set)
+    }
 }
 
 /// `GaugeHandle` is a handle for `Gauge` instances.
@@ -62,4 +68,10 @@ where
     fn set(&self, value: T) {
         self.record_one(value.into())
     }
+
+    /// As `set`, but returns an error if the value is rejected by the
+    /// instrument.
+    fn try_set(&self, value: T) -> metrics::Result<()> {
+        self.try_record_one(value.into())
+    }
 }
@@ -47,6 +47,12 @@ where
     fn record(&self, value: T, label_set: &LS) {
         self.record_one(value.into(), label_set)
     }
+
+    /// As `record`, but returns an error if the value is rejected by the
+    /// instrument.
+    fn try_record(&self, value: T, label_set: &LS) -> metrics::Result<()> {
+        self.try_record_one(value.into(), label_set)
+    }
 }
 
 /// `MeasureHandle` is a handle for `Measure` instances.
@@ -61,4 +67,10 @@ where
     fn record(&self, value: T) {
         self.record_one(value.into())
     }
+
+    /// As `record`, but returns an error if the value is rejected by the
+    /// instrument.
+    fn try_record(&self, value: T) -> metrics::Result<()> {
+        self.try_record_one(value.into())
+    }
 }
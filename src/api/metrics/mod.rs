@@ -39,6 +39,8 @@
 //!
 //! We believe the three metric kinds `Counter`, `Gauge`, and `Measure`
 //! form a sufficient basis for expression of a wide variety of metric data.
+//! Values that are only read when needed are reported by observers, whose
+//! callbacks are invoked when the metrics are collected.
 //! Programmers write and read these as `add()`, `set()`, and `record()`
 //! method calls, signifying the semantics and standard interpretation,
 //! and we believe these three methods are all that are needed.
@@ -55,31 +57,103 @@
 //! the kind of instrument or the method name used, as the semantics are
 //! unchanged.
 use crate::api;
+use std::fmt;
 use std::sync::Arc;
 
 pub mod counter;
 pub mod gauge;
 pub mod measure;
 pub mod noop;
+pub mod observer;
+pub mod provider;
 pub mod value;
 
 use counter::Counter;
 use gauge::Gauge;
 use measure::Measure;
+use observer::ObserverResult;
 use value::MeasurementValue;
 
+/// A specialized `Result` type for metric operations.
+pub type Result<T> = std::result::Result<T, MetricsError>;
+
+/// Errors returned when a measurement is rejected by an instrument.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MetricsError {
+    /// A negative value was recorded by a monotonic `Counter` or an absolute
+    /// `Measure`.
+    NegativeInput,
+    /// A value lower than the current value was set on a monotonic `Gauge`.
+    NonMonotoneInput,
+    /// A `NaN` value was recorded.
+    NaNInput,
+    /// The label set was created by a meter of a different type than the
+    /// instrument, for example before the global meter provider was replaced.
+    IncompatibleLabelSet,
+}
+
+impl fmt::Display for MetricsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MetricsError::NegativeInput => {
+                write!(
+                    f,
+                    "negative value recorded by a monotonic or absolute instrument"
+                )
+            }
+            MetricsError::NonMonotoneInput => {
+                write!(
+                    f,
+                    "value lower than the current value set on a monotonic gauge"
+                )
+            }
+            MetricsError::NaNInput => write!(f, "NaN value recorded"),
+            MetricsError::IncompatibleLabelSet => {
+                write!(f, "label set created by an incompatible meter")
+            }
+        }
+    }
+}
+
+impl std::error::Error for MetricsError {}
+
 /// The implementation-level interface to Set/Add/Record individual
 /// metrics without precomputed labels.
 pub trait Instrument<LS> {
     /// Allows the SDK to observe a single metric event for a given set of labels.
+    ///
+    /// Values rejected by the instrument are discarded.
     fn record_one(&self, value: MeasurementValue, label_set: &LS);
+
+    /// Allows the SDK to observe a single metric event for a given set of
+    /// labels, returning an error if the value is rejected by the instrument.
+    fn try_record_one(&self, value: MeasurementValue, label_set: &LS) -> Result<()> {
+        self.record_one(value, label_set);
+        Ok(())
+    }
+
+    /// Bind the instrument to a label set, for SDKs that record values more
+    /// efficiently through a handle. Returns `None` if the SDK does not
+    /// support it.
+    fn bind_one(&self, _label_set: &LS) -> Option<Box<dyn InstrumentHandle>> {
+        None
+    }
 }
 
 /// The implementation-level interface to Set/Add/Record individual
 /// metrics with precomputed labels.
 pub trait InstrumentHandle {
     /// Allows the SDK to observe a single metric event.
+    ///
+    /// Values rejected by the instrument are discarded.
     fn record_one(&self, value: MeasurementValue);
+
+    /// Allows the SDK to observe a single metric event, returning an error if
+    /// the value is rejected by the instrument.
+    fn try_record_one(&self, value: MeasurementValue) -> Result<()> {
+        self.record_one(value);
+        Ok(())
+    }
 }
 
 /// `LabelSet` is an implementation-level interface that represents a
@@ -100,6 +174,31 @@ pub struct MetricOptions {
     /// Keys are dimension names for the given metric.
     pub keys: Vec<api::Key>,
 
+    /// Monotonic applies to `Counter`s and `Gauge`s.
+    ///
+    /// - for `Counter`, `true` (the default) implies that only non-negative
+    ///   increments are accepted, `false` that the metric is an up-down
+    ///   `Counter`
+    ///
+    /// - for `Gauge`, `true` implies that the metric is a non-descending
+    ///   `Gauge`, `false` (the default) that any value can be set
+    ///
+    /// `None` selects the default of the instrument kind.
+    pub monotonic: Option<bool>,
+
+    /// Absolute applies to `Measure`s. `true` (the default) implies that
+    /// negative values are rejected, `false` that the metric supports
+    /// positive and negative values.
+    ///
+    /// `None` selects the default of the instrument kind.
+    pub absolute: Option<bool>,
+
+    /// Aggregation applies to `Measure`s, hinting how SDKs supporting it
+    /// should aggregate the recorded values.
+    ///
+    /// `None` selects the SDK's default aggregation.
+    pub aggregation: Option<MeasureAggregation>,
+
     /// Alternate defines the property of metric value dependent on
     /// a metric type.
     ///
@@ -111,9 +210,24 @@ pub struct MetricOptions {
     ///
     /// - for `Measure`, `true` implies that the metric supports
     ///   positive and negative values
+    ///
+    /// Only applies when `monotonic` or `absolute` is `None`.
+    #[deprecated(note = "use `monotonic` or `absolute` instead")]
     pub alternate: bool,
 }
 
+/// Aggregations of `Measure` values that can be requested through
+/// `MetricOptions`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MeasureAggregation {
+    /// Count values in buckets with the given inclusive upper bounds, values
+    /// larger than the last bound are counted in an overflow bucket.
+    Histogram(Vec<f64>),
+    /// Estimate quantiles with a sketch whose estimates are within the given
+    /// relative accuracy of the actual values, e.g. `0.01` for 1%.
+    Sketch(f64),
+}
+
 impl MetricOptions {
     /// Set a description for the current set of options.
     pub fn with_description<S: Into<String>>(self, description: S) -> Self {
@@ -133,16 +247,59 @@ impl MetricOptions {
         MetricOptions { keys, ..self }
     }
 
-    /// Set monotonic for the given set of metric options.
-    pub fn with_monotonic(self, _monotonic: bool) -> Self {
-        // TODO figure out counter vs gauge issue here.
-        unimplemented!()
+    /// Set monotonic for the given set of metric options. Applies to
+    /// `Counter`s and `Gauge`s.
+    pub fn with_monotonic(self, monotonic: bool) -> Self {
+        MetricOptions {
+            monotonic: Some(monotonic),
+            ..self
+        }
     }
 
-    /// Set absolute for the given set of metric options.
+    /// Set absolute for the given set of metric options. Applies to
+    /// `Measure`s.
     pub fn with_absolute(self, absolute: bool) -> Self {
         MetricOptions {
-            alternate: !absolute,
+            absolute: Some(absolute),
+            ..self
+        }
+    }
+
+    /// Aggregate measure values into a histogram with the given bucket upper
+    /// bounds.
+    pub fn with_buckets(self, boundaries: Vec<f64>) -> Self {
+        MetricOptions {
+            aggregation: Some(MeasureAggregation::Histogram(boundaries)),
+            ..self
+        }
+    }
+
+    /// Aggregate measure values into a histogram with `count` buckets of
+    /// `width`, the first bucket having an upper bound of `start`.
+    pub fn with_linear_buckets(self, start: f64, width: f64, count: usize) -> Self {
+        self.with_buckets((0..count).map(|i| start + width * i as f64).collect())
+    }
+
+    /// Aggregate measure values into a histogram with `count` buckets, the
+    /// first bucket having an upper bound of `start` and each following upper
+    /// bound being `factor` times the previous one.
+    pub fn with_exponential_buckets(self, start: f64, factor: f64, count: usize) -> Self {
+        self.with_buckets(
+            (0..count)
+                .scan(start, |bound, _| {
+                    let current = *bound;
+                    *bound *= factor;
+                    Some(current)
+                })
+                .collect(),
+        )
+    }
+
+    /// Aggregate measure values into a quantile sketch with the given
+    /// relative accuracy.
+    pub fn with_sketch(self, relative_accuracy: f64) -> Self {
+        MetricOptions {
+            aggregation: Some(MeasureAggregation::Sketch(relative_accuracy)),
             ..self
         }
     }
@@ -238,6 +395,48 @@ pub trait Meter {
     /// Creates a new `f64` measure with a given name and customized with passed options.
     fn new_f64_measure<S: Into<String>>(&self, name: S, opts: MetricOptions) -> Self::F64Measure;
 
+    /// Registers a new `i64` value observer with a given name and customized with passed
+    /// options. The callback is invoked every time the metrics are collected.
+    fn register_i64_value_observer<S, F>(&self, name: S, opts: MetricOptions, callback: F)
+    where
+        S: Into<String>,
+        F: Fn(ObserverResult<'_, i64, Self::LabelSet>) + Send + Sync + 'static;
+
+    /// Registers a new `i64` sum observer with a given name and customized with passed
+    /// options. The callback is invoked every time the metrics are collected.
+    fn register_i64_sum_observer<S, F>(&self, name: S, opts: MetricOptions, callback: F)
+    where
+        S: Into<String>,
+        F: Fn(ObserverResult<'_, i64, Self::LabelSet>) + Send + Sync + 'static;
+
+    /// Registers a new `i64` up-down sum observer with a given name and customized with passed
+    /// options. The callback is invoked every time the metrics are collected.
+    fn register_i64_up_down_sum_observer<S, F>(&self, name: S, opts: MetricOptions, callback: F)
+    where
+        S: Into<String>,
+        F: Fn(ObserverResult<'_, i64, Self::LabelSet>) + Send + Sync + 'static;
+
+    /// Registers a new `f64` value observer with a given name and customized with passed
+    /// options. The callback is invoked every time the metrics are collected.
+    fn register_f64_value_observer<S, F>(&self, name: S, opts: MetricOptions, callback: F)
+    where
+        S: Into<String>,
+        F: Fn(ObserverResult<'_, f64, Self::LabelSet>) + Send + Sync + 'static;
+
+    /// Registers a new `f64` sum observer with a given name and customized with passed
+    /// options. The callback is invoked every time the metrics are collected.
+    fn register_f64_sum_observer<S, F>(&self, name: S, opts: MetricOptions, callback: F)
+    where
+        S: Into<String>,
+        F: Fn(ObserverResult<'_, f64, Self::LabelSet>) + Send + Sync + 'static;
+
+    /// Registers a new `f64` up-down sum observer with a given name and customized with passed
+    /// options. The callback is invoked every time the metrics are collected.
+    fn register_f64_up_down_sum_observer<S, F>(&self, name: S, opts: MetricOptions, callback: F)
+    where
+        S: Into<String>,
+        F: Fn(ObserverResult<'_, f64, Self::LabelSet>) + Send + Sync + 'static;
+
     /// Atomically records a batch of measurements.
     fn record_batch<M: IntoIterator<Item = Measurement<Self::LabelSet>>>(
         &self,
@@ -245,3 +444,33 @@ pub trait Meter {
         measurements: M,
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bucket_options() {
+        assert_eq!(
+            MetricOptions::default()
+                .with_linear_buckets(0.5, 0.25, 3)
+                .aggregation,
+            Some(MeasureAggregation::Histogram(vec![0.5, 0.75, 1.0]))
+        );
+        assert_eq!(
+            MetricOptions::default()
+                .with_exponential_buckets(0.001, 10.0, 4)
+                .aggregation,
+            Some(MeasureAggregation::Histogram(vec![
+                0.001,
+                0.001 * 10.0,
+                0.001 * 10.0 * 10.0,
+                0.001 * 10.0 * 10.0 * 10.0
+            ]))
+        );
+        assert_eq!(
+            MetricOptions::default().with_sketch(0.01).aggregation,
+            Some(MeasureAggregation::Sketch(0.01))
+        );
+    }
+}
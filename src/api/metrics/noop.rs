@@ -7,6 +7,19 @@ use crate::api;
 use std::marker;
 use std::sync::Arc;
 
+/// A no-op instance of a `MeterProvider`.
+#[derive(Debug)]
+pub struct NoopMeterProvider {}
+
+impl api::MeterProvider for NoopMeterProvider {
+    type Meter = NoopMeter;
+
+    /// Returns a new `NoopMeter` instance.
+    fn get_meter(&self, _name: &'static str) -> Self::Meter {
+        NoopMeter {}
+    }
+}
+
 /// A no-op instance of a `Meter`.
 #[derive(Clone, Debug)]
 pub struct NoopMeter {}
@@ -91,6 +104,68 @@ impl api::Meter for NoopMeter {
         }
     }
 
+    /// Ignores the observer, its callback is never invoked.
+    fn register_i64_value_observer<S, F>(&self, _name: S, _opts: api::MetricOptions, _callback: F)
+    where
+        S: Into<String>,
+        F: Fn(api::ObserverResult<'_, i64, NoopLabelSet>) + Send + Sync + 'static,
+    {
+        // Ignored
+    }
+
+    /// Ignores the observer, its callback is never invoked.
+    fn register_i64_sum_observer<S, F>(&self, _name: S, _opts: api::MetricOptions, _callback: F)
+    where
+        S: Into<String>,
+        F: Fn(api::ObserverResult<'_, i64, NoopLabelSet>) + Send + Sync + 'static,
+    {
+        // Ignored
+    }
+
+    /// Ignores the observer, its callback is never invoked.
+    fn register_i64_up_down_sum_observer<S, F>(
+        &self,
+        _name: S,
+        _opts: api::MetricOptions,
+        _callback: F,
+    ) where
+        S: Into<String>,
+        F: Fn(api::ObserverResult<'_, i64, NoopLabelSet>) + Send + Sync + 'static,
+    {
+        // Ignored
+    }
+
+    /// Ignores the observer, its callback is never invoked.
+    fn register_f64_value_observer<S, F>(&self, _name: S, _opts: api::MetricOptions, _callback: F)
+    where
+        S: Into<String>,
+        F: Fn(api::ObserverResult<'_, f64, NoopLabelSet>) + Send + Sync + 'static,
+    {
+        // Ignored
+    }
+
+    /// Ignores the observer, its callback is never invoked.
+    fn register_f64_sum_observer<S, F>(&self, _name: S, _opts: api::MetricOptions, _callback: F)
+    where
+        S: Into<String>,
+        F: Fn(api::ObserverResult<'_, f64, NoopLabelSet>) + Send + Sync + 'static,
+    {
+        // Ignored
+    }
+
+    /// Ignores the observer, its callback is never invoked.
+    fn register_f64_up_down_sum_observer<S, F>(
+        &self,
+        _name: S,
+        _opts: api::MetricOptions,
+        _callback: F,
+    ) where
+        S: Into<String>,
+        F: Fn(api::ObserverResult<'_, f64, NoopLabelSet>) + Send + Sync + 'static,
+    {
+        // Ignored
+    }
+
     /// Ignores batch recordings
     fn record_batch<M: IntoIterator<Item = api::Measurement<NoopLabelSet>>>(
         &self,
@@ -0,0 +1,52 @@
+//! # Metrics Observer Interface
+//!
+//! Observers are asynchronous instruments: rather than recording values as
+//! they happen, they register a callback that the SDK invokes every time the
+//! metrics are collected. The callback reports the current values through the
+//! [`ObserverResult`] it is passed, one per label set. Choose an observer
+//! when the value is cheap to read when needed but expensive or awkward to
+//! track continuously, such as a queue depth or the memory in use.
+//!
+//! - value observers report a value where the distribution or the last
+//!   value is of interest, such as a temperature
+//! - sum observers report a monotonic sum, such as the total CPU time used
+//!   by the process
+//! - up-down sum observers report a sum that can go up or down, such as the
+//!   number of connections in a pool
+//!
+//! [`ObserverResult`]: struct.ObserverResult.html
+use crate::api::metrics;
+use std::fmt;
+use std::marker::PhantomData;
+
+/// Reports the observed values of an observer instrument from its callback.
+pub struct ObserverResult<'a, T, LS> {
+    observe: &'a dyn Fn(metrics::value::MeasurementValue, &LS),
+    _marker: PhantomData<fn(T)>,
+}
+
+impl<'a, T, LS> ObserverResult<'a, T, LS>
+where
+    T: Into<metrics::value::MeasurementValue>,
+    LS: metrics::LabelSet,
+{
+    /// Create a new result reporting observations to the given function.
+    /// Used by `Meter` implementations.
+    pub fn new(observe: &'a dyn Fn(metrics::value::MeasurementValue, &LS)) -> Self {
+        ObserverResult {
+            observe,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Report the current value for the given label set.
+    pub fn observe(&self, value: T, label_set: &LS) {
+        (self.observe)(value.into(), label_set)
+    }
+}
+
+impl<T, LS> fmt::Debug for ObserverResult<'_, T, LS> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ObserverResult").finish()
+    }
+}
@@ -0,0 +1,17 @@
+//! # OpenTelemetry Metrics Provider API
+//!
+//! The `MeterProvider` is the entry point of the metrics API. It provides
+//! access to named `Meter`s, which libraries use to create the instruments
+//! they record measurements with.
+use crate::api;
+use std::fmt;
+
+/// An interface to create and access named `Meter` instances.
+pub trait MeterProvider: fmt::Debug + 'static {
+    /// The `Meter` type that this `MeterProvider` will return.
+    type Meter: api::Meter;
+
+    /// Creates a named meter instance of `Self::Meter`.
+    /// If the name is an empty string then provider uses default name.
+    fn get_meter(&self, name: &'static str) -> Self::Meter;
+}
@@ -35,9 +35,12 @@ pub use metrics::{
     counter::{Counter, CounterHandle},
     gauge::{Gauge, GaugeHandle},
     measure::{Measure, MeasureHandle},
-    noop::NoopMeter,
+    noop::{NoopMeter, NoopMeterProvider},
+    observer::ObserverResult,
+    provider::MeterProvider,
     value::MeasurementValue,
-    Instrument, InstrumentHandle, LabelSet, Measurement, Meter, MetricOptions,
+    Instrument, InstrumentHandle, LabelSet, MeasureAggregation, Measurement, Meter, MetricOptions,
+    MetricsError,
 };
 pub use trace::{
     b3_propagator::B3Propagator,
@@ -50,7 +53,10 @@ pub use trace::{
     provider::Provider,
     sampler::{Sampler, SamplingDecision, SamplingResult},
     span::{Span, SpanKind, StatusCode},
-    span_context::{SpanContext, SpanId, TraceId, TRACE_FLAGS_UNUSED, TRACE_FLAG_SAMPLED},
+    span_context::{
+        SpanContext, SpanId, TraceId, TraceState, TraceStateError, TRACE_FLAGS_UNUSED,
+        TRACE_FLAG_SAMPLED,
+    },
     span_processor::SpanProcessor,
     trace_context_propagator::TraceContextPropagator,
     tracer::{SpanBuilder, Tracer},
@@ -136,3 +136,18 @@ impl exporter::trace::SpanExporter for NoopSpanExporter {
         // Noop
     }
 }
+
+impl exporter::trace::AsyncSpanExporter for NoopSpanExporter {
+    fn export(
+        &self,
+        _batch: Vec<Arc<exporter::trace::SpanData>>,
+    ) -> futures::future::BoxFuture<'static, exporter::trace::ExportResult> {
+        Box::pin(futures::future::ready(
+            exporter::trace::ExportResult::Success,
+        ))
+    }
+
+    fn shutdown(&self) {
+        // Noop
+    }
+}
@@ -12,6 +12,9 @@
 //! [w3c TraceContext specification]: https://www.w3.org/TR/trace-context/
 #[cfg(feature = "serialize")]
 use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt;
+use std::str::FromStr;
 
 const TRACE_FLAGS_BIT_MASK_SAMPLED: u8 = 0x01;
 const TRACE_FLAGS_BIT_MASK_UNUSED: u8 = 0xFE;
@@ -22,6 +25,9 @@ pub const TRACE_FLAG_SAMPLED: u8 = TRACE_FLAGS_BIT_MASK_SAMPLED;
 /// Useful for extracting trace context
 pub const TRACE_FLAGS_UNUSED: u8 = TRACE_FLAGS_BIT_MASK_UNUSED;
 
+/// Maximum number of list members in a `TraceState`.
+const TRACE_STATE_MAX_MEMBERS: usize = 32;
+
 /// TraceId is an 16-byte value which uniquely identifies a given trace
 /// The actual `u128` value is wrapped in a tuple struct in order to leverage the newtype pattern
 #[cfg_attr(feature = "serialize", derive(Deserialize, Serialize))]
@@ -68,6 +74,227 @@ impl SpanId {
     }
 }
 
+/// TraceState carries system-specific configuration data, represented as an
+/// ordered list of key-value pairs, as defined by the [w3c `tracestate`
+/// header]. It allows multiple tracing systems to participate in the same
+/// trace.
+///
+/// `TraceState`s are immutable, [`insert`] and [`delete`] return a new
+/// `TraceState` with the modification applied. Updated entries move to the
+/// front of the list, as required by the specification.
+///
+/// # Examples
+///
+/// ```
+/// use opentelemetry::api::TraceState;
+///
+/// let state: TraceState = "rojo=00f067aa0ba902b7,congo=t61rcWkgMzE".parse().unwrap();
+/// let updated = state.insert("congo", "ucfJifl5GOE").unwrap();
+///
+/// assert_eq!(updated.get("congo"), Some("ucfJifl5GOE"));
+/// assert_eq!(updated.header(), "congo=ucfJifl5GOE,rojo=00f067aa0ba902b7");
+/// ```
+///
+/// [w3c `tracestate` header]: https://www.w3.org/TR/trace-context/#tracestate-header
+/// [`insert`]: struct.TraceState.html#method.insert
+/// [`delete`]: struct.TraceState.html#method.delete
+#[cfg_attr(feature = "serialize", derive(Deserialize, Serialize))]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TraceState(VecDeque<(String, String)>);
+
+impl TraceState {
+    /// Creates a new `TraceState` from the given key-value pairs, in order.
+    pub fn from_key_value<T, K, V>(entries: T) -> Result<Self, TraceStateError>
+    where
+        T: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        let mut list = VecDeque::new();
+        for (key, value) in entries {
+            let (key, value) = (key.into(), value.into());
+            if !valid_key(&key) {
+                return Err(TraceStateError::InvalidKey(key));
+            }
+            if !valid_value(&value) {
+                return Err(TraceStateError::InvalidValue(value));
+            }
+            if list.iter().any(|(existing, _)| existing == &key) {
+                return Err(TraceStateError::DuplicateKey(key));
+            }
+            list.push_back((key, value));
+        }
+
+        if list.len() > TRACE_STATE_MAX_MEMBERS {
+            return Err(TraceStateError::TooManyMembers(list.len()));
+        }
+
+        Ok(TraceState(list))
+    }
+
+    /// Returns the value for the given key, if present.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(existing, _)| existing == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns a copy of this `TraceState` with the given entry added to the
+    /// front of the list, replacing any existing value for the key.
+    ///
+    /// If the list is full, the last entry is removed to make room.
+    pub fn insert<K, V>(&self, key: K, value: V) -> Result<Self, TraceStateError>
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        let (key, value) = (key.into(), value.into());
+        if !valid_key(&key) {
+            return Err(TraceStateError::InvalidKey(key));
+        }
+        if !valid_value(&value) {
+            return Err(TraceStateError::InvalidValue(value));
+        }
+
+        let mut list = self.0.clone();
+        list.retain(|(existing, _)| existing != &key);
+        list.push_front((key, value));
+        list.truncate(TRACE_STATE_MAX_MEMBERS);
+
+        Ok(TraceState(list))
+    }
+
+    /// Returns a copy of this `TraceState` without the entry for the given key.
+    pub fn delete<K: Into<String>>(&self, key: K) -> Result<Self, TraceStateError> {
+        let key = key.into();
+        if !valid_key(&key) {
+            return Err(TraceStateError::InvalidKey(key));
+        }
+
+        let mut list = self.0.clone();
+        list.retain(|(existing, _)| existing != &key);
+
+        Ok(TraceState(list))
+    }
+
+    /// Returns true if the `TraceState` has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Encodes the `TraceState` as a `tracestate` header value.
+    pub fn header(&self) -> String {
+        self.0
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+impl FromStr for TraceState {
+    type Err = TraceStateError;
+
+    /// Parses a `tracestate` header value. Empty list members are ignored.
+    fn from_str(header: &str) -> Result<Self, Self::Err> {
+        let entries = header
+            .split(',')
+            .map(str::trim)
+            .filter(|member| !member.is_empty())
+            .map(|member| {
+                let mut parts = member.splitn(2, '=');
+                match (parts.next(), parts.next()) {
+                    (Some(key), Some(value)) => Ok((key, value)),
+                    _ => Err(TraceStateError::InvalidMember(member.to_string())),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        TraceState::from_key_value(entries)
+    }
+}
+
+/// Validates a `tracestate` key, which is either a simple key or a
+/// `tenant@system` multi-tenant key.
+fn valid_key(key: &str) -> bool {
+    fn valid_chars(part: &str) -> bool {
+        part.bytes().all(|b| {
+            b.is_ascii_lowercase()
+                || b.is_ascii_digit()
+                || b == b'_'
+                || b == b'-'
+                || b == b'*'
+                || b == b'/'
+        })
+    }
+
+    match key.find('@') {
+        None => {
+            key.len() <= 256 && matches!(key.bytes().next(), Some(b'a'..=b'z')) && valid_chars(key)
+        }
+        Some(idx) => {
+            let (tenant, system) = (&key[..idx], &key[idx + 1..]);
+            tenant.len() <= 241
+                && matches!(tenant.bytes().next(), Some(b'a'..=b'z') | Some(b'0'..=b'9'))
+                && valid_chars(tenant)
+                && system.len() <= 14
+                && matches!(system.bytes().next(), Some(b'a'..=b'z'))
+                && valid_chars(system)
+        }
+    }
+}
+
+/// Validates a `tracestate` value: up to 256 printable ASCII characters
+/// other than `,` and `=`, not ending in a space.
+fn valid_value(value: &str) -> bool {
+    !value.is_empty()
+        && value.len() <= 256
+        && !value.ends_with(' ')
+        && value
+            .bytes()
+            .all(|b| (0x20..=0x7e).contains(&b) && b != b',' && b != b'=')
+}
+
+/// Errors returned when building or modifying a `TraceState`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TraceStateError {
+    /// The key does not match the w3c key format.
+    InvalidKey(String),
+    /// The value does not match the w3c value format.
+    InvalidValue(String),
+    /// The list member is not a `key=value` pair.
+    InvalidMember(String),
+    /// The key appears more than once in the list.
+    DuplicateKey(String),
+    /// The list has more members than the w3c maximum of 32.
+    TooManyMembers(usize),
+}
+
+impl fmt::Display for TraceStateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TraceStateError::InvalidKey(key) => write!(f, "invalid tracestate key: {:?}", key),
+            TraceStateError::InvalidValue(value) => {
+                write!(f, "invalid tracestate value: {:?}", value)
+            }
+            TraceStateError::InvalidMember(member) => {
+                write!(f, "invalid tracestate list member: {:?}", member)
+            }
+            TraceStateError::DuplicateKey(key) => {
+                write!(f, "duplicate tracestate key: {:?}", key)
+            }
+            TraceStateError::TooManyMembers(count) => write!(
+                f,
+                "tracestate has {} list members, at most {} are allowed",
+                count, TRACE_STATE_MAX_MEMBERS
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TraceStateError {}
+
 /// Immutable portion of a `Span` which can be serialized and propagated.
 #[cfg_attr(feature = "serialize", derive(Deserialize, Serialize))]
 #[derive(Clone, Debug, PartialEq)]
@@ -76,6 +303,7 @@ pub struct SpanContext {
     span_id: SpanId,
     trace_flags: u8,
     is_remote: bool,
+    trace_state: TraceState,
 }
 
 impl SpanContext {
@@ -91,6 +319,15 @@ impl SpanContext {
             span_id,
             trace_flags,
             is_remote,
+            trace_state: TraceState::default(),
+        }
+    }
+
+    /// Returns a copy of this `SpanContext` with the given `TraceState`.
+    pub fn with_trace_state(self, trace_state: TraceState) -> Self {
+        SpanContext {
+            trace_state,
+            ..self
         }
     }
 
@@ -125,4 +362,78 @@ impl SpanContext {
     pub fn is_sampled(&self) -> bool {
         (self.trace_flags & TRACE_FLAGS_BIT_MASK_SAMPLED) == TRACE_FLAGS_BIT_MASK_SAMPLED
     }
+
+    /// Returns the system-specific `TraceState` of this context.
+    pub fn trace_state(&self) -> &TraceState {
+        &self.trace_state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_encodes_trace_state() {
+        let state: TraceState = "rojo=00f067aa0ba902b7, ,congo=t61rcWkgMzE,fw529a3039@dt=abc"
+            .parse()
+            .unwrap();
+        assert_eq!(state.get("rojo"), Some("00f067aa0ba902b7"));
+        assert_eq!(state.get("fw529a3039@dt"), Some("abc"));
+        assert_eq!(
+            state.header(),
+            "rojo=00f067aa0ba902b7,congo=t61rcWkgMzE,fw529a3039@dt=abc"
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_trace_state() {
+        for header in &[
+            "Rojo=1",
+            "rojo",
+            "rojo=a,rojo=b",
+            "rojo=a=b",
+            "rojo=a\tb",
+            "1rojo=a",
+            "@dt=a",
+            "tenant@=a",
+            "tenant@system_name_too_long=a",
+        ] {
+            assert!(header.parse::<TraceState>().is_err(), "{}", header);
+        }
+
+        let too_many = (0..33).map(|i| format!("k{}=v", i)).collect::<Vec<_>>();
+        assert_eq!(
+            too_many.join(",").parse::<TraceState>(),
+            Err(TraceStateError::TooManyMembers(33))
+        );
+    }
+
+    #[test]
+    fn insert_and_delete_trace_state() {
+        let state = TraceState::from_key_value(vec![("a", "1"), ("b", "2")]).unwrap();
+
+        let inserted = state.insert("c", "3").unwrap();
+        assert_eq!(inserted.header(), "c=3,a=1,b=2");
+
+        let updated = inserted.insert("b", "4").unwrap();
+        assert_eq!(updated.header(), "b=4,c=3,a=1");
+
+        let deleted = updated.delete("c").unwrap();
+        assert_eq!(deleted.header(), "b=4,a=1");
+
+        assert!(state.insert("B", "1").is_err());
+        assert!(state.insert("b", "1,2").is_err());
+        // The original state is unmodified
+        assert_eq!(state.header(), "a=1,b=2");
+    }
+
+    #[test]
+    fn insert_drops_last_member_when_full() {
+        let full = TraceState::from_key_value((0..32).map(|i| (format!("k{}", i), "v"))).unwrap();
+        let inserted = full.insert("new", "v").unwrap();
+        assert_eq!(inserted.get("new"), Some("v"));
+        assert_eq!(inserted.get("k31"), None);
+        assert_eq!(inserted.get("k30"), Some("v"));
+    }
 }
@@ -43,8 +43,31 @@ pub trait SpanProcessor: Send + Sync + std::fmt::Debug {
     fn on_start(&self, span: Arc<exporter::trace::SpanData>);
     /// `on_end` method is invoked when a `Span` is ended.
     fn on_end(&self, span: Arc<exporter::trace::SpanData>);
+    /// Export all ended spans that have not yet been exported, blocking until
+    /// they are exported or the processor's timeout elapses. Processors that
+    /// do not buffer spans need not implement it.
+    fn force_flush(&self) {}
     /// Shutdown is invoked when SDK shuts down. Use this call to cleanup any
     /// processor data. No calls to `on_start` and `on_end` method is invoked
-    /// after `shutdown` call is made.
+    /// after `shutdown` call is made. Pending spans are exported before the
+    /// exporter is shut down.
     fn shutdown(&self);
 }
+
+impl<T: SpanProcessor + ?Sized> SpanProcessor for Arc<T> {
+    fn on_start(&self, span: Arc<exporter::trace::SpanData>) {
+        (**self).on_start(span)
+    }
+
+    fn on_end(&self, span: Arc<exporter::trace::SpanData>) {
+        (**self).on_end(span)
+    }
+
+    fn force_flush(&self) {
+        (**self).force_flush()
+    }
+
+    fn shutdown(&self) {
+        (**self).shutdown()
+    }
+}
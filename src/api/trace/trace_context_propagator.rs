@@ -14,15 +14,23 @@
 //!    - parent-id
 //!    - trace-flags
 //!
+//! The optional `tracestate` header carries vendor-specific trace
+//! identification data as an ordered list of key-value pairs, and is
+//! propagated alongside `traceparent` as the context's [`TraceState`].
+//!
+//! `tracestate: rojo=00f067aa0ba902b7,congo=t61rcWkgMzE`
+//!
 //! See the [w3c trace-context docs] for more details.
 //!
 //! [w3c trace-context docs]: https://w3c.github.io/trace-context/
+//! [`TraceState`]: ../span_context/struct.TraceState.html
 
 use crate::{api, api::TraceContextExt};
 
 static SUPPORTED_VERSION: u8 = 0;
 static MAX_VERSION: u8 = 254;
 static TRACEPARENT_HEADER: &str = "traceparent";
+static TRACESTATE_HEADER: &str = "tracestate";
 
 /// Extracts and injects `SpanContext`s into `Carrier`s using the
 /// trace-context format.
@@ -70,8 +78,16 @@ impl TraceContextPropagator {
         // Build trace flags
         let trace_flags = opts & !api::TRACE_FLAGS_UNUSED;
 
+        // Parse trace state, an invalid header is discarded rather than
+        // invalidating the whole context
+        let trace_state = carrier
+            .get(TRACESTATE_HEADER)
+            .and_then(|header| header.parse().ok())
+            .unwrap_or_default();
+
         // create context
-        let span_context = api::SpanContext::new(trace_id, span_id, trace_flags, true);
+        let span_context = api::SpanContext::new(trace_id, span_id, trace_flags, true)
+            .with_trace_state(trace_state);
 
         // Ensure span is valid
         if !span_context.is_valid() {
@@ -95,7 +111,12 @@ impl api::HttpTextFormat for TraceContextPropagator {
                 span_context.span_id().to_u64(),
                 span_context.trace_flags() & api::TRACE_FLAG_SAMPLED
             );
-            carrier.set(TRACEPARENT_HEADER, header_value)
+            carrier.set(TRACEPARENT_HEADER, header_value);
+
+            let trace_state = span_context.trace_state();
+            if !trace_state.is_empty() {
+                carrier.set(TRACESTATE_HEADER, trace_state.header());
+            }
         }
     }
 
@@ -153,6 +174,34 @@ mod tests {
         }
     }
 
+    #[test]
+    fn extract_w3c_tracestate() {
+        let propagator = TraceContextPropagator::new();
+        let traceparent = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+        for &(header, expected) in &[
+            (
+                "rojo=00f067aa0ba902b7,congo=t61rcWkgMzE",
+                "rojo=00f067aa0ba902b7,congo=t61rcWkgMzE",
+            ),
+            (
+                "rojo=00f067aa0ba902b7 , ,congo=t61rcWkgMzE",
+                "rojo=00f067aa0ba902b7,congo=t61rcWkgMzE",
+            ),
+            ("Invalid=value", ""),
+            ("rojo=1,rojo=2", ""),
+        ] {
+            let mut carrier = HashMap::new();
+            carrier.insert(TRACEPARENT_HEADER.to_string(), traceparent.to_owned());
+            carrier.insert(TRACESTATE_HEADER.to_string(), header.to_owned());
+
+            let cx = propagator.extract(&carrier);
+            let span_context = cx.remote_span_context().unwrap();
+            assert!(span_context.is_valid());
+            assert_eq!(span_context.trace_state().header(), expected);
+        }
+    }
+
     #[derive(Debug)]
     struct TestSpan(api::SpanContext);
     impl api::Span for TestSpan {
@@ -192,4 +241,33 @@ mod tests {
             )
         }
     }
+
+    #[test]
+    fn inject_w3c_tracestate() {
+        let propagator = TraceContextPropagator::new();
+        let span_context = api::SpanContext::new(
+            api::TraceId::from_u128(0x4bf9_2f35_77b3_4da6_a3ce_929d_0e0e_4736),
+            api::SpanId::from_u64(0x00f0_67aa_0ba9_02b7),
+            1,
+            true,
+        );
+
+        let mut carrier = HashMap::new();
+        propagator.inject_context(
+            &api::Context::current_with_span(TestSpan(span_context.clone())),
+            &mut carrier,
+        );
+        assert_eq!(Carrier::get(&carrier, TRACESTATE_HEADER), None);
+
+        let trace_state = "rojo=00f067aa0ba902b7,congo=t61rcWkgMzE".parse().unwrap();
+        let mut carrier = HashMap::new();
+        propagator.inject_context(
+            &api::Context::current_with_span(TestSpan(span_context.with_trace_state(trace_state))),
+            &mut carrier,
+        );
+        assert_eq!(
+            Carrier::get(&carrier, TRACESTATE_HEADER),
+            Some("rojo=00f067aa0ba902b7,congo=t61rcWkgMzE")
+        );
+    }
 }
@@ -1,2 +1,305 @@
 //! Metric exporters
+//!
+//! The metrics SDK aggregates the measurements recorded by instruments and
+//! periodically checkpoints them into a [`CheckpointSet`], which is handed to
+//! a [`MetricExporter`] to be encoded and transmitted. Pull based exporters
+//! such as Prometheus collect a checkpoint from the [`sdk::Meter`] when they
+//! are scraped instead.
+//!
+//! [`CheckpointSet`]: struct.CheckpointSet.html
+//! [`MetricExporter`]: trait.MetricExporter.html
+//! [`sdk::Meter`]: ../../sdk/metrics/meter/struct.Meter.html
+use crate::{api, sdk};
+use std::cmp;
+use std::fmt;
+use std::ops;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+#[cfg(feature = "prometheus")]
 pub mod prometheus;
+#[cfg(feature = "statsd")]
+pub mod statsd;
+pub mod stdout;
+
+pub use crate::exporter::ExportResult;
+
+/// `MetricExporter` defines the interface that protocol-specific exporters
+/// must implement so that they can be plugged into the metrics SDK with a
+/// [`PushController`].
+///
+/// [`PushController`]: ../../sdk/metrics/controller/struct.PushController.html
+pub trait MetricExporter: Send + Sync + fmt::Debug {
+    /// Exports a checkpoint of the aggregated metrics.
+    ///
+    /// This function will never be called concurrently for the same exporter
+    /// instance, and must not block indefinitely.
+    fn export(&self, checkpoint_set: &CheckpointSet) -> ExportResult;
+
+    /// Shuts down the exporter. Called once when the controller driving the
+    /// exporter is shut down.
+    fn shutdown(&self);
+}
+
+/// Whether exported aggregations cover the lifetime of the instrument or only
+/// the interval since the previous checkpoint.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportKind {
+    /// Aggregations accumulate across checkpoints.
+    Cumulative,
+    /// Aggregations are reset after every checkpoint.
+    Delta,
+}
+
+/// The kind of instrument a metric was recorded with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum InstrumentKind {
+    /// A `Counter` instrument.
+    Counter,
+    /// A `Gauge` instrument.
+    Gauge,
+    /// A `Measure` instrument.
+    Measure,
+    /// An observer reporting values where the distribution or the last value
+    /// is of interest.
+    ValueObserver,
+    /// An observer reporting a monotonic sum.
+    SumObserver,
+    /// An observer reporting a sum that can go up or down.
+    UpDownSumObserver,
+}
+
+/// The kind of number recorded by an instrument.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum NumberKind {
+    /// Values are recorded as `i64`.
+    I64,
+    /// Values are recorded as `f64`.
+    F64,
+}
+
+impl NumberKind {
+    /// The zero value of this kind.
+    pub fn zero(self) -> Number {
+        match self {
+            NumberKind::I64 => Number::I64(0),
+            NumberKind::F64 => Number::F64(0.0),
+        }
+    }
+
+    /// Interpret a recorded `MeasurementValue` as this kind of number.
+    pub fn number(self, value: api::MeasurementValue) -> Number {
+        match self {
+            NumberKind::I64 => Number::I64(value.into_i64()),
+            NumberKind::F64 => Number::F64(value.into_f64()),
+        }
+    }
+}
+
+/// A recorded or aggregated metric value.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Number {
+    /// An integer value.
+    I64(i64),
+    /// A floating point value.
+    F64(f64),
+}
+
+impl Number {
+    /// Convert the number to an `f64`, the representation used by most
+    /// exposition formats.
+    pub fn to_f64(self) -> f64 {
+        match self {
+            Number::I64(value) => value as f64,
+            Number::F64(value) => value,
+        }
+    }
+
+    /// Whether the number is lower than zero.
+    pub fn is_negative(self) -> bool {
+        match self {
+            Number::I64(value) => value < 0,
+            Number::F64(value) => value < 0.0,
+        }
+    }
+
+    /// Whether the number is a floating point `NaN`.
+    pub fn is_nan(self) -> bool {
+        match self {
+            Number::I64(_) => false,
+            Number::F64(value) => value.is_nan(),
+        }
+    }
+
+    /// The smaller of two numbers.
+    pub fn min(self, other: Number) -> Number {
+        if other < self {
+            other
+        } else {
+            self
+        }
+    }
+
+    /// The larger of two numbers.
+    pub fn max(self, other: Number) -> Number {
+        if other > self {
+            other
+        } else {
+            self
+        }
+    }
+}
+
+impl PartialOrd for Number {
+    /// Compare two numbers of the same kind, integers are compared exactly.
+    /// Numbers of different kinds are not ordered.
+    fn partial_cmp(&self, other: &Number) -> Option<cmp::Ordering> {
+        match (self, other) {
+            (Number::I64(a), Number::I64(b)) => a.partial_cmp(b),
+            (Number::F64(a), Number::F64(b)) => a.partial_cmp(b),
+            _ => None,
+        }
+    }
+}
+
+impl ops::Add for Number {
+    type Output = Number;
+
+    /// Add two numbers of the same kind. Integer additions wrap on overflow.
+    fn add(self, other: Number) -> Number {
+        match (self, other) {
+            (Number::I64(a), Number::I64(b)) => Number::I64(a.wrapping_add(b)),
+            (a, b) => Number::F64(a.to_f64() + b.to_f64()),
+        }
+    }
+}
+
+impl ops::Sub for Number {
+    type Output = Number;
+
+    /// Subtract two numbers of the same kind. Integer subtractions wrap on
+    /// overflow.
+    fn sub(self, other: Number) -> Number {
+        match (self, other) {
+            (Number::I64(a), Number::I64(b)) => Number::I64(a.wrapping_sub(b)),
+            (a, b) => Number::F64(a.to_f64() - b.to_f64()),
+        }
+    }
+}
+
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Number::I64(value) => value.fmt(f),
+            Number::F64(value) => value.fmt(f),
+        }
+    }
+}
+
+/// Describes an instrument, as created by a `Meter`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Descriptor {
+    /// The instrument name
+    pub name: String,
+    /// The name of the `Meter` the instrument was created with
+    pub meter_name: &'static str,
+    /// The kind of instrument
+    pub instrument_kind: InstrumentKind,
+    /// The kind of number recorded by the instrument
+    pub number_kind: NumberKind,
+    /// The instrument description
+    pub description: String,
+    /// The unit of recorded values
+    pub unit: api::Unit,
+    /// The recommended label keys
+    pub keys: Vec<api::Key>,
+    /// Whether the sum of a `Counter`, or the value of a `Gauge`, never
+    /// decreases
+    pub monotonic: bool,
+    /// Whether negative values are rejected
+    pub absolute: bool,
+    /// The aggregation requested for a `Measure`, overriding the aggregator
+    /// selector of the `Meter`
+    pub aggregation: Option<api::MeasureAggregation>,
+}
+
+/// The aggregated value of an instrument for a single label set.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Aggregation {
+    /// The sum of all recorded values.
+    Sum(Number),
+    /// The most recently recorded value.
+    LastValue {
+        /// The recorded value
+        value: Number,
+        /// The time the value was recorded
+        timestamp: SystemTime,
+    },
+    /// Summary statistics of the recorded values.
+    MinMaxSumCount {
+        /// The smallest recorded value
+        min: Number,
+        /// The largest recorded value
+        max: Number,
+        /// The sum of recorded values
+        sum: Number,
+        /// The number of recorded values
+        count: u64,
+    },
+    /// The distribution of recorded values over explicit buckets.
+    Histogram {
+        /// The inclusive upper bounds of all but the last bucket, in
+        /// increasing order
+        boundaries: Vec<f64>,
+        /// The number of values in each bucket, one more than the number of
+        /// boundaries
+        counts: Vec<u64>,
+        /// The most recent exemplar of each bucket
+        exemplars: Vec<Option<Exemplar>>,
+        /// The sum of recorded values
+        sum: Number,
+        /// The number of recorded values
+        count: u64,
+    },
+    /// A quantile sketch of the recorded values.
+    Sketch(sdk::metrics::sketch::DDSketch),
+}
+
+/// A measurement recorded while a sampled span was active, linking the
+/// aggregated values to a trace.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Exemplar {
+    /// The recorded value
+    pub value: Number,
+    /// The time the value was recorded
+    pub timestamp: SystemTime,
+    /// The trace id of the active span
+    pub trace_id: api::TraceId,
+    /// The span id of the active span
+    pub span_id: api::SpanId,
+}
+
+/// The aggregation of one instrument for one label set.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Record {
+    /// The instrument the aggregation belongs to
+    pub descriptor: Arc<Descriptor>,
+    /// The labels the values were recorded with
+    pub labels: sdk::LabelSet,
+    /// The aggregated value
+    pub aggregation: Aggregation,
+}
+
+/// All records collected by a `Meter` in a single checkpoint.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CheckpointSet {
+    /// The resource the metrics were recorded by
+    pub resource: Arc<sdk::Resource>,
+    /// The time the checkpoint was taken
+    pub timestamp: SystemTime,
+    /// Whether the records accumulate across checkpoints or only cover the
+    /// interval since the previous checkpoint
+    pub export_kind: ExportKind,
+    /// The collected records
+    pub records: Vec<Record>,
+}
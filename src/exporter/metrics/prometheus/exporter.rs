@@ -0,0 +1,544 @@
+//! # Prometheus Pull Exporter
+//!
+//! Serves the metrics of a `Meter` or `MeterProvider`, along with any other
+//! metrics of its [`Registry`], in the Prometheus text format on an embedded
+//! HTTP endpoint, so they can be scraped by a Prometheus server. Scrapes
+//! accepting the OpenMetrics text format are served in that format instead,
+//! with the exemplars of histogram buckets.
+//!
+//! ```no_run
+//! use opentelemetry::api::{Meter, MetricOptions};
+//! use opentelemetry::exporter::metrics::prometheus;
+//! use opentelemetry::sdk;
+//!
+//! fn main() -> Result<(), prometheus::Error> {
+//!     let meter = sdk::Meter::new("example");
+//!     let _exporter = prometheus::Exporter::builder(meter.clone())
+//!         .with_address("0.0.0.0:9464".parse().unwrap())
+//!         .with_registry(prometheus::Registry::new())
+//!         .init()?;
+//!
+//!     let _counter = meter.new_i64_counter("requests", MetricOptions::default());
+//!
+//!     // Metrics are now served on http://0.0.0.0:9464/metrics
+//!     Ok(())
+//! }
+//! ```
+//!
+//! [`Registry`]: https://docs.rs/prometheus/0.7.0/prometheus/struct.Registry.html
+use super::{open_metrics, MeterCollector};
+use crate::sdk;
+use prometheus::core::{Collector, Desc};
+use prometheus::{proto, Encoder, Registry, TextEncoder, TEXT_FORMAT};
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Default address the metrics endpoint listens on.
+const DEFAULT_ADDRESS: &str = "0.0.0.0:9464";
+/// Default path the metrics are served on.
+const DEFAULT_PATH: &str = "/metrics";
+/// Maximum time spent reading a scrape request and writing its response
+/// before the connection is dropped.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+/// Maximum size of the request line and headers of a scrape request.
+const MAX_HEADER_SIZE: u64 = 8 * 1024;
+
+/// Prometheus pull exporter, serving a `Registry` over HTTP until shut down
+/// or dropped.
+pub struct Exporter {
+    registry: Registry,
+    registration: Option<Registration>,
+    local_addr: SocketAddr,
+    is_shutdown: Arc<AtomicBool>,
+    server: Option<thread::JoinHandle<()>>,
+}
+
+impl fmt::Debug for Exporter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Exporter")
+            .field("local_addr", &self.local_addr)
+            .field("is_shutdown", &self.is_shutdown)
+            .finish()
+    }
+}
+
+impl Exporter {
+    /// Create a new builder for an exporter serving the metrics of `meter`.
+    pub fn builder(meter: sdk::Meter) -> Builder {
+        Builder::new(MeterCollector::new(meter))
+    }
+
+    /// Create a new builder for an exporter serving the metrics of all meters
+    /// of `provider`.
+    pub fn from_provider(provider: sdk::MeterProvider) -> Builder {
+        Builder::new(MeterCollector::from_provider(provider))
+    }
+
+    /// The registry served by this exporter. Other prometheus metrics
+    /// registered in it are exported alongside the meter's.
+    pub fn registry(&self) -> &Registry {
+        &self.registry
+    }
+
+    /// The address the metrics endpoint is bound to.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Stop serving metrics, waiting for any in progress scrape to complete,
+    /// and unregister the meter or provider from the registry.
+    pub fn shutdown(&mut self) {
+        if let Some(server) = self.server.take() {
+            self.is_shutdown.store(true, Ordering::SeqCst);
+            // Wake the server thread blocked on `accept`
+            let _ = TcpStream::connect(wake_addr(self.local_addr));
+            let _ = server.join();
+        }
+        if let Some(registration) = self.registration.take() {
+            let _ = self.registry.unregister(Box::new(registration));
+        }
+    }
+}
+
+impl Drop for Exporter {
+    fn drop(&mut self) {
+        self.shutdown()
+    }
+}
+
+/// Builder for configuring a Prometheus `Exporter`.
+pub struct Builder {
+    collector: prometheus::Result<MeterCollector>,
+    address: SocketAddr,
+    path: String,
+    registry: Option<Registry>,
+}
+
+impl fmt::Debug for Builder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Builder")
+            .field("collector", &self.collector)
+            .field("address", &self.address)
+            .field("path", &self.path)
+            .field("custom_registry", &self.registry.is_some())
+            .finish()
+    }
+}
+
+impl Builder {
+    fn new(collector: prometheus::Result<MeterCollector>) -> Self {
+        Builder {
+            collector,
+            address: DEFAULT_ADDRESS.parse().unwrap(),
+            path: DEFAULT_PATH.to_string(),
+            registry: None,
+        }
+    }
+
+    /// Assign the address the metrics endpoint listens on. Defaults to
+    /// `0.0.0.0:9464`.
+    pub fn with_address(self, address: SocketAddr) -> Self {
+        Builder { address, ..self }
+    }
+
+    /// Assign the path the metrics are served on. Defaults to `/metrics`.
+    pub fn with_path<T: Into<String>>(self, path: T) -> Self {
+        Builder {
+            path: path.into(),
+            ..self
+        }
+    }
+
+    /// Serve a custom registry instead of the global `default_registry`.
+    pub fn with_registry(self, registry: Registry) -> Self {
+        Builder {
+            registry: Some(registry),
+            ..self
+        }
+    }
+
+    /// Bind the metrics endpoint, register the meter or provider in the
+    /// registry, then start serving the endpoint on a background thread.
+    pub fn init(self) -> prometheus::Result<Exporter> {
+        let registry = self
+            .registry
+            .unwrap_or_else(|| prometheus::default_registry().clone());
+        let collector = self.collector?;
+        let listener = TcpListener::bind(self.address)?;
+        let local_addr = listener.local_addr()?;
+        let exemplars = collector.exemplars();
+        let registration = Registration(collector.desc().into_iter().cloned().collect());
+        registry.register(Box::new(collector))?;
+        let is_shutdown = Arc::new(AtomicBool::new(false));
+
+        let server = {
+            let registry = registry.clone();
+            let is_shutdown = is_shutdown.clone();
+            let path = self.path;
+            thread::Builder::new()
+                .name("opentelemetry-prometheus".to_string())
+                .spawn(move || serve(listener, &registry, &exemplars, &path, &is_shutdown))?
+        };
+
+        Ok(Exporter {
+            registry,
+            registration: Some(registration),
+            local_addr,
+            is_shutdown,
+            server: Some(server),
+        })
+    }
+}
+
+/// The descriptors of a registered collector, which identify it when it is
+/// unregistered.
+struct Registration(Vec<Desc>);
+
+impl Collector for Registration {
+    fn desc(&self) -> Vec<&Desc> {
+        self.0.iter().collect()
+    }
+
+    fn collect(&self) -> Vec<proto::MetricFamily> {
+        Vec::new()
+    }
+}
+
+/// A connection reading and writing until a deadline, so that a slow client
+/// cannot hold the server thread for longer than `REQUEST_TIMEOUT`.
+struct Connection {
+    stream: TcpStream,
+    deadline: Instant,
+}
+
+impl Connection {
+    fn new(stream: TcpStream) -> Self {
+        Connection {
+            stream,
+            deadline: Instant::now() + REQUEST_TIMEOUT,
+        }
+    }
+
+    fn try_clone(&self) -> io::Result<Self> {
+        Ok(Connection {
+            stream: self.stream.try_clone()?,
+            deadline: self.deadline,
+        })
+    }
+
+    /// The time left before the deadline, failing once it has passed.
+    fn remaining(&self) -> io::Result<Duration> {
+        let now = Instant::now();
+        if now >= self.deadline {
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "scrape request deadline exceeded",
+            ));
+        }
+        Ok(self.deadline - now)
+    }
+}
+
+impl Read for Connection {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.stream.set_read_timeout(Some(self.remaining()?))?;
+        self.stream.read(buf)
+    }
+}
+
+impl Write for Connection {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.stream.set_write_timeout(Some(self.remaining()?))?;
+        self.stream.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+/// Accept scrape requests until shut down.
+fn serve(
+    listener: TcpListener,
+    registry: &Registry,
+    exemplars: &Mutex<open_metrics::Exemplars>,
+    path: &str,
+    is_shutdown: &AtomicBool,
+) {
+    for stream in listener.incoming() {
+        if is_shutdown.load(Ordering::SeqCst) {
+            break;
+        }
+        if let Ok(stream) = stream {
+            // Failed scrapes only affect the scraping client
+            let _ = handle(Connection::new(stream), registry, exemplars, path);
+        }
+    }
+}
+
+/// Respond to a single scrape request.
+fn handle(
+    mut stream: Connection,
+    registry: &Registry,
+    exemplars: &Mutex<open_metrics::Exemplars>,
+    path: &str,
+) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?).take(MAX_HEADER_SIZE);
+    let mut request_line = String::new();
+    read_line(&mut reader, &mut request_line)?;
+    // Drain the request headers, looking for the accepted formats
+    let mut header = String::new();
+    let mut open_metrics = false;
+    while read_line(&mut reader, &mut header)? > 2 {
+        let mut parts = header.splitn(2, ':');
+        if let (Some(name), Some(value)) = (parts.next(), parts.next()) {
+            if name.trim().eq_ignore_ascii_case("accept") {
+                open_metrics = value.contains("application/openmetrics-text");
+            }
+        }
+        header.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or("");
+    let target = parts.next().unwrap_or("");
+    let target_path = target.split('?').next().unwrap_or("");
+
+    if target_path != path {
+        return respond(&mut stream, "404 Not Found", "text/plain", b"Not Found");
+    }
+    if method != "GET" {
+        return respond(
+            &mut stream,
+            "405 Method Not Allowed",
+            "text/plain",
+            b"Method Not Allowed",
+        );
+    }
+
+    let families = registry.gather();
+    let mut body = Vec::new();
+    let (content_type, encoded) = if open_metrics {
+        // Gathering the registry collected the exemplars
+        let exemplars = exemplars
+            .lock()
+            .map(|exemplars| exemplars.clone())
+            .unwrap_or_default();
+        let encoded = open_metrics::encode(&families, &exemplars, &mut body).is_ok();
+        (open_metrics::CONTENT_TYPE, encoded)
+    } else {
+        let encoded = TextEncoder::new().encode(&families, &mut body).is_ok();
+        (TEXT_FORMAT, encoded)
+    };
+    if !encoded {
+        return respond(
+            &mut stream,
+            "500 Internal Server Error",
+            "text/plain",
+            b"Internal Server Error",
+        );
+    }
+    respond(&mut stream, "200 OK", content_type, &body)
+}
+
+/// Read a complete line of the request head, failing if it is cut short by
+/// the end of the stream or by `MAX_HEADER_SIZE`.
+fn read_line<R: BufRead>(reader: &mut R, line: &mut String) -> io::Result<usize> {
+    let len = reader.read_line(line)?;
+    if !line.ends_with('\n') {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "incomplete or oversized scrape request",
+        ));
+    }
+    Ok(len)
+}
+
+fn respond(
+    stream: &mut Connection,
+    status: &str,
+    content_type: &str,
+    body: &[u8],
+) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    )?;
+    stream.write_all(body)?;
+    stream.flush()
+}
+
+/// The address to connect to in order to reach a listener bound to `addr`.
+fn wake_addr(addr: SocketAddr) -> SocketAddr {
+    match addr.ip() {
+        IpAddr::V4(ip) if ip.is_unspecified() => {
+            SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), addr.port())
+        }
+        IpAddr::V6(ip) if ip.is_unspecified() => {
+            SocketAddr::new(IpAddr::V6(Ipv6Addr::LOCALHOST), addr.port())
+        }
+        _ => addr,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{self, Counter, Meter};
+    use std::io::Read;
+
+    fn get(addr: SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    fn exporter(meter: sdk::Meter, registry: Registry) -> Exporter {
+        Exporter::builder(meter)
+            .with_address("127.0.0.1:0".parse().unwrap())
+            .with_path("/custom")
+            .with_registry(registry)
+            .init()
+            .unwrap()
+    }
+
+    #[test]
+    fn serves_meter_and_custom_registry() {
+        let registry = Registry::new();
+        let counter = prometheus::IntCounter::new("test_counter", "test counter").unwrap();
+        registry.register(Box::new(counter.clone())).unwrap();
+        counter.inc_by(3);
+
+        let meter = sdk::Meter::new("exporter");
+        let exporter = exporter(meter.clone(), registry);
+        meter
+            .new_i64_counter("requests", api::MetricOptions::default())
+            .add(5, &meter.labels(vec![]));
+
+        let response = get(exporter.local_addr(), "/custom?format=text");
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains("test_counter 3"));
+        assert!(response.contains("exporter_requests 5"));
+
+        let response = get(exporter.local_addr(), "/metrics");
+        assert!(response.starts_with("HTTP/1.1 404 Not Found"));
+    }
+
+    #[test]
+    fn serves_open_metrics_when_accepted() {
+        let meter = sdk::Meter::new("exporter");
+        let exporter = exporter(meter.clone(), Registry::new());
+        meter
+            .new_i64_counter("requests", api::MetricOptions::default())
+            .add(5, &meter.labels(vec![]));
+
+        let mut stream = TcpStream::connect(exporter.local_addr()).unwrap();
+        write!(
+            stream,
+            "GET /custom HTTP/1.1\r\nAccept: application/openmetrics-text; version=1.0.0\r\n\r\n"
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.contains(open_metrics::CONTENT_TYPE));
+        assert!(response.contains("exporter_requests_total 5.0"));
+        assert!(response.ends_with("# EOF\n"));
+    }
+
+    #[test]
+    fn shutdown_stops_serving() {
+        let mut exporter = exporter(sdk::Meter::new("shutdown"), Registry::new());
+        let addr = exporter.local_addr();
+        exporter.shutdown();
+
+        assert!(TcpStream::connect(addr).is_err());
+    }
+
+    #[test]
+    fn shutdown_unregisters_meter() {
+        let meter = sdk::Meter::new("reinit");
+        let registry = Registry::new();
+        let mut first = exporter(meter.clone(), registry.clone());
+        assert!(Exporter::builder(meter.clone())
+            .with_address("127.0.0.1:0".parse().unwrap())
+            .with_registry(registry.clone())
+            .init()
+            .is_err());
+        first.shutdown();
+
+        let second = exporter(meter, registry);
+        let response = get(second.local_addr(), "/custom");
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+    }
+
+    #[test]
+    fn registers_meter_only_once_bound() {
+        let meter = sdk::Meter::new("unbound");
+        let registry = Registry::new();
+        let taken = TcpListener::bind("127.0.0.1:0").unwrap();
+        assert!(Exporter::builder(meter.clone())
+            .with_address(taken.local_addr().unwrap())
+            .with_registry(registry.clone())
+            .init()
+            .is_err());
+
+        // The failed exporter did not register the meter
+        exporter(meter, registry);
+    }
+
+    #[test]
+    fn drops_oversized_requests() {
+        let exporter = exporter(sdk::Meter::new("oversized"), Registry::new());
+
+        let mut stream = TcpStream::connect(exporter.local_addr()).unwrap();
+        let header = "a".repeat(MAX_HEADER_SIZE as usize);
+        // The server may close the connection before the whole header is sent
+        let _ = write!(
+            stream,
+            "GET /custom HTTP/1.1\r\nX-Large: {}\r\n\r\n",
+            header
+        );
+        let mut response = String::new();
+        let _ = stream.read_to_string(&mut response);
+        assert!(response.is_empty());
+
+        let response = get(exporter.local_addr(), "/custom");
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+    }
+
+    #[test]
+    fn slow_clients_do_not_block_shutdown() {
+        let mut exporter = exporter(sdk::Meter::new("slow"), Registry::new());
+
+        // A client sending its request one byte at a time, for longer than the
+        // request deadline
+        let addr = exporter.local_addr();
+        thread::spawn(move || {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            for byte in b"GET /custom HTTP/1.1\r\nHost: localhost\r\n"
+                .iter()
+                .cycle()
+            {
+                if stream.write_all(&[*byte]).is_err() {
+                    break;
+                }
+                thread::sleep(Duration::from_millis(100));
+            }
+        });
+        thread::sleep(Duration::from_millis(200));
+
+        let start = Instant::now();
+        exporter.shutdown();
+        assert!(start.elapsed() < REQUEST_TIMEOUT + Duration::from_secs(1));
+    }
+}
@@ -1,264 +1,551 @@
 //! # OpenTelemetry Prometheus Exporter
 //!
-//! This exporter currently delegates to the [Prometheus library]
-//! library which implements the [Prometheus API].
+//! Exposes the metrics collected by an [`sdk::Meter`], or all meters of an
+//! `sdk::MeterProvider`, to the [Prometheus
+//! library], which implements the [Prometheus API]. A [`MeterCollector`] can
+//! be registered in any prometheus `Registry`, or metrics can be served for
+//! scraping with the pull [`Exporter`], which embeds a `/metrics` HTTP
+//! endpoint.
 //!
+//! Measures recorded while a sampled span is active keep the most recent
+//! exemplar of each histogram bucket, the single exemplar OpenMetrics allows
+//! per bucket, which is served by the pull [`Exporter`] to scrapes accepting
+//! the OpenMetrics text format.
+//!
+//! Each scrape collects the meter, so the meter must use the default
+//! `ExportKind::Cumulative`: cumulative checkpoints leave the aggregations in
+//! place, so scrapes do not take measurements away from other collectors
+//! such as a `PushController`. Measures are exported as histograms,
+//! which only have the implicit `+Inf` bucket unless the meter uses the
+//! `Selector::Histogram` aggregator selector or the measure was created with
+//! explicit buckets. Measures aggregated into a sketch are exported as a
+//! gauge with a `quantile` label, with `_sum` and `_count` metrics.
+//!
+//! [`sdk::Meter`]: ../../../sdk/metrics/meter/struct.Meter.html
 //! [Prometheus library]: https://github.com/tikv/rust-prometheus
 //! [Prometheus API]: https://prometheus.io
-use crate::api;
+//! [`MeterCollector`]: struct.MeterCollector.html
+//! [`Exporter`]: struct.Exporter.html
+use crate::exporter::metrics::{Aggregation, ExportKind, Record};
 use crate::sdk;
-use api::Key;
+use crate::sdk::metrics::Checkpointer;
+use prometheus::core::{Collector, Desc};
+use prometheus::proto;
 pub use prometheus::{
-    default_registry, gather, Counter, CounterVec, Encoder, Gauge, GaugeVec, Histogram,
+    default_registry, gather, Counter, CounterVec, Encoder, Error, Gauge, GaugeVec, Histogram,
     HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry,
     TextEncoder,
 };
-use std::collections::HashMap;
-use std::sync::Arc;
-
-/// Convert from `sdk::LabelSet` to `prometheus`' label format.
-fn convert_label_set(label_set: &sdk::LabelSet) -> HashMap<&str, &str> {
-    label_set
-        .iter()
-        .map(|(key, value)| (key.as_ref(), value.as_ref()))
-        .collect()
-}
-
-/// Convert from list of `Key`s to prometheus' label format.
-pub(crate) fn convert_labels(labels: &[Key]) -> Vec<&str> {
-    labels.iter().map(|k| k.as_str()).collect()
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+mod exporter;
+mod open_metrics;
+
+/// The quantiles exported for measures aggregated into a sketch.
+const SKETCH_QUANTILES: &[f64] = &[0.5, 0.9, 0.99];
+
+pub use exporter::{Builder, Exporter};
+
+/// A prometheus `Collector` exposing the metrics of a `Meter` or
+/// `MeterProvider`.
+///
+/// Instruments can be created after the collector is registered, so it does
+/// not describe the metric families it collects. Its single descriptor,
+/// `<meter>_meter` or `opentelemetry_meter_provider`, only identifies the
+/// collector in a `Registry`, which does not check the collected families
+/// against the families of other collectors.
+pub struct MeterCollector {
+    source: Box<dyn Checkpointer>,
+    desc: Desc,
+    /// The exemplars of the last collection
+    exemplars: Arc<Mutex<open_metrics::Exemplars>>,
 }
 
-/// Prometheus IntCounterHandle
-#[derive(Clone)]
-#[allow(missing_debug_implementations)]
-pub struct IntCounterHandle(prometheus::IntCounter);
-
-impl api::Counter<i64, sdk::LabelSet> for prometheus::IntCounterVec {
-    /// Prometheus' `CounterHandle`
-    type Handle = IntCounterHandle;
-
-    /// Creates a `Measurement` object to be used by a `Meter` when batch recording.
-    fn measurement(&self, value: i64) -> api::Measurement<sdk::LabelSet> {
-        api::Measurement::new(Arc::new(self.clone()), api::MeasurementValue::from(value))
-    }
-
-    /// Creates a handle for this instrument.
-    fn acquire_handle(&self, labels: &sdk::LabelSet) -> Self::Handle {
-        IntCounterHandle(self.with(&convert_label_set(labels)))
+impl std::fmt::Debug for MeterCollector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MeterCollector")
+            .field("source", &self.source)
+            .finish()
     }
 }
 
-impl api::Instrument<sdk::LabelSet> for prometheus::IntCounterVec {
-    /// Record a single counter measurement value
-    fn record_one(&self, value: api::MeasurementValue, label_set: &sdk::LabelSet) {
-        self.with(&convert_label_set(label_set))
-            .inc_by(value.into_i64())
+impl MeterCollector {
+    /// Create a new collector for the given meter. At most one collector per
+    /// meter name can be registered in a `Registry`. Fails if the meter does
+    /// not use `ExportKind::Cumulative`.
+    pub fn new(meter: sdk::Meter) -> prometheus::Result<Self> {
+        let desc = Desc::new(
+            sanitize_name(&format!("{}_meter", meter.name())),
+            format!("OpenTelemetry meter {}", meter.name()),
+            Vec::new(),
+            HashMap::new(),
+        )?;
+
+        MeterCollector::with_source(Box::new(meter), desc)
     }
-}
 
-impl api::InstrumentHandle for IntCounterHandle {
-    /// Record a single counter measurement value for preset values
-    fn record_one(&self, value: api::MeasurementValue) {
-        self.0.inc_by(value.into_i64())
+    /// Create a new collector for all meters of the given provider. At most
+    /// one provider collector can be registered in a `Registry`. Fails if the
+    /// provider does not use `ExportKind::Cumulative`.
+    pub fn from_provider(provider: sdk::MeterProvider) -> prometheus::Result<Self> {
+        let desc = Desc::new(
+            "opentelemetry_meter_provider".to_string(),
+            "OpenTelemetry meter provider".to_string(),
+            Vec::new(),
+            HashMap::new(),
+        )?;
+
+        MeterCollector::with_source(Box::new(provider), desc)
     }
-}
-
-impl api::CounterHandle<i64> for IntCounterHandle {}
-
-/// Prometheus CounterHandle
-#[derive(Clone)]
-#[allow(missing_debug_implementations)]
-pub struct CounterHandle(prometheus::Counter);
 
-impl api::Counter<f64, sdk::LabelSet> for prometheus::CounterVec {
-    type Handle = CounterHandle;
-
-    /// Creates a `Measurement` object to be used by a `Meter` when batch recording.
-    fn measurement(&self, value: f64) -> api::Measurement<sdk::LabelSet> {
-        api::Measurement::new(Arc::new(self.clone()), api::MeasurementValue::from(value))
+    fn with_source(source: Box<dyn Checkpointer>, desc: Desc) -> prometheus::Result<Self> {
+        // Delta checkpoints reset the aggregations, so every scrape would
+        // only see the measurements since the previous scrape or push.
+        if source.export_kind() != ExportKind::Cumulative {
+            return Err(prometheus::Error::Msg(
+                "prometheus requires cumulative checkpoints".to_string(),
+            ));
+        }
+
+        Ok(MeterCollector {
+            source,
+            desc,
+            exemplars: Default::default(),
+        })
     }
 
-    /// Creates a handle for this instrument.
-    fn acquire_handle(&self, labels: &sdk::LabelSet) -> Self::Handle {
-        CounterHandle(self.with(&convert_label_set(labels)))
+    /// The exemplars of the last collection, shared with the collector.
+    fn exemplars(&self) -> Arc<Mutex<open_metrics::Exemplars>> {
+        self.exemplars.clone()
     }
 }
 
-impl api::Instrument<sdk::LabelSet> for prometheus::CounterVec {
-    /// record a single counter measurement value
-    fn record_one(&self, value: api::MeasurementValue, label_set: &sdk::LabelSet) {
-        self.with(&convert_label_set(label_set))
-            .inc_by(value.into_f64())
+impl Collector for MeterCollector {
+    /// The descriptor identifying the meter or provider, rather than the
+    /// descriptors of the collected families.
+    fn desc(&self) -> Vec<&Desc> {
+        vec![&self.desc]
     }
-}
 
-impl api::InstrumentHandle for CounterHandle {
-    /// record a single counter measurement value for precomputed labels
-    fn record_one(&self, value: api::MeasurementValue) {
-        self.0.inc_by(value.into_f64())
+    fn collect(&self) -> Vec<proto::MetricFamily> {
+        let mut families = BTreeMap::new();
+        let mut exemplars = HashMap::new();
+        for record in self.source.collect().records {
+            for (name, metric_type, metric) in convert_record(&record) {
+                if let Aggregation::Histogram {
+                    exemplars: bucket_exemplars,
+                    ..
+                } = &record.aggregation
+                {
+                    if bucket_exemplars.iter().any(Option::is_some) {
+                        let key = open_metrics::metric_key(&name, &metric);
+                        exemplars.insert(key, bucket_exemplars.clone());
+                    }
+                }
+                let family = families.entry(name.clone()).or_insert_with(|| {
+                    let mut family = proto::MetricFamily::default();
+                    family.set_name(name);
+                    family.set_help(help(&record));
+                    family
+                });
+                family.set_field_type(metric_type);
+                family.mut_metric().push(metric);
+            }
+        }
+
+        if let Ok(mut last_exemplars) = self.exemplars.lock() {
+            *last_exemplars = exemplars;
+        }
+
+        families.into_values().collect()
     }
 }
 
-impl api::CounterHandle<f64> for CounterHandle {}
-
-// GAUGE COMPAT
-
-/// Prometheus IntGaugeHandle
-#[derive(Clone)]
-#[allow(missing_debug_implementations)]
-pub struct IntGaugeHandle(prometheus::IntGauge);
-
-impl api::Gauge<i64, sdk::LabelSet> for prometheus::IntGaugeVec {
-    type Handle = IntGaugeHandle;
-
-    /// Creates a `Measurement` object to be used by a `Meter` when batch recording.
-    fn measurement(&self, value: i64) -> api::Measurement<sdk::LabelSet> {
-        api::Measurement::new(Arc::new(self.clone()), api::MeasurementValue::from(value))
+/// The prometheus metric name, `meter_name_unit`.
+fn metric_name(record: &Record) -> String {
+    let descriptor = &record.descriptor;
+    let mut name = format!("{}_{}", descriptor.meter_name, descriptor.name);
+    if !descriptor.unit.as_str().is_empty() {
+        name.push_str(&format!("_{}", descriptor.unit.as_str()));
     }
 
-    /// Creates a handle for this instrument.
-    fn acquire_handle(&self, labels: &sdk::LabelSet) -> Self::Handle {
-        IntGaugeHandle(self.with(&convert_label_set(labels)))
-    }
+    sanitize_name(&name)
 }
 
-impl api::Instrument<sdk::LabelSet> for prometheus::IntGaugeVec {
-    /// record a single gauge measurement value
-    fn record_one(&self, value: api::MeasurementValue, label_set: &sdk::LabelSet) {
-        self.with(&convert_label_set(label_set))
-            .set(value.into_i64())
+fn help(record: &Record) -> String {
+    // Prometheus cannot have empty help strings
+    if !record.descriptor.description.is_empty() {
+        record.descriptor.description.clone()
+    } else {
+        format!("{} metric", record.descriptor.name)
     }
 }
 
-impl api::InstrumentHandle for IntGaugeHandle {
-    /// record a single gauge measurement value for precomputed labels
-    fn record_one(&self, value: api::MeasurementValue) {
-        self.0.set(value.into_i64())
+/// Replace the characters prometheus does not allow in metric and label
+/// names with underscores.
+fn sanitize_name(name: &str) -> String {
+    let mut sanitized = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+    if sanitized.starts_with(|c: char| c.is_ascii_digit()) {
+        sanitized.insert(0, '_');
     }
-}
-
-impl api::GaugeHandle<i64> for IntGaugeHandle {}
 
-/// Prometheus GaugeHandle
-#[derive(Clone)]
-#[allow(missing_debug_implementations)]
-pub struct GaugeHandle(prometheus::Gauge);
-
-impl api::Gauge<f64, sdk::LabelSet> for prometheus::GaugeVec {
-    type Handle = GaugeHandle;
-
-    /// Creates a `Measurement` object to be used by a `Meter` when batch recording.
-    fn measurement(&self, value: f64) -> api::Measurement<sdk::LabelSet> {
-        api::Measurement::new(Arc::new(self.clone()), api::MeasurementValue::from(value))
-    }
-
-    /// Creates a handle for this instrument.
-    fn acquire_handle(&self, labels: &sdk::LabelSet) -> Self::Handle {
-        GaugeHandle(self.with(&convert_label_set(labels)))
-    }
+    sanitized
 }
 
-impl api::Instrument<sdk::LabelSet> for prometheus::GaugeVec {
-    /// record a single gauge measurement value
-    fn record_one(&self, value: api::MeasurementValue, label_set: &sdk::LabelSet) {
-        self.with(&convert_label_set(label_set))
-            .set(value.into_f64())
-    }
+fn labels(record: &Record) -> Vec<proto::LabelPair> {
+    record
+        .labels
+        .iter()
+        .map(|(key, value)| {
+            let mut label = proto::LabelPair::default();
+            label.set_name(sanitize_name(key));
+            label.set_value(value.to_string());
+            label
+        })
+        .collect()
 }
 
-impl api::InstrumentHandle for GaugeHandle {
-    /// record a single gauge measurement value for precomputed labels
-    fn record_one(&self, value: api::MeasurementValue) {
-        self.0.set(value.into_f64())
-    }
+fn gauge(labels: Vec<proto::LabelPair>, value: f64) -> proto::Metric {
+    let mut metric = proto::Metric::default();
+    metric.set_label(labels.into());
+    let mut gauge = proto::Gauge::default();
+    gauge.set_value(value);
+    metric.set_gauge(gauge);
+    metric
 }
 
-impl api::GaugeHandle<f64> for GaugeHandle {}
-
-// MEASURE COMPAT
-
-/// Prometheus IntMeasureHandle
-#[derive(Clone)]
-#[allow(missing_debug_implementations)]
-pub struct IntMeasureHandle(prometheus::Histogram);
+fn counter(labels: Vec<proto::LabelPair>, value: f64) -> proto::Metric {
+    let mut metric = proto::Metric::default();
+    metric.set_label(labels.into());
+    let mut counter = proto::Counter::default();
+    counter.set_value(value);
+    metric.set_counter(counter);
+    metric
+}
 
-/// Prometheus Histograms do not have i64 variant, `IntMeasure` will convert i64 to float when it
-/// records values.
-#[derive(Clone)]
-#[allow(missing_debug_implementations)]
-pub struct IntMeasure(prometheus::HistogramVec);
+fn histogram(labels: Vec<proto::LabelPair>, histogram: proto::Histogram) -> proto::Metric {
+    let mut metric = proto::Metric::default();
+    metric.set_label(labels.into());
+    metric.set_histogram(histogram);
+    metric
+}
 
-impl IntMeasure {
-    pub(crate) fn new(histogram: prometheus::HistogramVec) -> Self {
-        IntMeasure(histogram)
+/// Convert a record into the metrics of one or more prometheus families,
+/// named after the record.
+fn convert_record(record: &Record) -> Vec<(String, proto::MetricType, proto::Metric)> {
+    let name = metric_name(record);
+    let labels = labels(record);
+
+    match &record.aggregation {
+        // Prometheus counters cannot decrease, up-down counters are gauges
+        Aggregation::Sum(sum) if !record.descriptor.monotonic => {
+            vec![(name, proto::MetricType::GAUGE, gauge(labels, sum.to_f64()))]
+        }
+        Aggregation::Sum(sum) => {
+            vec![(
+                name,
+                proto::MetricType::COUNTER,
+                counter(labels, sum.to_f64()),
+            )]
+        }
+        Aggregation::LastValue { value, .. } => {
+            vec![(
+                name,
+                proto::MetricType::GAUGE,
+                gauge(labels, value.to_f64()),
+            )]
+        }
+        // The text format of the prometheus library does not support
+        // summaries, so only the sum and count are exported
+        Aggregation::MinMaxSumCount { sum, count, .. } => {
+            let mut summary = proto::Histogram::default();
+            summary.set_sample_count(*count);
+            summary.set_sample_sum(sum.to_f64());
+            vec![(
+                name,
+                proto::MetricType::HISTOGRAM,
+                histogram(labels, summary),
+            )]
+        }
+        Aggregation::Histogram {
+            boundaries,
+            counts,
+            sum,
+            count,
+            ..
+        } => {
+            let mut buckets = proto::Histogram::default();
+            buckets.set_sample_count(*count);
+            buckets.set_sample_sum(sum.to_f64());
+            // Prometheus buckets are cumulative, the overflow bucket is
+            // implied by the sample count
+            let mut cumulative_count = 0;
+            for (boundary, bucket_count) in boundaries.iter().zip(counts) {
+                cumulative_count += bucket_count;
+                let mut bucket = proto::Bucket::default();
+                bucket.set_upper_bound(*boundary);
+                bucket.set_cumulative_count(cumulative_count);
+                buckets.mut_bucket().push(bucket);
+            }
+            vec![(
+                name,
+                proto::MetricType::HISTOGRAM,
+                histogram(labels, buckets),
+            )]
+        }
+        // Summaries cannot be encoded either, so quantiles are exported as a
+        // gauge with a `quantile` label, along with the sum and count
+        Aggregation::Sketch(sketch) => {
+            let mut metrics = Vec::new();
+            for quantile in SKETCH_QUANTILES {
+                if let Some(value) = sketch.quantile(*quantile) {
+                    let mut quantile_labels = labels.clone();
+                    let mut label = proto::LabelPair::default();
+                    label.set_name("quantile".to_string());
+                    label.set_value(quantile.to_string());
+                    quantile_labels.push(label);
+                    metrics.push((
+                        name.clone(),
+                        proto::MetricType::GAUGE,
+                        gauge(quantile_labels, value),
+                    ));
+                }
+            }
+            metrics.push((
+                format!("{}_sum", name),
+                proto::MetricType::GAUGE,
+                gauge(labels.clone(), sketch.sum()),
+            ));
+            metrics.push((
+                format!("{}_count", name),
+                proto::MetricType::COUNTER,
+                counter(labels, sketch.count() as f64),
+            ));
+            metrics
+        }
     }
 }
 
-impl api::Measure<i64, sdk::LabelSet> for IntMeasure {
-    type Handle = IntMeasureHandle;
-
-    /// Creates a `Measurement` object to be used by a `Meter` when batch recording.
-    fn measurement(&self, value: i64) -> api::Measurement<sdk::LabelSet> {
-        api::Measurement::new(Arc::new(self.clone()), api::MeasurementValue::from(value))
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{self, Counter, Gauge, Measure, Meter, Provider, TraceContextExt, Tracer};
+
+    fn encode(registry: &Registry) -> String {
+        let mut output = Vec::new();
+        TextEncoder::new()
+            .encode(&registry.gather(), &mut output)
+            .unwrap();
+        String::from_utf8(output).unwrap()
     }
 
-    /// Creates a handle for this instrument.
-    fn acquire_handle(&self, labels: &sdk::LabelSet) -> Self::Handle {
-        IntMeasureHandle(self.0.with(&convert_label_set(labels)))
+    #[test]
+    fn exposes_meter_aggregations() {
+        let meter = sdk::Meter::builder("test")
+            .with_aggregator_selector(sdk::metrics::Selector::Histogram(vec![1.0, 5.0]))
+            .build();
+        let registry = Registry::new();
+        registry
+            .register(Box::new(MeterCollector::new(meter.clone()).unwrap()))
+            .unwrap();
+
+        let labels = meter.labels(vec![api::KeyValue::new("http.method", "GET")]);
+        meter
+            .new_i64_counter(
+                "requests",
+                api::MetricOptions::default().with_description("Requests served"),
+            )
+            .add(3, &labels);
+        meter
+            .new_f64_gauge("temperature", api::MetricOptions::default())
+            .set(21.5, &labels);
+        let latency = meter.new_f64_measure(
+            "latency",
+            api::MetricOptions::default().with_unit(api::Unit::new("seconds")),
+        );
+        latency.record(0.5, &labels);
+        latency.record(2.0, &labels);
+        latency.record(7.0, &labels);
+
+        let output = encode(&registry);
+        for line in &[
+            "# HELP test_requests Requests served",
+            "# TYPE test_requests counter",
+            "test_requests{http_method=\"GET\"} 3",
+            "# TYPE test_temperature gauge",
+            "test_temperature{http_method=\"GET\"} 21.5",
+            "# TYPE test_latency_seconds histogram",
+            "test_latency_seconds_bucket{http_method=\"GET\",le=\"1\"} 1",
+            "test_latency_seconds_bucket{http_method=\"GET\",le=\"5\"} 2",
+            "test_latency_seconds_bucket{http_method=\"GET\",le=\"+Inf\"} 3",
+            "test_latency_seconds_sum{http_method=\"GET\"} 9.5",
+            "test_latency_seconds_count{http_method=\"GET\"} 3",
+        ] {
+            assert!(output.contains(line), "missing {:?} in\n{}", line, output);
+        }
     }
-}
 
-impl api::Instrument<sdk::LabelSet> for IntMeasure {
-    /// record a single measure measurement value
-    fn record_one(&self, value: api::MeasurementValue, label_set: &sdk::LabelSet) {
-        self.0
-            .with(&convert_label_set(label_set))
-            .observe(value.into_i64() as f64)
+    #[test]
+    fn exposes_up_down_counter_as_gauge() {
+        let meter = sdk::Meter::new("test");
+        let registry = Registry::new();
+        registry
+            .register(Box::new(MeterCollector::new(meter.clone()).unwrap()))
+            .unwrap();
+
+        let connections = meter.new_i64_counter(
+            "connections",
+            api::MetricOptions::default().with_monotonic(false),
+        );
+        connections.add(5, &meter.labels(vec![]));
+        connections.add(-2, &meter.labels(vec![]));
+
+        let output = encode(&registry);
+        for line in &["# TYPE test_connections gauge", "test_connections 3"] {
+            assert!(output.contains(line), "missing {:?} in\n{}", line, output);
+        }
     }
-}
 
-impl api::InstrumentHandle for IntMeasureHandle {
-    /// record a single measure measurement value for precomputed labels
-    fn record_one(&self, value: api::MeasurementValue) {
-        self.0.observe(value.into_i64() as f64)
+    #[test]
+    fn describes_the_meter_rather_than_its_families() {
+        let meter = sdk::Meter::new("described");
+        meter
+            .new_i64_counter("requests", api::MetricOptions::default())
+            .add(1, &meter.labels(vec![]));
+        let collector = MeterCollector::new(meter.clone()).unwrap();
+        let names = collector
+            .desc()
+            .iter()
+            .map(|desc| desc.fq_name.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["described_meter"]);
+
+        // The descriptor allows one collector per meter name in a registry
+        let registry = Registry::new();
+        registry.register(Box::new(collector)).unwrap();
+        assert!(registry
+            .register(Box::new(MeterCollector::new(meter).unwrap()))
+            .is_err());
     }
-}
-
-impl api::MeasureHandle<i64> for IntMeasureHandle {}
 
-/// Prometheus MeasureHandle
-#[derive(Clone)]
-#[allow(missing_debug_implementations)]
-pub struct MeasureHandle(prometheus::Histogram);
-
-impl api::Measure<f64, sdk::LabelSet> for prometheus::HistogramVec {
-    type Handle = MeasureHandle;
-
-    /// Creates a `Measurement` object to be used by a `Meter` when batch recording.
-    fn measurement(&self, value: f64) -> api::Measurement<sdk::LabelSet> {
-        api::Measurement::new(Arc::new(self.clone()), api::MeasurementValue::from(value))
+    #[test]
+    fn requires_cumulative_meters() {
+        let meter = sdk::Meter::builder("test")
+            .with_export_kind(crate::exporter::metrics::ExportKind::Delta)
+            .build();
+        assert!(MeterCollector::new(meter).is_err());
+
+        let meter = sdk::Meter::new("test");
+        let collector = MeterCollector::new(meter.clone()).unwrap();
+        meter
+            .new_i64_counter("requests", api::MetricOptions::default())
+            .add(3, &meter.labels(vec![]));
+
+        // Scrapes do not reset the aggregations seen by other collectors
+        collector.collect();
+        match meter.collect().records[0].aggregation {
+            Aggregation::Sum(sum) => assert_eq!(sum.to_f64(), 3.0),
+            ref aggregation => panic!("unexpected aggregation {:?}", aggregation),
+        }
     }
 
-    /// Creates a handle for this instrument.
-    fn acquire_handle(&self, labels: &sdk::LabelSet) -> Self::Handle {
-        MeasureHandle(self.with(&convert_label_set(labels)))
+    #[test]
+    fn exposes_min_max_sum_count_as_histogram() {
+        let meter = sdk::Meter::new("summary");
+        let registry = Registry::new();
+        registry
+            .register(Box::new(MeterCollector::new(meter.clone()).unwrap()))
+            .unwrap();
+
+        let measure = meter.new_i64_measure("size", api::MetricOptions::default());
+        measure.record(4, &meter.labels(vec![]));
+        measure.record(10, &meter.labels(vec![]));
+
+        let output = encode(&registry);
+        for line in &[
+            "# TYPE summary_size histogram",
+            "summary_size_bucket{le=\"+Inf\"} 2",
+            "summary_size_sum 14",
+            "summary_size_count 2",
+        ] {
+            assert!(output.contains(line), "missing {:?} in\n{}", line, output);
+        }
     }
-}
 
-impl api::Instrument<sdk::LabelSet> for prometheus::HistogramVec {
-    /// record a single measure measurement value
-    fn record_one(&self, value: api::MeasurementValue, label_set: &sdk::LabelSet) {
-        self.with(&convert_label_set(label_set))
-            .observe(value.into_f64())
+    #[test]
+    fn exposes_sketch_quantiles() {
+        let meter = sdk::Meter::new("test");
+        let registry = Registry::new();
+        registry
+            .register(Box::new(MeterCollector::new(meter.clone()).unwrap()))
+            .unwrap();
+
+        let latency =
+            meter.new_f64_measure("latency", api::MetricOptions::default().with_sketch(0.01));
+        latency.record(0.25, &meter.labels(vec![]));
+        latency.record(0.25, &meter.labels(vec![]));
+
+        let output = encode(&registry);
+        for line in &[
+            "# TYPE test_latency gauge",
+            "test_latency{quantile=\"0.5\"} 0.25",
+            "test_latency{quantile=\"0.99\"} 0.25",
+            "# TYPE test_latency_sum gauge",
+            "test_latency_sum 0.5",
+            "# TYPE test_latency_count counter",
+            "test_latency_count 2",
+        ] {
+            assert!(output.contains(line), "missing {:?} in\n{}", line, output);
+        }
     }
-}
 
-impl api::InstrumentHandle for MeasureHandle {
-    /// record a single measure measurement value for precomputed labels
-    fn record_one(&self, value: api::MeasurementValue) {
-        self.0.observe(value.into_f64())
+    #[test]
+    fn encodes_open_metrics_with_exemplars() {
+        let meter = sdk::Meter::builder("test")
+            .with_aggregator_selector(sdk::metrics::Selector::Histogram(vec![1.0]))
+            .build();
+        let collector = MeterCollector::new(meter.clone()).unwrap();
+        let labels = meter.labels(vec![api::KeyValue::new("path", "/")]);
+        meter
+            .new_i64_counter("requests", api::MetricOptions::default())
+            .add(2, &labels);
+        let latency = meter.new_f64_measure("latency", api::MetricOptions::default());
+        latency.record(0.5, &labels);
+        let tracer = sdk::Provider::default().get_tracer("test");
+        let span_context = tracer.in_span("request", |cx| {
+            latency.record(1.5, &labels);
+            cx.span().span_context()
+        });
+
+        let families = collector.collect();
+        let mut output = Vec::new();
+        open_metrics::encode(
+            &families,
+            &collector.exemplars().lock().unwrap(),
+            &mut output,
+        )
+        .unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let exemplar = format!(
+            "test_latency_bucket{{path=\"/\",le=\"+Inf\"}} 2 # {{trace_id=\"{:032x}\",span_id=\"{:016x}\"}} 1.5 ",
+            span_context.trace_id().to_u128(),
+            span_context.span_id().to_u64()
+        );
+        for line in &[
+            "# TYPE test_latency histogram",
+            "test_latency_bucket{path=\"/\",le=\"1.0\"} 1\n",
+            &exemplar,
+            "test_latency_sum{path=\"/\"} 2.0\n",
+            "test_latency_count{path=\"/\"} 2\n",
+            "# TYPE test_requests counter",
+            "test_requests_total{path=\"/\"} 2.0\n",
+        ] {
+            assert!(output.contains(line), "missing {:?} in\n{}", line, output);
+        }
+        assert!(output.ends_with("# EOF\n"));
     }
 }
-
-impl api::MeasureHandle<f64> for MeasureHandle {}
@@ -0,0 +1,194 @@
+//! # OpenMetrics Text Format
+//!
+//! Encodes gathered metric families in the [OpenMetrics] text format, which
+//! unlike the Prometheus text format can attach exemplars to histogram
+//! buckets, linking them to the traces of recorded measurements.
+//!
+//! [OpenMetrics]: https://github.com/OpenObservability/OpenMetrics/blob/master/specification/OpenMetrics.md
+use crate::exporter::metrics::Exemplar;
+use prometheus::proto;
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::time::UNIX_EPOCH;
+
+/// The content type of the OpenMetrics text format.
+pub(crate) const CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+/// Identifies a metric by its family name and sorted label pairs.
+pub(crate) type MetricKey = (String, Vec<(String, String)>);
+
+/// The exemplars of each bucket of the histograms of a collection.
+pub(crate) type Exemplars = HashMap<MetricKey, Vec<Option<Exemplar>>>;
+
+/// The key of a metric of the given family.
+pub(crate) fn metric_key(family_name: &str, metric: &proto::Metric) -> MetricKey {
+    let mut labels = metric
+        .get_label()
+        .iter()
+        .map(|label| (label.get_name().to_string(), label.get_value().to_string()))
+        .collect::<Vec<_>>();
+    labels.sort();
+
+    (family_name.to_string(), labels)
+}
+
+/// Encode the families, with the exemplars of their histogram buckets.
+pub(crate) fn encode<W: Write>(
+    families: &[proto::MetricFamily],
+    exemplars: &Exemplars,
+    writer: &mut W,
+) -> io::Result<()> {
+    for family in families {
+        let name = family.get_name();
+        let metric_type = family.get_field_type();
+        // Counter families are named without the suffix of their samples
+        let family_name = match metric_type {
+            proto::MetricType::COUNTER => name.strip_suffix("_total").unwrap_or(name),
+            _ => name,
+        };
+        let type_name = match metric_type {
+            proto::MetricType::COUNTER => "counter",
+            proto::MetricType::GAUGE => "gauge",
+            proto::MetricType::SUMMARY => "summary",
+            proto::MetricType::HISTOGRAM => "histogram",
+            proto::MetricType::UNTYPED => "unknown",
+        };
+        writeln!(writer, "# TYPE {} {}", family_name, type_name)?;
+        writeln!(
+            writer,
+            "# HELP {} {}",
+            family_name,
+            escape(family.get_help())
+        )?;
+
+        for metric in family.get_metric() {
+            let labels = metric.get_label();
+            match metric_type {
+                proto::MetricType::COUNTER => {
+                    let value = metric.get_counter().get_value();
+                    let sample_name = format!("{}_total", family_name);
+                    write_sample(writer, &sample_name, labels, None, &float(value), None)?;
+                }
+                proto::MetricType::GAUGE => {
+                    let value = metric.get_gauge().get_value();
+                    write_sample(writer, name, labels, None, &float(value), None)?;
+                }
+                proto::MetricType::UNTYPED => {
+                    let value = metric.get_untyped().get_value();
+                    write_sample(writer, name, labels, None, &float(value), None)?;
+                }
+                proto::MetricType::SUMMARY => {
+                    let summary = metric.get_summary();
+                    for quantile in summary.get_quantile() {
+                        let label = ("quantile", float(quantile.get_quantile()));
+                        let value = float(quantile.get_value());
+                        write_sample(writer, name, labels, Some(label), &value, None)?;
+                    }
+                    let sum = float(summary.get_sample_sum());
+                    write_sample(writer, &format!("{}_sum", name), labels, None, &sum, None)?;
+                    let count = summary.get_sample_count().to_string();
+                    let count_name = format!("{}_count", name);
+                    write_sample(writer, &count_name, labels, None, &count, None)?;
+                }
+                proto::MetricType::HISTOGRAM => {
+                    let histogram = metric.get_histogram();
+                    let bucket_exemplars = exemplars.get(&metric_key(name, metric));
+                    let exemplar = |index: usize| {
+                        bucket_exemplars
+                            .and_then(|exemplars| exemplars.get(index))
+                            .and_then(Option::as_ref)
+                    };
+                    let bucket_name = format!("{}_bucket", name);
+                    let buckets = histogram.get_bucket();
+                    for (index, bucket) in buckets.iter().enumerate() {
+                        let label = ("le", float(bucket.get_upper_bound()));
+                        let count = bucket.get_cumulative_count().to_string();
+                        let exemplar = exemplar(index);
+                        write_sample(writer, &bucket_name, labels, Some(label), &count, exemplar)?;
+                    }
+                    // The overflow bucket is implied by the sample count
+                    let count = histogram.get_sample_count().to_string();
+                    let has_overflow_bucket = buckets
+                        .last()
+                        .map(|bucket| bucket.get_upper_bound().is_infinite())
+                        .unwrap_or(false);
+                    if !has_overflow_bucket {
+                        let label = ("le", float(f64::INFINITY));
+                        let exemplar = exemplar(buckets.len());
+                        write_sample(writer, &bucket_name, labels, Some(label), &count, exemplar)?;
+                    }
+                    let sum = float(histogram.get_sample_sum());
+                    write_sample(writer, &format!("{}_sum", name), labels, None, &sum, None)?;
+                    let count_name = format!("{}_count", name);
+                    write_sample(writer, &count_name, labels, None, &count, None)?;
+                }
+            }
+        }
+    }
+
+    writeln!(writer, "# EOF")
+}
+
+/// Write a sample line, with an exemplar if there is one.
+fn write_sample<W: Write>(
+    writer: &mut W,
+    name: &str,
+    labels: &[proto::LabelPair],
+    additional_label: Option<(&str, String)>,
+    value: &str,
+    exemplar: Option<&Exemplar>,
+) -> io::Result<()> {
+    write!(writer, "{}", name)?;
+    let additional_label = additional_label
+        .as_ref()
+        .map(|(name, value)| (*name, value.as_str()));
+    let mut labels = labels
+        .iter()
+        .map(|label| (label.get_name(), label.get_value()))
+        .chain(additional_label)
+        .peekable();
+    if labels.peek().is_some() {
+        write!(writer, "{{")?;
+        for (index, (name, value)) in labels.enumerate() {
+            if index > 0 {
+                write!(writer, ",")?;
+            }
+            write!(writer, "{}=\"{}\"", name, escape(value))?;
+        }
+        write!(writer, "}}")?;
+    }
+    write!(writer, " {}", value)?;
+    if let Some(exemplar) = exemplar {
+        write!(
+            writer,
+            " # {{trace_id=\"{:032x}\",span_id=\"{:016x}\"}} {}",
+            exemplar.trace_id.to_u128(),
+            exemplar.span_id.to_u64(),
+            float(exemplar.value.to_f64())
+        )?;
+        if let Ok(timestamp) = exemplar.timestamp.duration_since(UNIX_EPOCH) {
+            write!(writer, " {:.3}", timestamp.as_secs_f64())?;
+        }
+    }
+
+    writeln!(writer)
+}
+
+/// Format a float, with a decimal point for integral values.
+fn float(value: f64) -> String {
+    if value.is_nan() {
+        "NaN".to_string()
+    } else if value.is_infinite() {
+        if value > 0.0 { "+Inf" } else { "-Inf" }.to_string()
+    } else {
+        format!("{:?}", value)
+    }
+}
+
+/// Escape backslashes, double quotes and line feeds.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
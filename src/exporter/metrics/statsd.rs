@@ -0,0 +1,481 @@
+//! # StatsD Metric Exporter
+//!
+//! The StatsD [`MetricExporter`] sends the collected [`Record`]s to a StatsD
+//! agent over UDP, in the StatsD line format. Labels can be sent as
+//! [DogStatsD] tags, plain StatsD agents ignore them.
+//!
+//! StatsD agents aggregate the values they receive between flushes, so the
+//! meter should be configured with `ExportKind::Delta`:
+//!
+//! - Counters and sum observers are sent as counters, up-down counters and
+//!   up-down sum observers as gauge increments.
+//! - Gauges and value observers are sent as gauges.
+//! - Measures are sent as `.count` and `.sum` counters, along with `.min` and
+//!   `.max` gauges, or `.p50`, `.p90` and `.p99` gauges for sketches.
+//!
+//! Cumulative sums would be counted again by the agent on every export, so
+//! with `ExportKind::Cumulative` all sums and counts are sent as gauges set to
+//! the running total instead.
+//!
+//! Lines are batched into packets of at most 1432 bytes by default, which
+//! fit in the usual Ethernet MTU.
+//!
+//! [`MetricExporter`]: ../trait.MetricExporter.html
+//! [`Record`]: ../struct.Record.html
+//! [DogStatsD]: https://docs.datadoghq.com/developers/dogstatsd/datagram_shell/
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use opentelemetry::exporter::metrics::{statsd, ExportKind};
+//! use opentelemetry::sdk;
+//!
+//! let exporter = statsd::Builder::default()
+//!     .with_agent_endpoint("127.0.0.1:8125")
+//!     .with_prefix("my_service")
+//!     .with_tags(true)
+//!     .init()
+//!     .expect("cannot bind UDP socket");
+//! let meter = sdk::Meter::builder("example")
+//!     .with_export_kind(ExportKind::Delta)
+//!     .build();
+//! let _controller = sdk::PushController::builder(meter, exporter).build();
+//! ```
+use crate::exporter::metrics::{self, Aggregation, ExportKind, Number, Record};
+use std::fmt::{Display, Write as _};
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+
+/// Default StatsD agent endpoint.
+const DEFAULT_AGENT_ENDPOINT: &str = "127.0.0.1:8125";
+
+/// Default maximum size of a packet, in bytes.
+const DEFAULT_MAX_PACKET_SIZE: usize = 1432;
+
+/// Builder
+#[derive(Debug)]
+pub struct Builder {
+    agent_endpoint: io::Result<Vec<SocketAddr>>,
+    prefix: Option<String>,
+    tags: bool,
+    sample_rate: f64,
+    max_packet_size: usize,
+}
+
+impl Default for Builder {
+    /// Return the default Exporter Builder.
+    fn default() -> Self {
+        Builder {
+            agent_endpoint: DEFAULT_AGENT_ENDPOINT
+                .to_socket_addrs()
+                .map(|addrs| addrs.collect()),
+            prefix: None,
+            tags: false,
+            sample_rate: 1.0,
+            max_packet_size: DEFAULT_MAX_PACKET_SIZE,
+        }
+    }
+}
+
+impl Builder {
+    /// Assign the StatsD agent endpoint. Defaults to `127.0.0.1:8125`.
+    pub fn with_agent_endpoint<T: ToSocketAddrs>(self, agent_endpoint: T) -> Self {
+        Builder {
+            agent_endpoint: agent_endpoint
+                .to_socket_addrs()
+                .map(|addrs| addrs.collect()),
+            ..self
+        }
+    }
+
+    /// Assign a prefix prepended to all metric names, separated by a dot.
+    pub fn with_prefix<T: Into<String>>(self, prefix: T) -> Self {
+        Builder {
+            prefix: Some(prefix.into()),
+            ..self
+        }
+    }
+
+    /// Send labels as DogStatsD tags. Defaults to `false`.
+    pub fn with_tags(self, tags: bool) -> Self {
+        Builder { tags, ..self }
+    }
+
+    /// Assign the probability of sending each record, between 0 and 1. The
+    /// lines of sent records are annotated with the rate, so that the agent
+    /// can scale their values. Defaults to 1, sending every record.
+    pub fn with_sample_rate(self, sample_rate: f64) -> Self {
+        Builder {
+            sample_rate: sample_rate.clamp(0.0, 1.0),
+            ..self
+        }
+    }
+
+    /// Assign the maximum size of a packet, in bytes. Lines longer than the
+    /// maximum are sent in their own packet. Defaults to 1432.
+    pub fn with_max_packet_size(self, max_packet_size: usize) -> Self {
+        Builder {
+            max_packet_size,
+            ..self
+        }
+    }
+
+    /// Create a new exporter sending to the agent endpoint from a local UDP
+    /// socket.
+    pub fn init(self) -> io::Result<Exporter> {
+        let agent_endpoint = self.agent_endpoint?;
+        let socket = UdpSocket::bind("0.0.0.0:0")?;
+        socket.connect(&agent_endpoint[..])?;
+
+        Ok(Exporter {
+            socket,
+            prefix: self.prefix,
+            tags: self.tags,
+            sample_rate: self.sample_rate,
+            max_packet_size: self.max_packet_size,
+        })
+    }
+}
+
+/// A [`MetricExporter`] that sends metrics to a StatsD agent.
+///
+/// [`MetricExporter`]: ../trait.MetricExporter.html
+#[derive(Debug)]
+pub struct Exporter {
+    socket: UdpSocket,
+    prefix: Option<String>,
+    tags: bool,
+    sample_rate: f64,
+    max_packet_size: usize,
+}
+
+impl Exporter {
+    /// Append the lines of a record to `lines`.
+    fn encode(&self, record: &Record, export_kind: ExportKind, lines: &mut Vec<String>) {
+        let descriptor = &record.descriptor;
+        let name = match &self.prefix {
+            Some(prefix) => format!("{}.{}", prefix, descriptor.name),
+            None => descriptor.name.clone(),
+        };
+        let name = sanitize(&name);
+        let mut line = |suffix: &str, value: &str, metric_type: &str| {
+            lines.push(self.line(&name, suffix, value, metric_type, record))
+        };
+        let delta = export_kind == ExportKind::Delta;
+
+        match &record.aggregation {
+            Aggregation::Sum(sum) if delta && descriptor.monotonic => {
+                line("", &sum.to_string(), "c");
+            }
+            // Sums that can decrease are sent as gauge increments
+            Aggregation::Sum(sum) if delta => {
+                line("", &signed(*sum), "g");
+            }
+            Aggregation::Sum(sum) => gauge(&mut line, "", sum, sum.is_negative()),
+            Aggregation::LastValue { value, .. } => {
+                gauge(&mut line, "", value, value.is_negative())
+            }
+            Aggregation::MinMaxSumCount {
+                min,
+                max,
+                sum,
+                count,
+            } => {
+                total(&mut line, ".count", count, false, delta);
+                total(&mut line, ".sum", sum, sum.is_negative(), delta);
+                line(".min", &min.to_string(), "g");
+                line(".max", &max.to_string(), "g");
+            }
+            Aggregation::Histogram { sum, count, .. } => {
+                total(&mut line, ".count", count, false, delta);
+                total(&mut line, ".sum", sum, sum.is_negative(), delta);
+            }
+            Aggregation::Sketch(sketch) => {
+                let sum = sketch.sum();
+                total(&mut line, ".count", sketch.count(), false, delta);
+                total(&mut line, ".sum", sum, sum < 0.0, delta);
+                for (suffix, quantile) in &[(".p50", 0.5), (".p90", 0.9), (".p99", 0.99)] {
+                    if let Some(value) = sketch.quantile(*quantile) {
+                        line(suffix, &value.to_string(), "g");
+                    }
+                }
+            }
+        }
+    }
+
+    fn line(
+        &self,
+        name: &str,
+        suffix: &str,
+        value: &str,
+        metric_type: &str,
+        record: &Record,
+    ) -> String {
+        let mut line = format!("{}{}:{}|{}", name, suffix, value, metric_type);
+        if self.sample_rate < 1.0 {
+            let _ = write!(line, "|@{}", self.sample_rate);
+        }
+        if self.tags && !record.labels.is_empty() {
+            line.push_str("|#");
+            for (index, (key, value)) in record.labels.iter().enumerate() {
+                if index > 0 {
+                    line.push(',');
+                }
+                let _ = write!(line, "{}:{}", sanitize(key), sanitize(value));
+            }
+        }
+
+        line
+    }
+
+    /// Send the lines in packets of at most `max_packet_size` bytes.
+    fn send(&self, lines: &[String]) -> io::Result<()> {
+        let mut packet = String::new();
+        for line in lines {
+            if !packet.is_empty() && packet.len() + 1 + line.len() > self.max_packet_size {
+                self.socket.send(packet.as_bytes())?;
+                packet.clear();
+            }
+            if !packet.is_empty() {
+                packet.push('\n');
+            }
+            packet.push_str(line);
+        }
+        if !packet.is_empty() {
+            self.socket.send(packet.as_bytes())?;
+        }
+
+        Ok(())
+    }
+}
+
+impl metrics::MetricExporter for Exporter {
+    /// Export metric records to the StatsD agent
+    fn export(&self, checkpoint_set: &metrics::CheckpointSet) -> metrics::ExportResult {
+        let mut lines = Vec::new();
+        for record in &checkpoint_set.records {
+            if self.sample_rate >= 1.0 || rand::random::<f64>() < self.sample_rate {
+                self.encode(record, checkpoint_set.export_kind, &mut lines);
+            }
+        }
+
+        match self.send(&lines) {
+            Ok(()) => metrics::ExportResult::Success,
+            Err(_) => metrics::ExportResult::FailedNotRetryable,
+        }
+    }
+
+    fn shutdown(&self) {}
+}
+
+/// Send a gauge set to `value`.
+fn gauge(
+    line: &mut impl FnMut(&str, &str, &str),
+    suffix: &str,
+    value: impl Display,
+    negative: bool,
+) {
+    // A signed value is a gauge increment, so negative values first reset
+    // the gauge
+    if negative {
+        line(suffix, "0", "g");
+    }
+    line(suffix, &value.to_string(), "g");
+}
+
+/// Send a sum or a count, as a counter for deltas or as a gauge set to the
+/// running total for cumulative checkpoints.
+fn total(
+    line: &mut impl FnMut(&str, &str, &str),
+    suffix: &str,
+    value: impl Display,
+    negative: bool,
+    delta: bool,
+) {
+    if delta {
+        line(suffix, &value.to_string(), "c");
+    } else {
+        gauge(line, suffix, value, negative);
+    }
+}
+
+/// Format a number with an explicit sign, as StatsD gauge increments.
+fn signed(number: Number) -> String {
+    if number.is_negative() {
+        number.to_string()
+    } else {
+        format!("+{}", number)
+    }
+}
+
+/// Replace the characters separating the fields of a line with underscores.
+fn sanitize(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            ':' | '|' | '@' | '#' | ',' | '\n' => '_',
+            c => c,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{self, Counter, Gauge, Measure, Meter};
+    use crate::exporter::metrics::{ExportKind, MetricExporter};
+    use crate::sdk;
+    use std::time::Duration;
+
+    fn agent() -> (UdpSocket, SocketAddr) {
+        let agent = UdpSocket::bind("127.0.0.1:0").unwrap();
+        agent
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let addr = agent.local_addr().unwrap();
+        (agent, addr)
+    }
+
+    fn receive(agent: &UdpSocket) -> String {
+        let mut buf = [0; 2048];
+        let len = agent.recv(&mut buf).unwrap();
+        String::from_utf8(buf[..len].to_vec()).unwrap()
+    }
+
+    #[test]
+    fn sends_statsd_lines() {
+        let (agent, addr) = agent();
+        let exporter = Builder::default()
+            .with_agent_endpoint(addr)
+            .with_prefix("app")
+            .with_tags(true)
+            .init()
+            .unwrap();
+        let meter = sdk::Meter::builder("test")
+            .with_export_kind(ExportKind::Delta)
+            .build();
+        let labels = meter.labels(vec![api::KeyValue::new("method", "GET")]);
+        meter
+            .new_i64_counter("requests", api::MetricOptions::default())
+            .add(3, &labels);
+        meter
+            .new_i64_counter(
+                "connections",
+                api::MetricOptions::default().with_monotonic(false),
+            )
+            .add(-2, &meter.labels(vec![]));
+        meter
+            .new_f64_gauge("temperature", api::MetricOptions::default())
+            .set(-1.5, &meter.labels(vec![]));
+        let latency = meter.new_i64_measure("latency", api::MetricOptions::default());
+        latency.record(5, &labels);
+        latency.record(15, &labels);
+
+        assert_eq!(
+            exporter.export(&meter.collect()),
+            metrics::ExportResult::Success
+        );
+        let mut lines = receive(&agent)
+            .lines()
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        lines.sort();
+        assert_eq!(
+            lines,
+            vec![
+                "app.connections:-2|g",
+                "app.latency.count:2|c|#method:GET",
+                "app.latency.max:15|g|#method:GET",
+                "app.latency.min:5|g|#method:GET",
+                "app.latency.sum:20|c|#method:GET",
+                "app.requests:3|c|#method:GET",
+                "app.temperature:-1.5|g",
+                "app.temperature:0|g",
+            ]
+        );
+    }
+
+    #[test]
+    fn sends_cumulative_sums_as_gauges() {
+        let (agent, addr) = agent();
+        let exporter = Builder::default().with_agent_endpoint(addr).init().unwrap();
+        let meter = sdk::Meter::builder("test")
+            .with_export_kind(ExportKind::Cumulative)
+            .build();
+        let labels = meter.labels(vec![]);
+        let requests = meter.new_i64_counter("requests", api::MetricOptions::default());
+        let connections = meter.new_i64_counter(
+            "connections",
+            api::MetricOptions::default().with_monotonic(false),
+        );
+        let latency = meter.new_i64_measure("latency", api::MetricOptions::default());
+
+        let mut exports = Vec::new();
+        for _ in 0..2 {
+            requests.add(3, &labels);
+            connections.add(-2, &labels);
+            latency.record(5, &labels);
+            exporter.export(&meter.collect());
+            let mut lines = receive(&agent)
+                .lines()
+                .map(str::to_string)
+                .collect::<Vec<_>>();
+            lines.sort();
+            exports.push(lines);
+        }
+
+        // The agent receives the running totals, which it must not add up
+        assert_eq!(
+            exports[1],
+            vec![
+                "connections:-4|g",
+                "connections:0|g",
+                "latency.count:2|g",
+                "latency.max:5|g",
+                "latency.min:5|g",
+                "latency.sum:10|g",
+                "requests:6|g",
+            ]
+        );
+    }
+
+    #[test]
+    fn batches_lines_into_packets() {
+        let (agent, addr) = agent();
+        let exporter = Builder::default()
+            .with_agent_endpoint(addr)
+            .with_max_packet_size(40)
+            .init()
+            .unwrap();
+        let lines = vec![
+            "a:1|c".to_string(),
+            "b".repeat(30) + ":1|c",
+            "c".repeat(50) + ":1|c",
+        ];
+        exporter.send(&lines).unwrap();
+
+        assert_eq!(receive(&agent), format!("a:1|c\n{}:1|c", "b".repeat(30)));
+        assert_eq!(receive(&agent), format!("{}:1|c", "c".repeat(50)));
+    }
+
+    #[test]
+    fn annotates_sampled_lines() {
+        let (agent, addr) = agent();
+        let exporter = Builder::default()
+            .with_agent_endpoint(addr)
+            .with_sample_rate(0.5)
+            .init()
+            .unwrap();
+        let meter = sdk::Meter::builder("test")
+            .with_export_kind(ExportKind::Delta)
+            .build();
+        let counter = meter.new_i64_counter("requests", api::MetricOptions::default());
+        let labels = meter.labels(vec![]);
+
+        // Each export sends the line with a probability of one half
+        for _ in 0..64 {
+            counter.add(1, &labels);
+            exporter.export(&meter.collect());
+        }
+        assert_eq!(receive(&agent), "requests:1|c|@0.5");
+    }
+}
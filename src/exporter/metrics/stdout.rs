@@ -0,0 +1,112 @@
+//! # Stdout Metric Exporter
+//!
+//! The stdout [`MetricExporter`] writes debug printed [`Record`]s to its
+//! configured [`Write`] instance. By default it will write to [`Stdout`].
+//!
+//! [`MetricExporter`]: ../trait.MetricExporter.html
+//! [`Record`]: ../struct.Record.html
+//! [`Write`]: std::io::Write
+//! [`Stdout`]: std::io::Stdout
+//!
+//! # Examples
+//!
+//! ```
+//! use opentelemetry::exporter::metrics::stdout;
+//! use opentelemetry::sdk;
+//!
+//! // Create a new stdout exporter that writes pretty printed metrics
+//! let exporter = stdout::Builder::default().with_pretty_print(true).init();
+//! let meter = sdk::Meter::new("example");
+//! let _controller = sdk::PushController::builder(meter, exporter).build();
+//! ```
+use crate::exporter::metrics;
+use std::fmt::Debug;
+use std::io::{self, stdout, Stdout, Write};
+use std::sync::Mutex;
+
+/// Builder
+#[derive(Debug)]
+pub struct Builder<W: Write + Debug> {
+    writer: Mutex<W>,
+    pretty_print: bool,
+}
+
+impl<W: Write + Debug> Builder<W> {
+    /// Specify the writer to use with this exporter
+    pub fn with_writer<T: Write + Debug>(self, writer: T) -> Builder<T> {
+        Builder {
+            writer: Mutex::new(writer),
+            pretty_print: self.pretty_print,
+        }
+    }
+
+    /// Specify the pretty print setting for this exporter
+    pub fn with_pretty_print(self, pretty_print: bool) -> Self {
+        Builder {
+            pretty_print,
+            ..self
+        }
+    }
+
+    /// Build a new exporter
+    pub fn init(self) -> Exporter<W> {
+        Exporter {
+            writer: self.writer,
+            pretty_print: self.pretty_print,
+        }
+    }
+}
+
+impl Default for Builder<Stdout> {
+    /// Return the default Exporter Builder.
+    fn default() -> Self {
+        Builder {
+            writer: Mutex::new(stdout()),
+            pretty_print: false,
+        }
+    }
+}
+
+/// A [`MetricExporter`] that writes to [`Stdout`] or other configured [`Write`].
+///
+/// [`MetricExporter`]: ../trait.MetricExporter.html
+/// [`Write`]: std::io::Write
+/// [`Stdout`]: std::io::Stdout
+#[derive(Debug)]
+pub struct Exporter<W: Write> {
+    writer: Mutex<W>,
+    pretty_print: bool,
+}
+
+impl<W> metrics::MetricExporter for Exporter<W>
+where
+    W: Write + Debug + Send + 'static,
+{
+    /// Export metric records to stdout
+    fn export(&self, checkpoint_set: &metrics::CheckpointSet) -> metrics::ExportResult {
+        let writer = self
+            .writer
+            .try_lock()
+            .map_err(|_| io::Error::from(io::ErrorKind::WouldBlock));
+        let result = writer.and_then(|mut w| {
+            for record in &checkpoint_set.records {
+                if self.pretty_print {
+                    w.write_all(format!("{:#?}\n", record).as_bytes())?;
+                } else {
+                    w.write_all(format!("{:?}\n", record).as_bytes())?;
+                }
+            }
+
+            Ok(0)
+        });
+
+        if result.is_ok() {
+            metrics::ExportResult::Success
+        } else {
+            metrics::ExportResult::FailedNotRetryable
+        }
+    }
+
+    /// Ignored for now.
+    fn shutdown(&self) {}
+}
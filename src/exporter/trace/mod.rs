@@ -1,5 +1,6 @@
 //! Trace exporters
 use crate::{api, sdk};
+use futures::future::{self, BoxFuture};
 #[cfg(feature = "serialize")]
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
@@ -7,16 +8,7 @@ use std::time::SystemTime;
 
 pub mod stdout;
 
-/// Describes the result of an export.
-#[derive(Clone, Debug, PartialEq, Eq)]
-pub enum ExportResult {
-    /// Batch is successfully exported.
-    Success,
-    /// Batch export failed. Caller must not retry.
-    FailedNotRetryable,
-    /// Batch export failed transiently. Caller should record error and may retry.
-    FailedRetryable,
-}
+pub use crate::exporter::ExportResult;
 
 /// `SpanExporter` defines the interface that protocol-specific exporters must
 /// implement so that they can be plugged into OpenTelemetry SDK and support
@@ -56,6 +48,60 @@ pub trait SpanExporter: Send + Sync + std::fmt::Debug {
     fn shutdown(&self);
 }
 
+/// `AsyncSpanExporter` is the non-blocking variant of [`SpanExporter`], used by
+/// the [`BatchSpanProcessor`] so that exports do not block the executor thread
+/// driving its worker.
+///
+/// Synchronous exporters can be used where an `AsyncSpanExporter` is expected
+/// by wrapping them in a [`SyncSpanExporterAdapter`].
+///
+/// [`SpanExporter`]: trait.SpanExporter.html
+/// [`BatchSpanProcessor`]: ../../sdk/trace/span_processor/struct.BatchSpanProcessor.html
+/// [`SyncSpanExporterAdapter`]: struct.SyncSpanExporterAdapter.html
+pub trait AsyncSpanExporter: Send + Sync + std::fmt::Debug {
+    /// Exports a batch of telemetry data, returning a future that resolves once
+    /// the batch has been transmitted or the export has failed.
+    ///
+    /// This function will never be called concurrently for the same exporter
+    /// instance. It can be called again only after the returned future has
+    /// resolved.
+    ///
+    /// The returned future must not stay pending indefinitely, there must be a
+    /// reasonable upper limit after which it resolves with an error result.
+    fn export(&self, batch: Vec<Arc<SpanData>>) -> BoxFuture<'static, ExportResult>;
+
+    /// Shuts down the exporter. Called when SDK is shut down. This is an
+    /// opportunity for exporter to do any cleanup required.
+    fn shutdown(&self);
+}
+
+/// Adapts a synchronous [`SpanExporter`] to the [`AsyncSpanExporter`]
+/// interface. Exports still run on the thread polling the returned future.
+///
+/// [`SpanExporter`]: trait.SpanExporter.html
+/// [`AsyncSpanExporter`]: trait.AsyncSpanExporter.html
+#[derive(Debug)]
+pub struct SyncSpanExporterAdapter<E> {
+    exporter: E,
+}
+
+impl<E: SpanExporter> SyncSpanExporterAdapter<E> {
+    /// Wrap a synchronous exporter.
+    pub fn new(exporter: E) -> Self {
+        SyncSpanExporterAdapter { exporter }
+    }
+}
+
+impl<E: SpanExporter> AsyncSpanExporter for SyncSpanExporterAdapter<E> {
+    fn export(&self, batch: Vec<Arc<SpanData>>) -> BoxFuture<'static, ExportResult> {
+        Box::pin(future::ready(self.exporter.export(batch)))
+    }
+
+    fn shutdown(&self) {
+        self.exporter.shutdown();
+    }
+}
+
 /// `SpanData` contains all the information collected by a `Span` and can be used
 /// by exporters as a standard input.
 #[cfg_attr(feature = "serialize", derive(Deserialize, Serialize))]
@@ -0,0 +1,823 @@
+//! # Global Metrics API
+//!
+//! The global meter provider gives libraries access to named [`Meter`]s
+//! without knowing the SDK used by the application, through the boxed
+//! [`BoxedMeter`] and its type-erased instruments. Until the application
+//! sets a provider with [`set_meter_provider`], meters are no-ops.
+//!
+//! Instruments are bound to the provider that was set when they were created,
+//! so libraries should create them after the application is initialized, or
+//! on demand.
+//!
+//! [`Meter`]: ../api/metrics/trait.Meter.html
+//! [`BoxedMeter`]: struct.BoxedMeter.html
+//! [`set_meter_provider`]: fn.set_meter_provider.html
+use crate::api::{self, MeterProvider};
+use std::any::Any;
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::{Arc, RwLock};
+
+type DynInstrument = dyn GenericInstrument + Send + Sync;
+type DynInstrumentHandle = dyn api::InstrumentHandle + Send + Sync;
+type ObserverCallback<T> = Box<dyn Fn(api::ObserverResult<'_, T, BoxedLabelSet>) + Send + Sync>;
+
+/// Wraps the label set of the [`BoxedMeter`]'s underlying meter so it can be
+/// used generically by applications without knowing the underlying type.
+///
+/// [`BoxedMeter`]: struct.BoxedMeter.html
+#[derive(Clone)]
+pub struct BoxedLabelSet(Arc<dyn Any + Send + Sync>);
+
+impl BoxedLabelSet {
+    fn downcast<LS: 'static>(&self) -> api::metrics::Result<&LS> {
+        self.0
+            .downcast_ref()
+            .ok_or(api::MetricsError::IncompatibleLabelSet)
+    }
+}
+
+impl fmt::Debug for BoxedLabelSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BoxedLabelSet").finish()
+    }
+}
+
+impl api::LabelSet for BoxedLabelSet {}
+
+/// Allows a specific instrument to be used generically by the boxed
+/// instruments by mirroring the interface and boxing the label set and
+/// handle types.
+pub trait GenericInstrument: fmt::Debug + 'static {
+    /// Records a single measurement value, returning an error if the label
+    /// set was not created by the instrument's meter.
+    fn try_record_one_boxed(
+        &self,
+        value: api::MeasurementValue,
+        label_set: &BoxedLabelSet,
+    ) -> api::metrics::Result<()>;
+
+    /// Returns a trait object handle so the underlying implementation can be
+    /// swapped out at runtime.
+    fn acquire_handle_boxed(
+        &self,
+        label_set: &BoxedLabelSet,
+    ) -> api::metrics::Result<Arc<DynInstrumentHandle>>;
+}
+
+macro_rules! erased_instrument {
+    ($name:ident, $api_trait:ident) => {
+        /// Adapts an instrument to `GenericInstrument`.
+        struct $name<I, T, LS> {
+            instrument: I,
+            _marker: PhantomData<fn(T, LS)>,
+        }
+
+        impl<I, T, LS> $name<I, T, LS> {
+            fn new(instrument: I) -> Self {
+                $name {
+                    instrument,
+                    _marker: PhantomData,
+                }
+            }
+        }
+
+        impl<I: fmt::Debug, T, LS> fmt::Debug for $name<I, T, LS> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.instrument.fmt(f)
+            }
+        }
+
+        impl<I, T, LS> GenericInstrument for $name<I, T, LS>
+        where
+            I: api::$api_trait<T, LS> + fmt::Debug + 'static,
+            <I as api::$api_trait<T, LS>>::Handle: Send + Sync + 'static,
+            T: Into<api::MeasurementValue> + 'static,
+            LS: api::LabelSet + 'static,
+        {
+            fn try_record_one_boxed(
+                &self,
+                value: api::MeasurementValue,
+                label_set: &BoxedLabelSet,
+            ) -> api::metrics::Result<()> {
+                self.instrument
+                    .try_record_one(value, label_set.downcast::<LS>()?)
+            }
+
+            fn acquire_handle_boxed(
+                &self,
+                label_set: &BoxedLabelSet,
+            ) -> api::metrics::Result<Arc<DynInstrumentHandle>> {
+                let label_set = label_set.downcast::<LS>()?;
+                Ok(Arc::new(self.instrument.acquire_handle(label_set)))
+            }
+        }
+    };
+}
+
+erased_instrument!(ErasedCounter, Counter);
+erased_instrument!(ErasedGauge, Gauge);
+erased_instrument!(ErasedMeasure, Measure);
+
+/// A handle of a boxed instrument, recording values for a fixed label set.
+///
+/// Values recorded by a handle acquired with an incompatible label set are
+/// discarded.
+pub struct BoxedHandle<T> {
+    handle: api::metrics::Result<Arc<DynInstrumentHandle>>,
+    _marker: PhantomData<fn(T)>,
+}
+
+impl<T> fmt::Debug for BoxedHandle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BoxedHandle")
+            .field("bound", &self.handle.is_ok())
+            .finish()
+    }
+}
+
+impl<T> Clone for BoxedHandle<T> {
+    fn clone(&self) -> Self {
+        BoxedHandle {
+            handle: self.handle.clone(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> api::InstrumentHandle for BoxedHandle<T> {
+    /// Record a single measurement value for the bound label set.
+    fn record_one(&self, value: api::MeasurementValue) {
+        let _ = self.try_record_one(value);
+    }
+
+    /// Record a single measurement value for the bound label set.
+    fn try_record_one(&self, value: api::MeasurementValue) -> api::metrics::Result<()> {
+        match &self.handle {
+            Ok(handle) => handle.try_record_one(value),
+            Err(err) => Err(err.clone()),
+        }
+    }
+}
+
+impl<T: Into<api::MeasurementValue>> api::CounterHandle<T> for BoxedHandle<T> {}
+
+impl<T: Into<api::MeasurementValue>> api::GaugeHandle<T> for BoxedHandle<T> {}
+
+impl<T: Into<api::MeasurementValue>> api::MeasureHandle<T> for BoxedHandle<T> {}
+
+macro_rules! boxed_instrument {
+    ($(#[$doc:meta])* $name:ident, $api_trait:ident) => {
+        $(#[$doc])*
+        #[derive(Debug)]
+        pub struct $name<T> {
+            instrument: Arc<DynInstrument>,
+            _marker: PhantomData<fn(T)>,
+        }
+
+        impl<T> Clone for $name<T> {
+            fn clone(&self) -> Self {
+                $name {
+                    instrument: self.instrument.clone(),
+                    _marker: PhantomData,
+                }
+            }
+        }
+
+        impl<T> api::Instrument<BoxedLabelSet> for $name<T> {
+            /// Record a single measurement value for the given label set.
+            /// Rejected values are discarded.
+            fn record_one(&self, value: api::MeasurementValue, label_set: &BoxedLabelSet) {
+                let _ = self.instrument.try_record_one_boxed(value, label_set);
+            }
+
+            /// Record a single measurement value for the given label set.
+            fn try_record_one(
+                &self,
+                value: api::MeasurementValue,
+                label_set: &BoxedLabelSet,
+            ) -> api::metrics::Result<()> {
+                self.instrument.try_record_one_boxed(value, label_set)
+            }
+        }
+
+        impl<T> api::$api_trait<T, BoxedLabelSet> for $name<T>
+        where
+            T: Into<api::MeasurementValue> + 'static,
+        {
+            type Handle = BoxedHandle<T>;
+
+            /// Creates a `Measurement` object to be used by a `Meter` when batch recording.
+            fn measurement(&self, value: T) -> api::Measurement<BoxedLabelSet> {
+                api::Measurement::new(Arc::new(self.clone()), value.into())
+            }
+
+            /// Creates a handle for this instrument.
+            fn acquire_handle(&self, labels: &BoxedLabelSet) -> Self::Handle {
+                BoxedHandle {
+                    handle: self.instrument.acquire_handle_boxed(labels),
+                    _marker: PhantomData,
+                }
+            }
+        }
+    };
+}
+
+boxed_instrument!(
+    /// Wraps the [`BoxedMeter`]'s `Counter` so it can be used generically by
+    /// applications without knowing the underlying type.
+    ///
+    /// [`BoxedMeter`]: struct.BoxedMeter.html
+    BoxedCounter,
+    Counter
+);
+boxed_instrument!(
+    /// Wraps the [`BoxedMeter`]'s `Gauge` so it can be used generically by
+    /// applications without knowing the underlying type.
+    ///
+    /// [`BoxedMeter`]: struct.BoxedMeter.html
+    BoxedGauge,
+    Gauge
+);
+boxed_instrument!(
+    /// Wraps the [`BoxedMeter`]'s `Measure` so it can be used generically by
+    /// applications without knowing the underlying type.
+    ///
+    /// [`BoxedMeter`]: struct.BoxedMeter.html
+    BoxedMeasure,
+    Measure
+);
+
+/// Allows a specific [`Meter`] to be used generically by [`BoxedMeter`]
+/// instances by mirroring the interface and boxing the return types.
+///
+/// [`Meter`]: ../api/metrics/trait.Meter.html
+/// [`BoxedMeter`]: struct.BoxedMeter.html
+pub trait GenericMeter: fmt::Debug + 'static {
+    /// Returns a boxed label set of the underlying meter.
+    fn labels_boxed(&self, key_values: Vec<api::KeyValue>) -> BoxedLabelSet;
+
+    /// Creates a new `i64` counter as a trait object.
+    fn new_i64_counter_boxed(&self, name: String, opts: api::MetricOptions) -> Arc<DynInstrument>;
+
+    /// Creates a new `f64` counter as a trait object.
+    fn new_f64_counter_boxed(&self, name: String, opts: api::MetricOptions) -> Arc<DynInstrument>;
+
+    /// Creates a new `i64` gauge as a trait object.
+    fn new_i64_gauge_boxed(&self, name: String, opts: api::MetricOptions) -> Arc<DynInstrument>;
+
+    /// Creates a new `f64` gauge as a trait object.
+    fn new_f64_gauge_boxed(&self, name: String, opts: api::MetricOptions) -> Arc<DynInstrument>;
+
+    /// Creates a new `i64` measure as a trait object.
+    fn new_i64_measure_boxed(&self, name: String, opts: api::MetricOptions) -> Arc<DynInstrument>;
+
+    /// Creates a new `f64` measure as a trait object.
+    fn new_f64_measure_boxed(&self, name: String, opts: api::MetricOptions) -> Arc<DynInstrument>;
+
+    /// Registers a new `i64` value observer with a boxed callback.
+    fn register_i64_value_observer_boxed(
+        &self,
+        name: String,
+        opts: api::MetricOptions,
+        callback: ObserverCallback<i64>,
+    );
+
+    /// Registers a new `i64` sum observer with a boxed callback.
+    fn register_i64_sum_observer_boxed(
+        &self,
+        name: String,
+        opts: api::MetricOptions,
+        callback: ObserverCallback<i64>,
+    );
+
+    /// Registers a new `i64` up-down sum observer with a boxed callback.
+    fn register_i64_up_down_sum_observer_boxed(
+        &self,
+        name: String,
+        opts: api::MetricOptions,
+        callback: ObserverCallback<i64>,
+    );
+
+    /// Registers a new `f64` value observer with a boxed callback.
+    fn register_f64_value_observer_boxed(
+        &self,
+        name: String,
+        opts: api::MetricOptions,
+        callback: ObserverCallback<f64>,
+    );
+
+    /// Registers a new `f64` sum observer with a boxed callback.
+    fn register_f64_sum_observer_boxed(
+        &self,
+        name: String,
+        opts: api::MetricOptions,
+        callback: ObserverCallback<f64>,
+    );
+
+    /// Registers a new `f64` up-down sum observer with a boxed callback.
+    fn register_f64_up_down_sum_observer_boxed(
+        &self,
+        name: String,
+        opts: api::MetricOptions,
+        callback: ObserverCallback<f64>,
+    );
+}
+
+/// Adapts an observer callback reporting to boxed label sets to the label
+/// sets of the underlying meter. Observations with incompatible label sets
+/// are discarded.
+fn unbox_callback<T, LS>(
+    callback: ObserverCallback<T>,
+    convert: fn(api::MeasurementValue) -> T,
+) -> impl Fn(api::ObserverResult<'_, T, LS>) + Send + Sync + 'static
+where
+    T: Into<api::MeasurementValue> + 'static,
+    LS: api::LabelSet + 'static,
+{
+    move |result| {
+        callback(api::ObserverResult::new(&|value, label_set| {
+            if let Ok(label_set) = label_set.downcast::<LS>() {
+                result.observe(convert(value), label_set)
+            }
+        }))
+    }
+}
+
+impl<M, LS> GenericMeter for M
+where
+    M: api::Meter<LabelSet = LS> + fmt::Debug + 'static,
+    LS: api::LabelSet + Send + Sync + 'static,
+    M::I64Counter: fmt::Debug + Send + Sync + 'static,
+    M::F64Counter: fmt::Debug + Send + Sync + 'static,
+    M::I64Gauge: fmt::Debug + Send + Sync + 'static,
+    M::F64Gauge: fmt::Debug + Send + Sync + 'static,
+    M::I64Measure: fmt::Debug + Send + Sync + 'static,
+    M::F64Measure: fmt::Debug + Send + Sync + 'static,
+    <M::I64Counter as api::Counter<i64, LS>>::Handle: Send + Sync + 'static,
+    <M::F64Counter as api::Counter<f64, LS>>::Handle: Send + Sync + 'static,
+    <M::I64Gauge as api::Gauge<i64, LS>>::Handle: Send + Sync + 'static,
+    <M::F64Gauge as api::Gauge<f64, LS>>::Handle: Send + Sync + 'static,
+    <M::I64Measure as api::Measure<i64, LS>>::Handle: Send + Sync + 'static,
+    <M::F64Measure as api::Measure<f64, LS>>::Handle: Send + Sync + 'static,
+{
+    fn labels_boxed(&self, key_values: Vec<api::KeyValue>) -> BoxedLabelSet {
+        BoxedLabelSet(Arc::new(self.labels(key_values)))
+    }
+
+    fn new_i64_counter_boxed(&self, name: String, opts: api::MetricOptions) -> Arc<DynInstrument> {
+        Arc::new(ErasedCounter::<_, i64, LS>::new(
+            self.new_i64_counter(name, opts),
+        ))
+    }
+
+    fn new_f64_counter_boxed(&self, name: String, opts: api::MetricOptions) -> Arc<DynInstrument> {
+        Arc::new(ErasedCounter::<_, f64, LS>::new(
+            self.new_f64_counter(name, opts),
+        ))
+    }
+
+    fn new_i64_gauge_boxed(&self, name: String, opts: api::MetricOptions) -> Arc<DynInstrument> {
+        Arc::new(ErasedGauge::<_, i64, LS>::new(
+            self.new_i64_gauge(name, opts),
+        ))
+    }
+
+    fn new_f64_gauge_boxed(&self, name: String, opts: api::MetricOptions) -> Arc<DynInstrument> {
+        Arc::new(ErasedGauge::<_, f64, LS>::new(
+            self.new_f64_gauge(name, opts),
+        ))
+    }
+
+    fn new_i64_measure_boxed(&self, name: String, opts: api::MetricOptions) -> Arc<DynInstrument> {
+        Arc::new(ErasedMeasure::<_, i64, LS>::new(
+            self.new_i64_measure(name, opts),
+        ))
+    }
+
+    fn new_f64_measure_boxed(&self, name: String, opts: api::MetricOptions) -> Arc<DynInstrument> {
+        Arc::new(ErasedMeasure::<_, f64, LS>::new(
+            self.new_f64_measure(name, opts),
+        ))
+    }
+    fn register_i64_value_observer_boxed(
+        &self,
+        name: String,
+        opts: api::MetricOptions,
+        callback: ObserverCallback<i64>,
+    ) {
+        self.register_i64_value_observer(
+            name,
+            opts,
+            unbox_callback(callback, api::MeasurementValue::into_i64),
+        )
+    }
+
+    fn register_i64_sum_observer_boxed(
+        &self,
+        name: String,
+        opts: api::MetricOptions,
+        callback: ObserverCallback<i64>,
+    ) {
+        self.register_i64_sum_observer(
+            name,
+            opts,
+            unbox_callback(callback, api::MeasurementValue::into_i64),
+        )
+    }
+
+    fn register_i64_up_down_sum_observer_boxed(
+        &self,
+        name: String,
+        opts: api::MetricOptions,
+        callback: ObserverCallback<i64>,
+    ) {
+        self.register_i64_up_down_sum_observer(
+            name,
+            opts,
+            unbox_callback(callback, api::MeasurementValue::into_i64),
+        )
+    }
+
+    fn register_f64_value_observer_boxed(
+        &self,
+        name: String,
+        opts: api::MetricOptions,
+        callback: ObserverCallback<f64>,
+    ) {
+        self.register_f64_value_observer(
+            name,
+            opts,
+            unbox_callback(callback, api::MeasurementValue::into_f64),
+        )
+    }
+
+    fn register_f64_sum_observer_boxed(
+        &self,
+        name: String,
+        opts: api::MetricOptions,
+        callback: ObserverCallback<f64>,
+    ) {
+        self.register_f64_sum_observer(
+            name,
+            opts,
+            unbox_callback(callback, api::MeasurementValue::into_f64),
+        )
+    }
+
+    fn register_f64_up_down_sum_observer_boxed(
+        &self,
+        name: String,
+        opts: api::MetricOptions,
+        callback: ObserverCallback<f64>,
+    ) {
+        self.register_f64_up_down_sum_observer(
+            name,
+            opts,
+            unbox_callback(callback, api::MeasurementValue::into_f64),
+        )
+    }
+}
+
+/// Wraps the [`GlobalMeterProvider`]'s [`Meter`] so it can be used
+/// generically by applications without knowing the underlying type.
+///
+/// [`GlobalMeterProvider`]: struct.GlobalMeterProvider.html
+/// [`Meter`]: ../api/metrics/trait.Meter.html
+#[derive(Debug)]
+pub struct BoxedMeter(Box<dyn GenericMeter + Send + Sync>);
+
+impl api::Meter for BoxedMeter {
+    type LabelSet = BoxedLabelSet;
+    type I64Counter = BoxedCounter<i64>;
+    type F64Counter = BoxedCounter<f64>;
+    type I64Gauge = BoxedGauge<i64>;
+    type F64Gauge = BoxedGauge<f64>;
+    type I64Measure = BoxedMeasure<i64>;
+    type F64Measure = BoxedMeasure<f64>;
+
+    /// Builds a label set of the underlying meter.
+    fn labels(&self, key_values: Vec<api::KeyValue>) -> Self::LabelSet {
+        self.0.labels_boxed(key_values)
+    }
+
+    /// Creates a new `i64` counter with a given name and customized with passed options.
+    fn new_i64_counter<S: Into<String>>(
+        &self,
+        name: S,
+        opts: api::MetricOptions,
+    ) -> Self::I64Counter {
+        BoxedCounter {
+            instrument: self.0.new_i64_counter_boxed(name.into(), opts),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Creates a new `f64` counter with a given name and customized with passed options.
+    fn new_f64_counter<S: Into<String>>(
+        &self,
+        name: S,
+        opts: api::MetricOptions,
+    ) -> Self::F64Counter {
+        BoxedCounter {
+            instrument: self.0.new_f64_counter_boxed(name.into(), opts),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Creates a new `i64` gauge with a given name and customized with passed options.
+    fn new_i64_gauge<S: Into<String>>(&self, name: S, opts: api::MetricOptions) -> Self::I64Gauge {
+        BoxedGauge {
+            instrument: self.0.new_i64_gauge_boxed(name.into(), opts),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Creates a new `f64` gauge with a given name and customized with passed options.
+    fn new_f64_gauge<S: Into<String>>(&self, name: S, opts: api::MetricOptions) -> Self::F64Gauge {
+        BoxedGauge {
+            instrument: self.0.new_f64_gauge_boxed(name.into(), opts),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Creates a new `i64` measure with a given name and customized with passed options.
+    fn new_i64_measure<S: Into<String>>(
+        &self,
+        name: S,
+        opts: api::MetricOptions,
+    ) -> Self::I64Measure {
+        BoxedMeasure {
+            instrument: self.0.new_i64_measure_boxed(name.into(), opts),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Creates a new `f64` measure with a given name and customized with passed options.
+    fn new_f64_measure<S: Into<String>>(
+        &self,
+        name: S,
+        opts: api::MetricOptions,
+    ) -> Self::F64Measure {
+        BoxedMeasure {
+            instrument: self.0.new_f64_measure_boxed(name.into(), opts),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Registers a new `i64` value observer with a given name and customized with passed options.
+    fn register_i64_value_observer<S, F>(&self, name: S, opts: api::MetricOptions, callback: F)
+    where
+        S: Into<String>,
+        F: Fn(api::ObserverResult<'_, i64, Self::LabelSet>) + Send + Sync + 'static,
+    {
+        self.0
+            .register_i64_value_observer_boxed(name.into(), opts, Box::new(callback))
+    }
+
+    /// Registers a new `i64` sum observer with a given name and customized with passed options.
+    fn register_i64_sum_observer<S, F>(&self, name: S, opts: api::MetricOptions, callback: F)
+    where
+        S: Into<String>,
+        F: Fn(api::ObserverResult<'_, i64, Self::LabelSet>) + Send + Sync + 'static,
+    {
+        self.0
+            .register_i64_sum_observer_boxed(name.into(), opts, Box::new(callback))
+    }
+
+    /// Registers a new `i64` up-down sum observer with a given name and customized with passed options.
+    fn register_i64_up_down_sum_observer<S, F>(
+        &self,
+        name: S,
+        opts: api::MetricOptions,
+        callback: F,
+    ) where
+        S: Into<String>,
+        F: Fn(api::ObserverResult<'_, i64, Self::LabelSet>) + Send + Sync + 'static,
+    {
+        self.0
+            .register_i64_up_down_sum_observer_boxed(name.into(), opts, Box::new(callback))
+    }
+
+    /// Registers a new `f64` value observer with a given name and customized with passed options.
+    fn register_f64_value_observer<S, F>(&self, name: S, opts: api::MetricOptions, callback: F)
+    where
+        S: Into<String>,
+        F: Fn(api::ObserverResult<'_, f64, Self::LabelSet>) + Send + Sync + 'static,
+    {
+        self.0
+            .register_f64_value_observer_boxed(name.into(), opts, Box::new(callback))
+    }
+
+    /// Registers a new `f64` sum observer with a given name and customized with passed options.
+    fn register_f64_sum_observer<S, F>(&self, name: S, opts: api::MetricOptions, callback: F)
+    where
+        S: Into<String>,
+        F: Fn(api::ObserverResult<'_, f64, Self::LabelSet>) + Send + Sync + 'static,
+    {
+        self.0
+            .register_f64_sum_observer_boxed(name.into(), opts, Box::new(callback))
+    }
+
+    /// Registers a new `f64` up-down sum observer with a given name and customized with passed options.
+    fn register_f64_up_down_sum_observer<S, F>(
+        &self,
+        name: S,
+        opts: api::MetricOptions,
+        callback: F,
+    ) where
+        S: Into<String>,
+        F: Fn(api::ObserverResult<'_, f64, Self::LabelSet>) + Send + Sync + 'static,
+    {
+        self.0
+            .register_f64_up_down_sum_observer_boxed(name.into(), opts, Box::new(callback))
+    }
+
+    /// Records a batch of measurements.
+    fn record_batch<M: IntoIterator<Item = api::Measurement<Self::LabelSet>>>(
+        &self,
+        label_set: &Self::LabelSet,
+        measurements: M,
+    ) {
+        for measure in measurements.into_iter() {
+            let instrument = measure.instrument();
+            instrument.record_one(measure.into_value(), label_set);
+        }
+    }
+}
+
+/// Allows a specific [`MeterProvider`] to be used generically by the
+/// [`GlobalMeterProvider`] by mirroring the interface and boxing the return
+/// types.
+///
+/// [`MeterProvider`]: ../api/metrics/provider/trait.MeterProvider.html
+/// [`GlobalMeterProvider`]: struct.GlobalMeterProvider.html
+pub trait GenericMeterProvider: fmt::Debug + 'static {
+    /// Creates a named meter instance that is a trait object through the
+    /// underlying `MeterProvider`.
+    fn get_meter_boxed(&self, name: &'static str) -> Box<dyn GenericMeter + Send + Sync>;
+}
+
+impl<M, P> GenericMeterProvider for P
+where
+    M: GenericMeter + Send + Sync,
+    P: api::MeterProvider<Meter = M>,
+{
+    /// Return a boxed generic meter
+    fn get_meter_boxed(&self, name: &'static str) -> Box<dyn GenericMeter + Send + Sync> {
+        Box::new(self.get_meter(name))
+    }
+}
+
+/// Represents the globally configured [`MeterProvider`] instance for this
+/// application. This allows generic metrics recording through the returned
+/// [`BoxedMeter`] instances.
+///
+/// [`MeterProvider`]: ../api/metrics/provider/trait.MeterProvider.html
+/// [`BoxedMeter`]: struct.BoxedMeter.html
+#[derive(Clone, Debug)]
+pub struct GlobalMeterProvider {
+    provider: Arc<dyn GenericMeterProvider + Send + Sync>,
+}
+
+impl GlobalMeterProvider {
+    /// Create a new GlobalMeterProvider instance from a struct that
+    /// implements `MeterProvider`.
+    fn new<P, M>(provider: P) -> Self
+    where
+        M: GenericMeter + Send + Sync,
+        P: api::MeterProvider<Meter = M> + Send + Sync,
+    {
+        GlobalMeterProvider {
+            provider: Arc::new(provider),
+        }
+    }
+}
+
+impl api::MeterProvider for GlobalMeterProvider {
+    type Meter = BoxedMeter;
+
+    /// Find or create a named meter using the global provider.
+    fn get_meter(&self, name: &'static str) -> Self::Meter {
+        BoxedMeter(self.provider.get_meter_boxed(name))
+    }
+}
+
+lazy_static::lazy_static! {
+    /// The global `MeterProvider` singleton.
+    static ref GLOBAL_METER_PROVIDER: RwLock<GlobalMeterProvider> = RwLock::new(GlobalMeterProvider::new(api::NoopMeterProvider {}));
+}
+
+/// Returns an instance of the currently configured global [`MeterProvider`]
+/// through [`GlobalMeterProvider`].
+///
+/// [`MeterProvider`]: ../api/metrics/provider/trait.MeterProvider.html
+/// [`GlobalMeterProvider`]: struct.GlobalMeterProvider.html
+pub fn meter_provider() -> GlobalMeterProvider {
+    GLOBAL_METER_PROVIDER
+        .read()
+        .expect("GLOBAL_METER_PROVIDER RwLock poisoned")
+        .clone()
+}
+
+/// Creates a named instance of [`Meter`] via the configured
+/// [`GlobalMeterProvider`].
+///
+/// If the name is an empty string, the provider will use a default name.
+///
+/// This is a more convenient way of expressing
+/// `global::meter_provider().get_meter(name)`.
+///
+/// [`Meter`]: ../api/metrics/trait.Meter.html
+/// [`GlobalMeterProvider`]: struct.GlobalMeterProvider.html
+pub fn meter(name: &'static str) -> BoxedMeter {
+    meter_provider().get_meter(name)
+}
+
+/// Sets the given [`MeterProvider`] instance as the current global meter
+/// provider.
+///
+/// [`MeterProvider`]: ../api/metrics/provider/trait.MeterProvider.html
+pub fn set_meter_provider<P, M>(new_provider: P)
+where
+    M: GenericMeter + Send + Sync,
+    P: api::MeterProvider<Meter = M> + Send + Sync,
+{
+    let mut global_provider = GLOBAL_METER_PROVIDER
+        .write()
+        .expect("GLOBAL_METER_PROVIDER RwLock poisoned");
+    *global_provider = GlobalMeterProvider::new(new_provider);
+}
+
+#[cfg(all(test, feature = "metrics"))]
+mod tests {
+    use super::*;
+    use crate::api::{Counter, CounterHandle, Measure, Meter};
+    use crate::exporter::metrics::{Aggregation, Number};
+    use crate::sdk;
+
+    #[test]
+    fn boxed_meter_records_to_underlying_meter() {
+        let sdk_provider = sdk::MeterProvider::default();
+        let meter = GlobalMeterProvider::new(sdk_provider.clone()).get_meter("test");
+        let labels = meter.labels(vec![api::KeyValue::new("method", "GET")]);
+        let counter = meter.new_i64_counter("requests", api::MetricOptions::default());
+        let measure = meter.new_f64_measure("latency", api::MetricOptions::default());
+
+        counter.add(1, &labels);
+        counter.acquire_handle(&labels).add(2);
+        meter.record_batch(
+            &labels,
+            vec![counter.measurement(3), measure.measurement(0.5)],
+        );
+        assert_eq!(
+            measure.try_record(-1.0, &labels),
+            Err(api::MetricsError::NegativeInput)
+        );
+
+        let mut records = sdk_provider.collect().records;
+        records.sort_by(|a, b| a.descriptor.name.cmp(&b.descriptor.name));
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1].aggregation, Aggregation::Sum(Number::I64(6)));
+        assert_eq!(
+            records[1].labels.get("method").map(|value| value.as_ref()),
+            Some("GET")
+        );
+    }
+
+    #[test]
+    fn boxed_observers_report_to_underlying_meter() {
+        let sdk_provider = sdk::MeterProvider::default();
+        let meter = GlobalMeterProvider::new(sdk_provider.clone()).get_meter("test");
+        let labels = meter.labels(vec![]);
+        meter.register_f64_value_observer(
+            "temperature",
+            api::MetricOptions::default(),
+            move |result| result.observe(21.5, &labels),
+        );
+
+        let records = sdk_provider.collect().records;
+        assert_eq!(records.len(), 1);
+        match records[0].aggregation {
+            Aggregation::LastValue { value, .. } => assert_eq!(value, Number::F64(21.5)),
+            ref other => panic!("unexpected aggregation {:?}", other),
+        }
+    }
+
+    #[test]
+    fn rejects_label_sets_of_other_meters() {
+        let noop_meter = GlobalMeterProvider::new(api::NoopMeterProvider {}).get_meter("noop");
+        let meter = GlobalMeterProvider::new(sdk::MeterProvider::default()).get_meter("test");
+        let counter = meter.new_i64_counter("requests", api::MetricOptions::default());
+        let noop_labels = noop_meter.labels(vec![]);
+
+        assert_eq!(
+            counter.try_add(1, &noop_labels),
+            Err(api::MetricsError::IncompatibleLabelSet)
+        );
+        assert_eq!(
+            counter.acquire_handle(&noop_labels).try_add(1),
+            Err(api::MetricsError::IncompatibleLabelSet)
+        );
+    }
+}
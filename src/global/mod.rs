@@ -37,6 +37,26 @@
 //! do_something_tracked();
 //! ```
 //!
+//! ## Metrics
+//!
+//! Similarly, the configured [`MeterProvider`] is accessed anywhere via
+//! [`meter_provider`] and [`meter`], which return type-erased [`BoxedMeter`]
+//! instances so libraries can record metrics without knowing the SDK type.
+//!
+//! ```rust
+//! use opentelemetry::api::{Counter, Meter, MetricOptions};
+//! use opentelemetry::{global, sdk};
+//!
+//! // Configure the global `MeterProvider` singleton when your app starts
+//! // (there is a no-op default if this is not set by your application)
+//! global::set_meter_provider(sdk::MeterProvider::default());
+//!
+//! // Then libraries can use the global provider to create instruments
+//! let meter = global::meter("my-component");
+//! let counter = meter.new_i64_counter("requests", MetricOptions::default());
+//! counter.add(1, &meter.labels(vec![]));
+//! ```
+//!
 //! ## Implementation
 //!
 //! This module provides types for working with the Open Telemetry API in an
@@ -65,9 +85,21 @@
 //! [`BoxedTracer`]: struct.BoxedTracer.html
 //! [`BoxedSpan`]: struct.BoxedSpan.html
 //! [`trace_provider`]: fn.trace_provider.html
+//! [`MeterProvider`]: ../api/metrics/provider/trait.MeterProvider.html
+//! [`meter_provider`]: fn.meter_provider.html
+//! [`meter`]: fn.meter.html
+//! [`BoxedMeter`]: struct.BoxedMeter.html
 //! [trait objects]: https://doc.rust-lang.org/reference/types/trait-object.html#trait-objects
 use crate::{api, api::Provider};
 use std::fmt;
+
+mod metrics;
+
+pub use metrics::{
+    meter, meter_provider, set_meter_provider, BoxedCounter, BoxedGauge, BoxedHandle,
+    BoxedLabelSet, BoxedMeasure, BoxedMeter, GenericInstrument, GenericMeter, GenericMeterProvider,
+    GlobalMeterProvider,
+};
 use std::sync::{Arc, RwLock};
 use std::time::SystemTime;
 
@@ -378,9 +410,10 @@ where
         .unwrap_or_else(|_| f(&*DEFAULT_HTTP_TEXT_PROPAGATOR as &dyn api::HttpTextFormat))
 }
 
-/// Returns [`NoopMeter`] for now
+/// Returns [`NoopMeter`]
 ///
-/// [`NoopMeter`]: ../api/trace/noop/struct.NoopMeter.html
+/// [`NoopMeter`]: ../api/metrics/noop/struct.NoopMeter.html
+#[deprecated(note = "use `global::meter` to access the configured meter provider")]
 pub fn global_meter() -> crate::api::NoopMeter {
     crate::api::NoopMeter {}
 }
@@ -0,0 +1,647 @@
+//! # Metric Aggregators
+//!
+//! Aggregators combine the measurements recorded by an instrument for a
+//! single label set, and produce an [`Aggregation`] of them every time the
+//! `Meter` is collected. The aggregator used for each instrument is chosen by
+//! the `Meter`'s [`AggregatorSelector`].
+//!
+//! [`Aggregation`]: ../../../exporter/metrics/enum.Aggregation.html
+//! [`AggregatorSelector`]: trait.AggregatorSelector.html
+use crate::api::{self, MetricsError};
+use crate::exporter::metrics::{
+    Aggregation, Descriptor, Exemplar, ExportKind, InstrumentKind, Number, NumberKind,
+};
+use crate::sdk::metrics::sketch::DDSketch;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+/// Aggregates the measurements of an instrument for a single label set.
+pub trait Aggregator: Send + Sync + fmt::Debug {
+    /// Record a new measurement, or return an error if the aggregator rejects
+    /// it.
+    fn update(&self, number: Number) -> api::metrics::Result<()>;
+
+    /// Record a new measurement taken while a sampled span was active.
+    /// Aggregators that do not keep exemplars only record the measurement.
+    fn update_with_exemplar(&self, number: Number, exemplar: Exemplar) -> api::metrics::Result<()> {
+        let _ = exemplar;
+        self.update(number)
+    }
+
+    /// Take a checkpoint of the aggregated measurements. Returns `None` if
+    /// there is nothing to export.
+    ///
+    /// With `ExportKind::Delta` the aggregator is reset, and `None` is
+    /// returned if nothing was recorded since the previous checkpoint.
+    fn checkpoint(&self, export_kind: ExportKind) -> Option<Aggregation>;
+}
+
+/// Chooses the aggregator used for each instrument.
+pub trait AggregatorSelector: Send + Sync + fmt::Debug {
+    /// Create a new aggregator for the described instrument.
+    fn aggregator_for(&self, descriptor: &Descriptor) -> Arc<dyn Aggregator>;
+}
+
+/// The built in aggregator selection strategies.
+#[derive(Clone, Debug, Default)]
+pub enum Selector {
+    /// Counters are summed, gauges and value observers keep their last
+    /// value, sum observers export their last observed sum, and measures are
+    /// summarized by their min, max, sum and count.
+    #[default]
+    Simple,
+    /// As `Simple`, but measures are aggregated into a histogram with the
+    /// given bucket boundaries.
+    Histogram(Vec<f64>),
+    /// As `Simple`, but measures are aggregated into a quantile sketch with
+    /// the given relative accuracy.
+    Sketch(f64),
+}
+
+impl AggregatorSelector for Selector {
+    fn aggregator_for(&self, descriptor: &Descriptor) -> Arc<dyn Aggregator> {
+        let kind = descriptor.number_kind;
+        // The aggregation requested by a measure overrides the selector
+        match &descriptor.aggregation {
+            Some(api::MeasureAggregation::Histogram(boundaries)) => {
+                return Arc::new(HistogramAggregator::new(kind, boundaries.clone()))
+            }
+            Some(api::MeasureAggregation::Sketch(relative_accuracy)) => {
+                return Arc::new(SketchAggregator::new(*relative_accuracy))
+            }
+            None => (),
+        }
+        match (descriptor.instrument_kind, self) {
+            (InstrumentKind::Counter, _) => Arc::new(SumAggregator::new(kind)),
+            (InstrumentKind::Gauge, _) | (InstrumentKind::ValueObserver, _) => {
+                Arc::new(LastValueAggregator::new())
+            }
+            (InstrumentKind::SumObserver, _) | (InstrumentKind::UpDownSumObserver, _) => {
+                Arc::new(ObservedSumAggregator::new(kind))
+            }
+            (InstrumentKind::Measure, Selector::Simple) => {
+                Arc::new(MinMaxSumCountAggregator::new(kind))
+            }
+            (InstrumentKind::Measure, Selector::Histogram(boundaries)) => {
+                Arc::new(HistogramAggregator::new(kind, boundaries.clone()))
+            }
+            (InstrumentKind::Measure, Selector::Sketch(relative_accuracy)) => {
+                Arc::new(SketchAggregator::new(*relative_accuracy))
+            }
+        }
+    }
+}
+
+/// Check that a measurement is valid for the described instrument: `NaN`
+/// values are always rejected, and negative values are rejected by absolute
+/// instruments.
+pub fn range_test(number: Number, descriptor: &Descriptor) -> api::metrics::Result<()> {
+    if number.is_nan() {
+        Err(MetricsError::NaNInput)
+    } else if descriptor.absolute && number.is_negative() {
+        Err(MetricsError::NegativeInput)
+    } else {
+        Ok(())
+    }
+}
+
+/// Check that a measurement of the described instrument does not decrease
+/// the value last recorded with the same labels, `last`: monotonic gauges
+/// reject lower values, whatever the aggregator of the instrument.
+pub fn monotonic_test(
+    number: Number,
+    last: Option<Number>,
+    descriptor: &Descriptor,
+) -> api::metrics::Result<()> {
+    match last {
+        Some(last)
+            if descriptor.instrument_kind == InstrumentKind::Gauge
+                && descriptor.monotonic
+                && number < last =>
+        {
+            Err(MetricsError::NonMonotoneInput)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Sums all recorded values.
+#[derive(Debug)]
+pub struct SumAggregator {
+    kind: NumberKind,
+    state: Mutex<SumState>,
+}
+
+#[derive(Debug)]
+struct SumState {
+    sum: Number,
+    updated: bool,
+}
+
+impl SumAggregator {
+    /// Create a new sum aggregator for the given kind of number.
+    pub fn new(kind: NumberKind) -> Self {
+        SumAggregator {
+            kind,
+            state: Mutex::new(SumState {
+                sum: kind.zero(),
+                updated: false,
+            }),
+        }
+    }
+}
+
+impl Aggregator for SumAggregator {
+    fn update(&self, number: Number) -> api::metrics::Result<()> {
+        if let Ok(mut state) = self.state.lock() {
+            state.sum = state.sum + number;
+            state.updated = true;
+        }
+
+        Ok(())
+    }
+
+    fn checkpoint(&self, export_kind: ExportKind) -> Option<Aggregation> {
+        let mut state = self.state.lock().ok()?;
+        let sum = state.sum;
+        if export_kind == ExportKind::Delta {
+            if !state.updated {
+                return None;
+            }
+            state.sum = self.kind.zero();
+        }
+        state.updated = false;
+
+        Some(Aggregation::Sum(sum))
+    }
+}
+
+/// Keeps the most recently recorded value.
+#[derive(Debug, Default)]
+pub struct LastValueAggregator {
+    monotonic: bool,
+    state: Mutex<LastValueState>,
+}
+
+#[derive(Debug, Default)]
+struct LastValueState {
+    last: Option<(Number, SystemTime)>,
+    updated: bool,
+}
+
+impl LastValueAggregator {
+    /// Create a new last value aggregator.
+    pub fn new() -> Self {
+        LastValueAggregator::default()
+    }
+
+    /// Create a new last value aggregator rejecting values lower than the
+    /// current value.
+    pub fn monotonic() -> Self {
+        LastValueAggregator {
+            monotonic: true,
+            ..Default::default()
+        }
+    }
+}
+
+impl Aggregator for LastValueAggregator {
+    fn update(&self, number: Number) -> api::metrics::Result<()> {
+        if let Ok(mut state) = self.state.lock() {
+            if let Some((last, _)) = state.last {
+                if self.monotonic && number < last {
+                    return Err(MetricsError::NonMonotoneInput);
+                }
+            }
+            state.last = Some((number, SystemTime::now()));
+            state.updated = true;
+        }
+
+        Ok(())
+    }
+
+    fn checkpoint(&self, export_kind: ExportKind) -> Option<Aggregation> {
+        let mut state = self.state.lock().ok()?;
+        let updated = std::mem::replace(&mut state.updated, false);
+        if export_kind == ExportKind::Delta && !updated {
+            return None;
+        }
+
+        state
+            .last
+            .map(|(value, timestamp)| Aggregation::LastValue { value, timestamp })
+    }
+}
+
+/// Exports the most recently observed value of a sum observer as a sum.
+///
+/// Observed values are totals, so delta checkpoints export the difference
+/// with the value observed at the previous checkpoint.
+#[derive(Debug)]
+pub struct ObservedSumAggregator {
+    state: Mutex<ObservedSumState>,
+}
+
+#[derive(Debug)]
+struct ObservedSumState {
+    sum: Number,
+    checkpointed: Number,
+    updated: bool,
+}
+
+impl ObservedSumAggregator {
+    /// Create a new observed sum aggregator for the given kind of number.
+    pub fn new(kind: NumberKind) -> Self {
+        ObservedSumAggregator {
+            state: Mutex::new(ObservedSumState {
+                sum: kind.zero(),
+                checkpointed: kind.zero(),
+                updated: false,
+            }),
+        }
+    }
+}
+
+impl Aggregator for ObservedSumAggregator {
+    fn update(&self, number: Number) -> api::metrics::Result<()> {
+        if let Ok(mut state) = self.state.lock() {
+            state.sum = number;
+            state.updated = true;
+        }
+
+        Ok(())
+    }
+
+    fn checkpoint(&self, export_kind: ExportKind) -> Option<Aggregation> {
+        let mut state = self.state.lock().ok()?;
+        let updated = std::mem::replace(&mut state.updated, false);
+        match export_kind {
+            ExportKind::Cumulative => Some(Aggregation::Sum(state.sum)),
+            ExportKind::Delta if updated => {
+                let delta = state.sum - state.checkpointed;
+                state.checkpointed = state.sum;
+                Some(Aggregation::Sum(delta))
+            }
+            ExportKind::Delta => None,
+        }
+    }
+}
+
+/// Summarizes recorded values by their minimum, maximum, sum and count.
+#[derive(Debug)]
+pub struct MinMaxSumCountAggregator {
+    kind: NumberKind,
+    state: Mutex<MinMaxSumCountState>,
+}
+
+#[derive(Debug)]
+struct MinMaxSumCountState {
+    min: Number,
+    max: Number,
+    sum: Number,
+    count: u64,
+}
+
+impl MinMaxSumCountState {
+    fn new(kind: NumberKind) -> Self {
+        MinMaxSumCountState {
+            min: kind.zero(),
+            max: kind.zero(),
+            sum: kind.zero(),
+            count: 0,
+        }
+    }
+}
+
+impl MinMaxSumCountAggregator {
+    /// Create a new min, max, sum and count aggregator for the given kind of
+    /// number.
+    pub fn new(kind: NumberKind) -> Self {
+        MinMaxSumCountAggregator {
+            kind,
+            state: Mutex::new(MinMaxSumCountState::new(kind)),
+        }
+    }
+}
+
+impl Aggregator for MinMaxSumCountAggregator {
+    fn update(&self, number: Number) -> api::metrics::Result<()> {
+        if let Ok(mut state) = self.state.lock() {
+            if state.count == 0 {
+                state.min = number;
+                state.max = number;
+            } else {
+                state.min = state.min.min(number);
+                state.max = state.max.max(number);
+            }
+            state.sum = state.sum + number;
+            state.count += 1;
+        }
+
+        Ok(())
+    }
+
+    fn checkpoint(&self, export_kind: ExportKind) -> Option<Aggregation> {
+        let mut state = self.state.lock().ok()?;
+        if state.count == 0 {
+            return None;
+        }
+        let aggregation = Aggregation::MinMaxSumCount {
+            min: state.min,
+            max: state.max,
+            sum: state.sum,
+            count: state.count,
+        };
+        if export_kind == ExportKind::Delta {
+            *state = MinMaxSumCountState::new(self.kind);
+        }
+
+        Some(aggregation)
+    }
+}
+
+/// Counts recorded values in explicit buckets, keeping the most recent
+/// exemplar of each bucket.
+#[derive(Debug)]
+pub struct HistogramAggregator {
+    kind: NumberKind,
+    boundaries: Vec<f64>,
+    state: Mutex<HistogramState>,
+}
+
+#[derive(Debug)]
+struct HistogramState {
+    counts: Vec<u64>,
+    exemplars: Vec<Option<Exemplar>>,
+    sum: Number,
+    count: u64,
+}
+
+impl HistogramState {
+    fn new(kind: NumberKind, buckets: usize) -> Self {
+        HistogramState {
+            counts: vec![0; buckets],
+            exemplars: vec![None; buckets],
+            sum: kind.zero(),
+            count: 0,
+        }
+    }
+}
+
+impl HistogramAggregator {
+    /// Create a new histogram aggregator with the given bucket boundaries.
+    /// Each boundary is the inclusive upper bound of a bucket, values larger
+    /// than the last boundary are counted in an additional overflow bucket.
+    pub fn new(kind: NumberKind, mut boundaries: Vec<f64>) -> Self {
+        boundaries.retain(|boundary| !boundary.is_nan());
+        boundaries.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        boundaries.dedup();
+        let buckets = boundaries.len() + 1;
+
+        HistogramAggregator {
+            kind,
+            boundaries,
+            state: Mutex::new(HistogramState::new(kind, buckets)),
+        }
+    }
+
+    fn record(&self, number: Number, exemplar: Option<Exemplar>) {
+        let value = number.to_f64();
+        let bucket = self
+            .boundaries
+            .iter()
+            .position(|boundary| value <= *boundary)
+            .unwrap_or(self.boundaries.len());
+
+        if let Ok(mut state) = self.state.lock() {
+            state.counts[bucket] += 1;
+            state.sum = state.sum + number;
+            state.count += 1;
+            if exemplar.is_some() {
+                state.exemplars[bucket] = exemplar;
+            }
+        }
+    }
+}
+
+impl Aggregator for HistogramAggregator {
+    fn update(&self, number: Number) -> api::metrics::Result<()> {
+        self.record(number, None);
+        Ok(())
+    }
+
+    fn update_with_exemplar(&self, number: Number, exemplar: Exemplar) -> api::metrics::Result<()> {
+        self.record(number, Some(exemplar));
+        Ok(())
+    }
+
+    fn checkpoint(&self, export_kind: ExportKind) -> Option<Aggregation> {
+        let mut state = self.state.lock().ok()?;
+        if state.count == 0 {
+            return None;
+        }
+        let aggregation = Aggregation::Histogram {
+            boundaries: self.boundaries.clone(),
+            counts: state.counts.clone(),
+            exemplars: state.exemplars.clone(),
+            sum: state.sum,
+            count: state.count,
+        };
+        if export_kind == ExportKind::Delta {
+            *state = HistogramState::new(self.kind, self.boundaries.len() + 1);
+        }
+
+        Some(aggregation)
+    }
+}
+
+/// Estimates quantiles of the recorded values with a [`DDSketch`].
+///
+/// [`DDSketch`]: ../sketch/struct.DDSketch.html
+#[derive(Debug)]
+pub struct SketchAggregator {
+    sketch: Mutex<DDSketch>,
+}
+
+impl SketchAggregator {
+    /// Create a new sketch aggregator with the given relative accuracy.
+    pub fn new(relative_accuracy: f64) -> Self {
+        SketchAggregator {
+            sketch: Mutex::new(DDSketch::new(relative_accuracy)),
+        }
+    }
+}
+
+impl Aggregator for SketchAggregator {
+    fn update(&self, number: Number) -> api::metrics::Result<()> {
+        if let Ok(mut sketch) = self.sketch.lock() {
+            sketch.add(number.to_f64());
+        }
+
+        Ok(())
+    }
+
+    fn checkpoint(&self, export_kind: ExportKind) -> Option<Aggregation> {
+        let mut sketch = self.sketch.lock().ok()?;
+        if sketch.count() == 0 {
+            return None;
+        }
+        let aggregation = Aggregation::Sketch(sketch.clone());
+        if export_kind == ExportKind::Delta {
+            *sketch = DDSketch::new(sketch.relative_accuracy());
+        }
+
+        Some(aggregation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sum_checkpoints() {
+        let sum = SumAggregator::new(NumberKind::I64);
+        sum.update(Number::I64(2)).unwrap();
+        sum.update(Number::I64(3)).unwrap();
+        assert_eq!(
+            sum.checkpoint(ExportKind::Cumulative),
+            Some(Aggregation::Sum(Number::I64(5)))
+        );
+
+        sum.update(Number::I64(1)).unwrap();
+        assert_eq!(
+            sum.checkpoint(ExportKind::Delta),
+            Some(Aggregation::Sum(Number::I64(6)))
+        );
+        assert_eq!(sum.checkpoint(ExportKind::Delta), None);
+        assert_eq!(
+            sum.checkpoint(ExportKind::Cumulative),
+            Some(Aggregation::Sum(Number::I64(0)))
+        );
+    }
+
+    #[test]
+    fn last_value_checkpoints() {
+        let last_value = LastValueAggregator::new();
+        assert_eq!(last_value.checkpoint(ExportKind::Cumulative), None);
+
+        last_value.update(Number::F64(1.5)).unwrap();
+        last_value.update(Number::F64(-2.5)).unwrap();
+        match last_value.checkpoint(ExportKind::Delta) {
+            Some(Aggregation::LastValue { value, .. }) => assert_eq!(value, Number::F64(-2.5)),
+            other => panic!("unexpected aggregation {:?}", other),
+        }
+        assert_eq!(last_value.checkpoint(ExportKind::Delta), None);
+        assert!(last_value.checkpoint(ExportKind::Cumulative).is_some());
+    }
+
+    #[test]
+    fn monotonic_last_value_rejects_lower_values() {
+        let last_value = LastValueAggregator::monotonic();
+        last_value.update(Number::I64(5)).unwrap();
+        last_value.update(Number::I64(5)).unwrap();
+        assert_eq!(
+            last_value.update(Number::I64(4)),
+            Err(MetricsError::NonMonotoneInput)
+        );
+        match last_value.checkpoint(ExportKind::Delta) {
+            Some(Aggregation::LastValue { value, .. }) => assert_eq!(value, Number::I64(5)),
+            other => panic!("unexpected aggregation {:?}", other),
+        }
+        // The previous value is kept across delta checkpoints
+        assert!(last_value.update(Number::I64(3)).is_err());
+    }
+
+    #[test]
+    fn monotonic_last_value_compares_large_integers_exactly() {
+        // Both values round to the same f64
+        let last_value = LastValueAggregator::monotonic();
+        last_value.update(Number::I64(i64::MAX)).unwrap();
+        assert_eq!(
+            last_value.update(Number::I64(i64::MAX - 1)),
+            Err(MetricsError::NonMonotoneInput)
+        );
+    }
+
+    #[test]
+    fn observed_sum_checkpoints() {
+        let observed = ObservedSumAggregator::new(NumberKind::I64);
+        observed.update(Number::I64(10)).unwrap();
+        observed.update(Number::I64(12)).unwrap();
+        assert_eq!(
+            observed.checkpoint(ExportKind::Delta),
+            Some(Aggregation::Sum(Number::I64(12)))
+        );
+        assert_eq!(observed.checkpoint(ExportKind::Delta), None);
+
+        observed.update(Number::I64(15)).unwrap();
+        assert_eq!(
+            observed.checkpoint(ExportKind::Delta),
+            Some(Aggregation::Sum(Number::I64(3)))
+        );
+        assert_eq!(
+            observed.checkpoint(ExportKind::Cumulative),
+            Some(Aggregation::Sum(Number::I64(15)))
+        );
+    }
+
+    #[test]
+    fn min_max_sum_count_checkpoints() {
+        let mmsc = MinMaxSumCountAggregator::new(NumberKind::F64);
+        for value in &[3.0, -1.0, 7.5] {
+            mmsc.update(Number::F64(*value)).unwrap();
+        }
+        let expected = Aggregation::MinMaxSumCount {
+            min: Number::F64(-1.0),
+            max: Number::F64(7.5),
+            sum: Number::F64(9.5),
+            count: 3,
+        };
+        assert_eq!(
+            mmsc.checkpoint(ExportKind::Cumulative),
+            Some(expected.clone())
+        );
+        assert_eq!(mmsc.checkpoint(ExportKind::Delta), Some(expected));
+        assert_eq!(mmsc.checkpoint(ExportKind::Delta), None);
+    }
+
+    #[test]
+    fn histogram_checkpoints() {
+        let histogram = HistogramAggregator::new(NumberKind::I64, vec![10.0, 1.0, 5.0]);
+        for value in &[0, 1, 2, 5, 6, 10, 11, 100] {
+            histogram.update(Number::I64(*value)).unwrap();
+        }
+        assert_eq!(
+            histogram.checkpoint(ExportKind::Delta),
+            Some(Aggregation::Histogram {
+                boundaries: vec![1.0, 5.0, 10.0],
+                counts: vec![2, 2, 2, 2],
+                exemplars: vec![None; 4],
+                sum: Number::I64(135),
+                count: 8,
+            })
+        );
+        assert_eq!(histogram.checkpoint(ExportKind::Cumulative), None);
+    }
+
+    #[test]
+    fn sketch_checkpoints() {
+        let sketch = SketchAggregator::new(0.01);
+        for value in 1..=100 {
+            sketch.update(Number::I64(value)).unwrap();
+        }
+        let aggregation = sketch.checkpoint(ExportKind::Delta);
+        match &aggregation {
+            Some(Aggregation::Sketch(sketch)) => {
+                assert_eq!(sketch.count(), 100);
+                assert_eq!(sketch.sum(), 5050.0);
+                let median = sketch.quantile(0.5).unwrap();
+                assert!((median - 50.0).abs() <= 0.5, "median {}", median);
+            }
+            other => panic!("unexpected aggregation {:?}", other),
+        }
+        assert_eq!(sketch.checkpoint(ExportKind::Delta), None);
+    }
+}
@@ -0,0 +1,168 @@
+//! # Push Controller
+//!
+//! The [`PushController`] periodically collects a `Meter`, or all meters of a
+//! `MeterProvider`, and pushes the checkpoint to a [`MetricExporter`] on a
+//! background thread.
+//!
+//! ```
+//! use opentelemetry::exporter::metrics::stdout;
+//! use opentelemetry::sdk;
+//! use std::time::Duration;
+//!
+//! let meter = sdk::Meter::new("example");
+//! let exporter = stdout::Builder::default().init();
+//! let mut controller = sdk::PushController::builder(meter.clone(), exporter)
+//!     .with_period(Duration::from_secs(10))
+//!     .build();
+//!
+//! // Record metrics with `meter`, then export the final values on exit
+//! controller.shutdown();
+//! ```
+//!
+//! [`PushController`]: struct.PushController.html
+//! [`MetricExporter`]: ../../../exporter/metrics/trait.MetricExporter.html
+use crate::exporter::metrics::MetricExporter;
+use crate::sdk::metrics::Checkpointer;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+/// Default interval between two exports.
+const DEFAULT_PERIOD: Duration = Duration::from_secs(10);
+
+/// Periodically exports the metrics of a `Meter` or `MeterProvider` until
+/// shut down or dropped.
+#[derive(Debug)]
+pub struct PushController {
+    shutdown_sender: mpsc::Sender<()>,
+    worker: Option<thread::JoinHandle<()>>,
+}
+
+impl PushController {
+    /// Create a new push controller builder, exporting the checkpoints
+    /// collected from `source`.
+    pub fn builder<C, E>(source: C, exporter: E) -> PushControllerBuilder
+    where
+        C: Checkpointer + 'static,
+        E: MetricExporter + 'static,
+    {
+        PushControllerBuilder {
+            source: Box::new(source),
+            exporter: Box::new(exporter),
+            period: DEFAULT_PERIOD,
+        }
+    }
+
+    /// Stop the controller, exporting the current metrics one last time and
+    /// shutting down the exporter.
+    pub fn shutdown(&mut self) {
+        if let Some(worker) = self.worker.take() {
+            let _ = self.shutdown_sender.send(());
+            let _ = worker.join();
+        }
+    }
+}
+
+impl Drop for PushController {
+    fn drop(&mut self) {
+        self.shutdown()
+    }
+}
+
+/// Builder for configuring a `PushController`.
+#[derive(Debug)]
+pub struct PushControllerBuilder {
+    source: Box<dyn Checkpointer>,
+    exporter: Box<dyn MetricExporter>,
+    period: Duration,
+}
+
+impl PushControllerBuilder {
+    /// Assign the interval between two exports. Defaults to 10 seconds.
+    pub fn with_period(self, period: Duration) -> Self {
+        PushControllerBuilder { period, ..self }
+    }
+
+    /// Start exporting on a background thread.
+    pub fn build(self) -> PushController {
+        let (shutdown_sender, shutdown_receiver) = mpsc::channel();
+        let PushControllerBuilder {
+            source,
+            exporter,
+            period,
+        } = self;
+
+        let worker = thread::Builder::new()
+            .name("opentelemetry-metrics".to_string())
+            .spawn(move || loop {
+                match shutdown_receiver.recv_timeout(period) {
+                    Err(mpsc::RecvTimeoutError::Timeout) => {
+                        exporter.export(&source.collect());
+                    }
+                    // Shut down requested, or the controller is gone
+                    _ => {
+                        exporter.export(&source.collect());
+                        exporter.shutdown();
+                        break;
+                    }
+                }
+            })
+            .ok();
+
+        PushController {
+            shutdown_sender,
+            worker,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{self, Counter, Meter};
+    use crate::exporter::metrics::{Aggregation, CheckpointSet, ExportResult, Number};
+    use crate::sdk;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Debug, Default)]
+    struct TestExporter {
+        exports: Arc<Mutex<Vec<CheckpointSet>>>,
+        shutdown: Arc<Mutex<bool>>,
+    }
+
+    impl MetricExporter for TestExporter {
+        fn export(&self, checkpoint_set: &CheckpointSet) -> ExportResult {
+            self.exports.lock().unwrap().push(checkpoint_set.clone());
+            ExportResult::Success
+        }
+
+        fn shutdown(&self) {
+            *self.shutdown.lock().unwrap() = true;
+        }
+    }
+
+    #[test]
+    fn exports_periodically_and_on_shutdown() {
+        let meter = sdk::Meter::new("test");
+        let exporter = TestExporter::default();
+        let exports = exporter.exports.clone();
+        let shutdown = exporter.shutdown.clone();
+        let mut controller = PushController::builder(meter.clone(), exporter)
+            .with_period(Duration::from_millis(10))
+            .build();
+
+        let counter = meter.new_i64_counter("requests", api::MetricOptions::default());
+        counter.add(3, &meter.labels(vec![]));
+        thread::sleep(Duration::from_millis(50));
+        assert!(!exports.lock().unwrap().is_empty());
+
+        counter.add(4, &meter.labels(vec![]));
+        controller.shutdown();
+        assert!(*shutdown.lock().unwrap());
+        let exports = exports.lock().unwrap();
+        assert_eq!(
+            exports.last().unwrap().records[0].aggregation,
+            Aggregation::Sum(Number::I64(7))
+        );
+    }
+}
@@ -0,0 +1,1488 @@
+//! # Meter
+//!
+//! The SDK `Meter` creates instruments that feed recorded measurements into
+//! per label set [`Aggregator`]s. Collecting the meter checkpoints every
+//! aggregator into a [`CheckpointSet`] for export. The instruments can be
+//! renamed, filtered or dropped by the [`View`]s the meter is built with.
+//!
+//! [`Aggregator`]: ../aggregators/trait.Aggregator.html
+//! [`View`]: ../view/struct.View.html
+//! [`CheckpointSet`]: ../../../exporter/metrics/struct.CheckpointSet.html
+use crate::api::TraceContextExt;
+use crate::exporter::metrics::{
+    CheckpointSet, Descriptor, Exemplar, ExportKind, InstrumentKind, Number, NumberKind, Record,
+};
+use crate::sdk::metrics::aggregators::{
+    monotonic_test, range_test, Aggregator, AggregatorSelector, Selector,
+};
+use crate::sdk::metrics::View;
+use crate::{api, sdk};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::SystemTime;
+
+/// Default maximum number of label sets aggregated by an instrument.
+pub const DEFAULT_CARDINALITY_LIMIT: usize = 2000;
+
+/// The label recording the measurements of new label sets once an instrument
+/// reached its cardinality limit, with the value `"true"`.
+pub const OVERFLOW_LABEL_KEY: &str = "otel.metric.overflow";
+
+/// The sum observer registered by every meter reporting its
+/// `dropped_label_sets`, once a label set was dropped.
+pub const DROPPED_LABEL_SETS_METRIC: &str = "otel.metric.dropped_label_sets";
+
+/// `Meter` implementation to create and manage metric instruments and record
+/// batch measurements.
+///
+/// Meters are cheap to clone, clones share their instruments.
+#[derive(Clone, Debug)]
+pub struct Meter {
+    inner: Arc<MeterInner>,
+}
+
+#[derive(Debug)]
+struct MeterInner {
+    name: &'static str,
+    selector: Arc<dyn AggregatorSelector>,
+    export_kind: ExportKind,
+    resource: Arc<sdk::Resource>,
+    views: Vec<View>,
+    cardinality_limit: usize,
+    dropped_label_sets: Arc<AtomicU64>,
+    instruments: Mutex<HashMap<String, Arc<InstrumentCore>>>,
+    observers: Mutex<Vec<Arc<Observer>>>,
+    /// Held for reading while recording a batch, and for writing while
+    /// checkpointing, so batches are never partially collected.
+    collection: RwLock<()>,
+}
+
+impl Meter {
+    /// Create a new `Meter` instance with a component name and the default
+    /// configuration.
+    pub fn new(name: &'static str) -> Self {
+        Meter::builder(name).build()
+    }
+
+    /// Create a new `Meter` builder.
+    pub fn builder(name: &'static str) -> Builder {
+        Builder {
+            name,
+            selector: Arc::new(Selector::default()),
+            export_kind: ExportKind::Cumulative,
+            resource: Arc::new(sdk::Resource::default()),
+            views: Vec::new(),
+            cardinality_limit: DEFAULT_CARDINALITY_LIMIT,
+        }
+    }
+
+    /// The name of this meter.
+    pub fn name(&self) -> &'static str {
+        self.inner.name
+    }
+
+    /// Whether collected aggregations are cumulative or deltas since the
+    /// previous collection.
+    pub fn export_kind(&self) -> ExportKind {
+        self.inner.export_kind
+    }
+
+    /// The number of label sets replaced by the overflow label set, because
+    /// their instrument reached its cardinality limit. A label set is counted
+    /// once per instrument and collection.
+    ///
+    /// The count is also collected as the `DROPPED_LABEL_SETS_METRIC` sum
+    /// observer.
+    pub fn dropped_label_sets(&self) -> u64 {
+        self.inner.dropped_label_sets.load(Ordering::Relaxed)
+    }
+
+    /// Invoke the callbacks of all observers registered with this meter, then
+    /// checkpoint the aggregations of all instruments created by this meter.
+    pub fn collect(&self) -> CheckpointSet {
+        // Callbacks are invoked without holding the lock, so they can create
+        // instruments
+        let observers = self
+            .inner
+            .observers
+            .lock()
+            .map(|observers| observers.clone())
+            .unwrap_or_default();
+        for observer in &observers {
+            observer.observe();
+        }
+
+        let _collection = self.inner.collection.write();
+        let mut records = Vec::new();
+        if let Ok(instruments) = self.inner.instruments.lock() {
+            for instrument in instruments.values() {
+                instrument.checkpoint(self.inner.export_kind, &mut records);
+            }
+        }
+
+        CheckpointSet {
+            resource: self.inner.resource.clone(),
+            timestamp: SystemTime::now(),
+            export_kind: self.inner.export_kind,
+            records,
+        }
+    }
+
+    /// Create a new instrument, or return the existing instrument with the
+    /// same name.
+    ///
+    /// The first view matching the instrument is applied to it. An instrument
+    /// whose name is already used by an instrument of a different kind is not
+    /// collected, its measurements are discarded.
+    fn instrument<S: Into<String>>(
+        &self,
+        name: S,
+        instrument_kind: InstrumentKind,
+        number_kind: NumberKind,
+        opts: api::MetricOptions,
+    ) -> Arc<InstrumentCore> {
+        let name = name.into();
+        let view = self
+            .inner
+            .views
+            .iter()
+            .find(|view| view.matches(&name, instrument_kind));
+        #[allow(deprecated)]
+        let api::MetricOptions {
+            description,
+            unit,
+            mut keys,
+            monotonic,
+            absolute,
+            mut aggregation,
+            alternate,
+        } = opts;
+        // Counters are monotonic and measures absolute unless stated
+        // otherwise, a monotonic counter only accepts non-negative increments
+        let (monotonic, absolute) = match instrument_kind {
+            InstrumentKind::Counter => {
+                let monotonic = monotonic.unwrap_or(!alternate);
+                (monotonic, monotonic)
+            }
+            InstrumentKind::Gauge => (monotonic.unwrap_or(alternate), false),
+            InstrumentKind::Measure => (false, absolute.unwrap_or(!alternate)),
+            InstrumentKind::ValueObserver | InstrumentKind::UpDownSumObserver => (false, false),
+            InstrumentKind::SumObserver => (true, true),
+        };
+        let mut selector = self.inner.selector.clone();
+        let mut label_keys = None;
+        let mut cardinality_limit = self.inner.cardinality_limit;
+        let mut dropped = false;
+        let (name, description) = match view {
+            Some(view) => {
+                if let Some(allowed) = &view.label_keys {
+                    keys.retain(|key| allowed.contains(key));
+                    label_keys = Some(allowed.clone());
+                }
+                // The view selector also replaces the requested aggregation
+                if let Some(view_selector) = &view.selector {
+                    selector = view_selector.clone();
+                    aggregation = None;
+                }
+                cardinality_limit = view.cardinality_limit.unwrap_or(cardinality_limit);
+                dropped = view.drop;
+                (
+                    view.name.clone().unwrap_or(name),
+                    view.description.clone().unwrap_or(description),
+                )
+            }
+            None => (name, description),
+        };
+        let descriptor = Descriptor {
+            name,
+            meter_name: self.inner.name,
+            instrument_kind,
+            number_kind,
+            description,
+            unit,
+            keys,
+            monotonic,
+            absolute,
+            // Only measures can choose their aggregation
+            aggregation: aggregation.filter(|_| instrument_kind == InstrumentKind::Measure),
+        };
+        let new_instrument = |descriptor| {
+            Arc::new(InstrumentCore {
+                descriptor: Arc::new(descriptor),
+                selector,
+                label_keys,
+                cardinality_limit,
+                overflow_label_set: overflow_label_set(),
+                dropped_label_sets: self.inner.dropped_label_sets.clone(),
+                dropped,
+                records: Mutex::new(Records::default()),
+            })
+        };
+        if dropped {
+            return new_instrument(descriptor);
+        }
+
+        let mut instruments = match self.inner.instruments.lock() {
+            Ok(instruments) => instruments,
+            Err(_) => return new_instrument(descriptor),
+        };
+        match instruments.get(&descriptor.name) {
+            Some(existing)
+                if existing.descriptor.instrument_kind == instrument_kind
+                    && existing.descriptor.number_kind == number_kind =>
+            {
+                existing.clone()
+            }
+            Some(_) => new_instrument(descriptor),
+            None => {
+                let instrument = new_instrument(descriptor);
+                instruments.insert(instrument.descriptor.name.clone(), instrument.clone());
+                instrument
+            }
+        }
+    }
+}
+
+impl Meter {
+    /// Create a new observer instrument, whose callback is invoked on every
+    /// collection.
+    fn register_observer<S, T, F>(
+        &self,
+        name: S,
+        instrument_kind: InstrumentKind,
+        number_kind: NumberKind,
+        opts: api::MetricOptions,
+        callback: F,
+    ) where
+        S: Into<String>,
+        T: Into<api::MeasurementValue>,
+        F: Fn(api::ObserverResult<'_, T, sdk::LabelSet>) + Send + Sync + 'static,
+    {
+        let observer = Observer {
+            core: self.instrument(name, instrument_kind, number_kind, opts),
+            callback: Box::new(move |core| {
+                // Rejected observations are discarded
+                callback(api::ObserverResult::new(&|value, label_set| {
+                    let _ = core.record(value, label_set);
+                }))
+            }),
+        };
+
+        if let Ok(mut observers) = self.inner.observers.lock() {
+            observers.push(Arc::new(observer));
+        }
+    }
+}
+
+/// Builder for configuring a `Meter`.
+#[derive(Clone, Debug)]
+pub struct Builder {
+    name: &'static str,
+    selector: Arc<dyn AggregatorSelector>,
+    export_kind: ExportKind,
+    resource: Arc<sdk::Resource>,
+    views: Vec<View>,
+    cardinality_limit: usize,
+}
+
+impl Builder {
+    /// The export kind of the meters built by this builder.
+    pub(crate) fn export_kind(&self) -> ExportKind {
+        self.export_kind
+    }
+
+    /// Assign the selector choosing the aggregator of each instrument.
+    /// Defaults to `Selector::Simple`.
+    pub fn with_aggregator_selector<T: AggregatorSelector + 'static>(self, selector: T) -> Self {
+        Builder {
+            selector: Arc::new(selector),
+            ..self
+        }
+    }
+
+    /// Assign whether collected aggregations are cumulative or deltas since
+    /// the previous collection. Defaults to `ExportKind::Cumulative`.
+    pub fn with_export_kind(self, export_kind: ExportKind) -> Self {
+        Builder {
+            export_kind,
+            ..self
+        }
+    }
+
+    /// Assign the resource exported with the collected metrics.
+    pub fn with_resource(self, resource: sdk::Resource) -> Self {
+        Builder {
+            resource: Arc::new(resource),
+            ..self
+        }
+    }
+
+    /// Add a view configuring the instruments it matches. Instruments use
+    /// the first matching view, in the order views are added.
+    pub fn with_view(mut self, view: View) -> Self {
+        self.views.push(view);
+        self
+    }
+
+    /// Assign the maximum number of label sets aggregated by each instrument.
+    /// Measurements with new label sets are then recorded with the overflow
+    /// label set instead, which is not counted in the limit. Defaults to
+    /// `DEFAULT_CARDINALITY_LIMIT`.
+    pub fn with_cardinality_limit(self, cardinality_limit: usize) -> Self {
+        Builder {
+            cardinality_limit,
+            ..self
+        }
+    }
+
+    /// Assign the name of the meter.
+    pub(crate) fn with_name(self, name: &'static str) -> Self {
+        Builder { name, ..self }
+    }
+
+    /// Create a new `Meter` from this configuration.
+    pub fn build(self) -> Meter {
+        let meter = Meter {
+            inner: Arc::new(MeterInner {
+                name: self.name,
+                selector: self.selector,
+                export_kind: self.export_kind,
+                resource: self.resource,
+                views: self.views,
+                cardinality_limit: self.cardinality_limit,
+                dropped_label_sets: Arc::new(AtomicU64::new(0)),
+                instruments: Mutex::new(HashMap::new()),
+                observers: Mutex::new(Vec::new()),
+                collection: RwLock::new(()),
+            }),
+        };
+
+        let dropped_label_sets = meter.inner.dropped_label_sets.clone();
+        let no_labels = sdk::LabelSet::default();
+        api::Meter::register_i64_sum_observer(
+            &meter,
+            DROPPED_LABEL_SETS_METRIC,
+            api::MetricOptions::default()
+                .with_description("Label sets replaced by the overflow label set"),
+            move |result| {
+                let dropped = dropped_label_sets.load(Ordering::Relaxed);
+                if dropped > 0 {
+                    result.observe(dropped as i64, &no_labels);
+                }
+            },
+        );
+
+        meter
+    }
+}
+
+impl sdk::metrics::Checkpointer for Meter {
+    fn collect(&self) -> CheckpointSet {
+        Meter::collect(self)
+    }
+
+    fn export_kind(&self) -> ExportKind {
+        Meter::export_kind(self)
+    }
+}
+
+impl api::Meter for Meter {
+    /// The label set used by this `Meter`.
+    type LabelSet = sdk::LabelSet;
+    /// This implementation of `api::Meter` produces `sdk::Counter<i64>` instances.
+    type I64Counter = Counter<i64>;
+    /// This implementation of `api::Meter` produces `sdk::Counter<f64>` instances.
+    type F64Counter = Counter<f64>;
+    /// This implementation of `api::Meter` produces `sdk::Gauge<i64>` instances.
+    type I64Gauge = Gauge<i64>;
+    /// This implementation of `api::Meter` produces `sdk::Gauge<f64>` instances.
+    type F64Gauge = Gauge<f64>;
+    /// This implementation of `api::Meter` produces `sdk::Measure<i64>` instances.
+    type I64Measure = Measure<i64>;
+    /// This implementation of `api::Meter` produces `sdk::Measure<f64>` instances.
+    type F64Measure = Measure<f64>;
+
+    /// Builds a `LabelSet` from `KeyValue`s.
+    fn labels(&self, key_values: Vec<api::KeyValue>) -> Self::LabelSet {
+        key_values
+            .into_iter()
+            .map(|api::KeyValue { key, value }| (Cow::Owned(key.into()), Cow::Owned(value.into())))
+            .collect()
+    }
+
+    /// Creates a new `i64` counter with a given name and customized with passed options.
+    fn new_i64_counter<S: Into<String>>(
+        &self,
+        name: S,
+        opts: api::MetricOptions,
+    ) -> Self::I64Counter {
+        Counter::new(self.instrument(name, InstrumentKind::Counter, NumberKind::I64, opts))
+    }
+
+    /// Creates a new `f64` counter with a given name and customized with passed options.
+    fn new_f64_counter<S: Into<String>>(
+        &self,
+        name: S,
+        opts: api::MetricOptions,
+    ) -> Self::F64Counter {
+        Counter::new(self.instrument(name, InstrumentKind::Counter, NumberKind::F64, opts))
+    }
+
+    /// Creates a new `i64` gauge with a given name and customized with passed options.
+    fn new_i64_gauge<S: Into<String>>(&self, name: S, opts: api::MetricOptions) -> Self::I64Gauge {
+        Gauge::new(self.instrument(name, InstrumentKind::Gauge, NumberKind::I64, opts))
+    }
+
+    /// Creates a new `f64` gauge with a given name and customized with passed options.
+    fn new_f64_gauge<S: Into<String>>(&self, name: S, opts: api::MetricOptions) -> Self::F64Gauge {
+        Gauge::new(self.instrument(name, InstrumentKind::Gauge, NumberKind::F64, opts))
+    }
+
+    /// Creates a new `i64` measure with a given name and customized with passed options.
+    fn new_i64_measure<S: Into<String>>(
+        &self,
+        name: S,
+        opts: api::MetricOptions,
+    ) -> Self::I64Measure {
+        Measure::new(self.instrument(name, InstrumentKind::Measure, NumberKind::I64, opts))
+    }
+
+    /// Creates a new `f64` measure with a given name and customized with passed options.
+    fn new_f64_measure<S: Into<String>>(
+        &self,
+        name: S,
+        opts: api::MetricOptions,
+    ) -> Self::F64Measure {
+        Measure::new(self.instrument(name, InstrumentKind::Measure, NumberKind::F64, opts))
+    }
+
+    /// Registers a new `i64` value observer with a given name and customized with passed options.
+    fn register_i64_value_observer<S, F>(&self, name: S, opts: api::MetricOptions, callback: F)
+    where
+        S: Into<String>,
+        F: Fn(api::ObserverResult<'_, i64, Self::LabelSet>) + Send + Sync + 'static,
+    {
+        self.register_observer(
+            name,
+            InstrumentKind::ValueObserver,
+            NumberKind::I64,
+            opts,
+            callback,
+        )
+    }
+
+    /// Registers a new `i64` sum observer with a given name and customized with passed options.
+    fn register_i64_sum_observer<S, F>(&self, name: S, opts: api::MetricOptions, callback: F)
+    where
+        S: Into<String>,
+        F: Fn(api::ObserverResult<'_, i64, Self::LabelSet>) + Send + Sync + 'static,
+    {
+        self.register_observer(
+            name,
+            InstrumentKind::SumObserver,
+            NumberKind::I64,
+            opts,
+            callback,
+        )
+    }
+
+    /// Registers a new `i64` up-down sum observer with a given name and customized with passed options.
+    fn register_i64_up_down_sum_observer<S, F>(
+        &self,
+        name: S,
+        opts: api::MetricOptions,
+        callback: F,
+    ) where
+        S: Into<String>,
+        F: Fn(api::ObserverResult<'_, i64, Self::LabelSet>) + Send + Sync + 'static,
+    {
+        self.register_observer(
+            name,
+            InstrumentKind::UpDownSumObserver,
+            NumberKind::I64,
+            opts,
+            callback,
+        )
+    }
+
+    /// Registers a new `f64` value observer with a given name and customized with passed options.
+    fn register_f64_value_observer<S, F>(&self, name: S, opts: api::MetricOptions, callback: F)
+    where
+        S: Into<String>,
+        F: Fn(api::ObserverResult<'_, f64, Self::LabelSet>) + Send + Sync + 'static,
+    {
+        self.register_observer(
+            name,
+            InstrumentKind::ValueObserver,
+            NumberKind::F64,
+            opts,
+            callback,
+        )
+    }
+
+    /// Registers a new `f64` sum observer with a given name and customized with passed options.
+    fn register_f64_sum_observer<S, F>(&self, name: S, opts: api::MetricOptions, callback: F)
+    where
+        S: Into<String>,
+        F: Fn(api::ObserverResult<'_, f64, Self::LabelSet>) + Send + Sync + 'static,
+    {
+        self.register_observer(
+            name,
+            InstrumentKind::SumObserver,
+            NumberKind::F64,
+            opts,
+            callback,
+        )
+    }
+
+    /// Registers a new `f64` up-down sum observer with a given name and customized with passed options.
+    fn register_f64_up_down_sum_observer<S, F>(
+        &self,
+        name: S,
+        opts: api::MetricOptions,
+        callback: F,
+    ) where
+        S: Into<String>,
+        F: Fn(api::ObserverResult<'_, f64, Self::LabelSet>) + Send + Sync + 'static,
+    {
+        self.register_observer(
+            name,
+            InstrumentKind::UpDownSumObserver,
+            NumberKind::F64,
+            opts,
+            callback,
+        )
+    }
+
+    /// Atomically records a batch of measurements: a collection of this meter
+    /// either includes all of them or none. Rejected values are discarded.
+    fn record_batch<M: IntoIterator<Item = api::Measurement<Self::LabelSet>>>(
+        &self,
+        label_set: &Self::LabelSet,
+        measurements: M,
+    ) {
+        // Aggregators are resolved before the batch is recorded, so the
+        // collection is only held off while the values are recorded
+        let measurements = measurements
+            .into_iter()
+            .map(|measure| {
+                let instrument = measure.instrument();
+                let handle = instrument.bind_one(label_set);
+                (instrument, handle, measure.into_value())
+            })
+            .collect::<Vec<_>>();
+
+        let _batch = self.inner.collection.read();
+        for (instrument, handle, value) in measurements {
+            match handle {
+                Some(handle) => handle.record_one(value),
+                None => instrument.record_one(value, label_set),
+            }
+        }
+    }
+}
+
+/// The state shared by all clones of an instrument: its descriptor, the
+/// configuration of its view and the aggregators of each label set it
+/// recorded.
+#[derive(Debug)]
+struct InstrumentCore {
+    descriptor: Arc<Descriptor>,
+    selector: Arc<dyn AggregatorSelector>,
+    /// The label keys kept by the view, other labels are removed
+    label_keys: Option<Vec<api::Key>>,
+    /// The maximum number of label sets, not counting the overflow label set
+    cardinality_limit: usize,
+    /// The label set of measurements exceeding the cardinality limit
+    overflow_label_set: sdk::LabelSet,
+    /// The meter's count of label sets replaced by the overflow label set
+    dropped_label_sets: Arc<AtomicU64>,
+    /// Whether the view drops the instrument
+    dropped: bool,
+    records: Mutex<Records>,
+}
+
+/// The aggregators of an instrument by label set.
+#[derive(Debug, Default)]
+struct Records {
+    aggregators: HashMap<sdk::LabelSet, Arc<Aggregated>>,
+    /// The label sets kept by the view, by recorded label set, so each label
+    /// set is only filtered once. Cleared when it holds more label sets than
+    /// the cardinality limit.
+    filtered: HashMap<sdk::LabelSet, sdk::LabelSet>,
+    /// The label sets replaced by the overflow label set since the last
+    /// checkpoint, so each is counted once per collection.
+    overflowed: HashSet<sdk::LabelSet>,
+}
+
+impl InstrumentCore {
+    /// A new aggregator chosen by the selector.
+    fn new_aggregator(&self) -> Arc<Aggregated> {
+        let last = if self.descriptor.instrument_kind == InstrumentKind::Gauge {
+            Some(Mutex::new(None))
+        } else {
+            None
+        };
+
+        Arc::new(Aggregated {
+            aggregator: self.selector.aggregator_for(&self.descriptor),
+            last,
+        })
+    }
+
+    /// The aggregator for the given label set, created on first use.
+    fn aggregator(&self, label_set: &sdk::LabelSet) -> Arc<Aggregated> {
+        // Handles of dropped instruments record into a detached aggregator
+        if self.dropped {
+            return self.new_aggregator();
+        }
+        match self.records.lock() {
+            Ok(mut records) => {
+                let records = &mut *records;
+                let label_set = match &self.label_keys {
+                    Some(keys) => match records.filtered.get(label_set) {
+                        Some(filtered) => Cow::Borrowed(filtered),
+                        None => {
+                            if records.filtered.len() >= self.cardinality_limit {
+                                records.filtered.clear();
+                            }
+                            let filtered = filter(label_set, keys);
+                            records.filtered.insert(label_set.clone(), filtered.clone());
+                            Cow::Owned(filtered)
+                        }
+                    },
+                    None => Cow::Borrowed(label_set),
+                };
+                if let Some(aggregator) = records.aggregators.get(label_set.as_ref()) {
+                    return aggregator.clone();
+                }
+                let overflow = &self.overflow_label_set;
+                let aggregated = records.aggregators.len()
+                    - usize::from(records.aggregators.contains_key(overflow));
+                let label_set = if aggregated < self.cardinality_limit {
+                    label_set.into_owned()
+                } else {
+                    if records.overflowed.insert(label_set.into_owned()) {
+                        self.dropped_label_sets.fetch_add(1, Ordering::Relaxed);
+                    }
+                    if let Some(aggregator) = records.aggregators.get(overflow) {
+                        return aggregator.clone();
+                    }
+                    overflow.clone()
+                };
+                let aggregator = self.new_aggregator();
+                records.aggregators.insert(label_set, aggregator.clone());
+                aggregator
+            }
+            // Measurements are discarded by a detached aggregator
+            Err(_) => self.new_aggregator(),
+        }
+    }
+
+    fn record(
+        &self,
+        value: api::MeasurementValue,
+        label_set: &sdk::LabelSet,
+    ) -> api::metrics::Result<()> {
+        let number = self.descriptor.number_kind.number(value);
+        range_test(number, &self.descriptor)?;
+        if self.dropped {
+            return Ok(());
+        }
+        self.aggregator(label_set).update(number, &self.descriptor)
+    }
+
+    fn bind(&self, label_set: &sdk::LabelSet) -> BoundInstrument {
+        BoundInstrument {
+            aggregator: self.aggregator(label_set),
+            descriptor: self.descriptor.clone(),
+        }
+    }
+
+    /// Append the checkpoint of every label set to `records`. With delta
+    /// exports, label sets that were not updated and are not bound to a handle
+    /// are forgotten.
+    fn checkpoint(&self, export_kind: ExportKind, records: &mut Vec<Record>) {
+        if let Ok(mut aggregators) = self.records.lock() {
+            aggregators.overflowed.clear();
+            aggregators.aggregators.retain(|labels, aggregator| {
+                match aggregator.aggregator.checkpoint(export_kind) {
+                    Some(aggregation) => {
+                        records.push(Record {
+                            descriptor: self.descriptor.clone(),
+                            labels: labels.clone(),
+                            aggregation,
+                        });
+                        true
+                    }
+                    None => {
+                        export_kind == ExportKind::Cumulative || Arc::strong_count(aggregator) > 1
+                    }
+                }
+            });
+        }
+    }
+}
+
+/// The aggregator of a label set.
+#[derive(Debug)]
+struct Aggregated {
+    aggregator: Arc<dyn Aggregator>,
+    /// The last value recorded by a gauge
+    last: Option<Mutex<Option<Number>>>,
+}
+
+impl Aggregated {
+    /// Update the aggregator with a valid measurement. The last value of a
+    /// gauge is locked until the aggregator is updated, so concurrent
+    /// measurements of a monotonic gauge cannot record a lower value.
+    fn update(&self, number: Number, descriptor: &Descriptor) -> api::metrics::Result<()> {
+        if let Some(Ok(mut last)) = self.last.as_ref().map(Mutex::lock) {
+            monotonic_test(number, *last, descriptor)?;
+            update(self.aggregator.as_ref(), number, descriptor)?;
+            *last = Some(number);
+            return Ok(());
+        }
+
+        update(self.aggregator.as_ref(), number, descriptor)
+    }
+}
+
+/// Update an aggregator with a measurement, along with an exemplar of it if
+/// it was recorded by a measure while a sampled span was active.
+fn update(
+    aggregator: &dyn Aggregator,
+    number: Number,
+    descriptor: &Descriptor,
+) -> api::metrics::Result<()> {
+    if descriptor.instrument_kind == InstrumentKind::Measure {
+        let exemplar = api::Context::map_current(|cx| {
+            let span_context = cx.span().span_context();
+            if !span_context.is_sampled() || !span_context.is_valid() {
+                return None;
+            }
+
+            Some(Exemplar {
+                value: number,
+                timestamp: SystemTime::now(),
+                trace_id: span_context.trace_id(),
+                span_id: span_context.span_id(),
+            })
+        });
+        if let Some(exemplar) = exemplar {
+            return aggregator.update_with_exemplar(number, exemplar);
+        }
+    }
+
+    aggregator.update(number)
+}
+
+/// The labels of a label set with the given keys.
+fn filter(label_set: &sdk::LabelSet, keys: &[api::Key]) -> sdk::LabelSet {
+    label_set
+        .iter()
+        .filter(|(key, _)| keys.iter().any(|allowed| allowed.as_str() == key.as_ref()))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect()
+}
+
+/// The label set replacing new label sets once an instrument reached its
+/// cardinality limit.
+fn overflow_label_set() -> sdk::LabelSet {
+    std::iter::once((Cow::Borrowed(OVERFLOW_LABEL_KEY), Cow::Borrowed("true"))).collect()
+}
+
+/// An observer instrument and the callback reporting its values.
+struct Observer {
+    core: Arc<InstrumentCore>,
+    callback: Box<dyn Fn(&InstrumentCore) + Send + Sync>,
+}
+
+impl Observer {
+    fn observe(&self) {
+        (self.callback)(&self.core)
+    }
+}
+
+impl fmt::Debug for Observer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Observer")
+            .field("descriptor", &self.core.descriptor)
+            .finish()
+    }
+}
+
+/// An aggregator bound to a label set, used by instrument handles.
+#[derive(Clone, Debug)]
+struct BoundInstrument {
+    aggregator: Arc<Aggregated>,
+    descriptor: Arc<Descriptor>,
+}
+
+impl api::InstrumentHandle for BoundInstrument {
+    fn record_one(&self, value: api::MeasurementValue) {
+        let _ = self.try_record_one(value);
+    }
+
+    fn try_record_one(&self, value: api::MeasurementValue) -> api::metrics::Result<()> {
+        let number = self.descriptor.number_kind.number(value);
+        range_test(number, &self.descriptor)?;
+        self.aggregator.update(number, &self.descriptor)
+    }
+}
+
+macro_rules! instrument {
+    ($(#[$doc:meta])* $name:ident, $handle:ident, $api_trait:ident, $api_handle:ident) => {
+        $(#[$doc])*
+        #[derive(Debug)]
+        pub struct $name<T> {
+            core: Arc<InstrumentCore>,
+            _marker: PhantomData<T>,
+        }
+
+        impl<T> Clone for $name<T> {
+            fn clone(&self) -> Self {
+                $name::new(self.core.clone())
+            }
+        }
+
+        impl<T> $name<T> {
+            fn new(core: Arc<InstrumentCore>) -> Self {
+                $name {
+                    core,
+                    _marker: PhantomData,
+                }
+            }
+        }
+
+        impl<T> api::Instrument<sdk::LabelSet> for $name<T> {
+            /// Record a single measurement value for the given label set.
+            /// Rejected values are discarded.
+            fn record_one(&self, value: api::MeasurementValue, label_set: &sdk::LabelSet) {
+                let _ = self.core.record(value, label_set);
+            }
+
+            /// Record a single measurement value for the given label set.
+            fn try_record_one(
+                &self,
+                value: api::MeasurementValue,
+                label_set: &sdk::LabelSet,
+            ) -> api::metrics::Result<()> {
+                self.core.record(value, label_set)
+            }
+
+            /// Bind the instrument to the aggregator of a label set.
+            fn bind_one(
+                &self,
+                label_set: &sdk::LabelSet,
+            ) -> Option<Box<dyn api::InstrumentHandle>> {
+                Some(Box::new(self.core.bind(label_set)))
+            }
+        }
+
+        impl<T> api::$api_trait<T, sdk::LabelSet> for $name<T>
+        where
+            T: Into<api::MeasurementValue> + Send + Sync + 'static,
+        {
+            type Handle = $handle<T>;
+
+            /// Creates a `Measurement` object to be used by a `Meter` when batch recording.
+            fn measurement(&self, value: T) -> api::Measurement<sdk::LabelSet> {
+                api::Measurement::new(Arc::new(self.clone()), value.into())
+            }
+
+            /// Creates a handle for this instrument. The handle keeps the
+            /// aggregator of the label set, so recording through it skips the
+            /// label set lookup.
+            fn acquire_handle(&self, labels: &sdk::LabelSet) -> Self::Handle {
+                $handle {
+                    bound: self.core.bind(labels),
+                    _marker: PhantomData,
+                }
+            }
+        }
+
+        /// A handle recording values for a fixed label set.
+        #[derive(Debug)]
+        pub struct $handle<T> {
+            bound: BoundInstrument,
+            _marker: PhantomData<T>,
+        }
+
+        impl<T> Clone for $handle<T> {
+            fn clone(&self) -> Self {
+                $handle {
+                    bound: self.bound.clone(),
+                    _marker: PhantomData,
+                }
+            }
+        }
+
+        impl<T> api::InstrumentHandle for $handle<T> {
+            /// Record a single measurement value for the bound label set.
+            /// Rejected values are discarded.
+            fn record_one(&self, value: api::MeasurementValue) {
+                api::InstrumentHandle::record_one(&self.bound, value)
+            }
+
+            /// Record a single measurement value for the bound label set.
+            fn try_record_one(&self, value: api::MeasurementValue) -> api::metrics::Result<()> {
+                api::InstrumentHandle::try_record_one(&self.bound, value)
+            }
+        }
+
+        impl<T: Into<api::MeasurementValue>> api::$api_handle<T> for $handle<T> {}
+    };
+}
+
+instrument!(
+    /// SDK `Counter` instrument, aggregating values with the `Meter`'s selector.
+    Counter,
+    CounterHandle,
+    Counter,
+    CounterHandle
+);
+instrument!(
+    /// SDK `Gauge` instrument, aggregating values with the `Meter`'s selector.
+    Gauge,
+    GaugeHandle,
+    Gauge,
+    GaugeHandle
+);
+instrument!(
+    /// SDK `Measure` instrument, aggregating values with the `Meter`'s selector.
+    Measure,
+    MeasureHandle,
+    Measure,
+    MeasureHandle
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{
+        Counter as _, CounterHandle as _, Gauge as _, GaugeHandle as _, Measure as _, Meter as _,
+        Provider as _, Tracer as _,
+    };
+    use crate::exporter::metrics::{Aggregation, Number};
+
+    fn aggregations(checkpoint_set: &CheckpointSet) -> Vec<(&str, &sdk::LabelSet, &Aggregation)> {
+        let mut aggregations = checkpoint_set
+            .records
+            .iter()
+            .map(|record| {
+                (
+                    record.descriptor.name.as_str(),
+                    &record.labels,
+                    &record.aggregation,
+                )
+            })
+            .collect::<Vec<_>>();
+        aggregations.sort_by_key(|(name, labels, _)| (*name, *labels));
+        aggregations
+    }
+
+    #[test]
+    fn collects_instruments_per_label_set() {
+        let meter = Meter::new("test");
+        let counter = meter.new_i64_counter("requests", api::MetricOptions::default());
+        let gauge = meter.new_f64_gauge("temperature", api::MetricOptions::default());
+        let measure = meter.new_f64_measure("latency", api::MetricOptions::default());
+
+        let get = meter.labels(vec![api::KeyValue::new("method", "GET")]);
+        let post = meter.labels(vec![api::KeyValue::new("method", "POST")]);
+        counter.add(1, &get);
+        counter.add(2, &post);
+        counter.acquire_handle(&get).add(3);
+        gauge.set(21.5, &get);
+        meter.record_batch(
+            &post,
+            vec![measure.measurement(0.5), counter.measurement(4)],
+        );
+        measure.record(1.5, &post);
+
+        let checkpoint_set = meter.collect();
+        let aggregations = aggregations(&checkpoint_set);
+        assert_eq!(aggregations.len(), 4);
+        assert_eq!(
+            aggregations[0],
+            (
+                "latency",
+                &post,
+                &Aggregation::MinMaxSumCount {
+                    min: Number::F64(0.5),
+                    max: Number::F64(1.5),
+                    sum: Number::F64(2.0),
+                    count: 2,
+                }
+            )
+        );
+        assert_eq!(
+            aggregations[1],
+            ("requests", &get, &Aggregation::Sum(Number::I64(4)))
+        );
+        assert_eq!(
+            aggregations[2],
+            ("requests", &post, &Aggregation::Sum(Number::I64(6)))
+        );
+        match aggregations[3] {
+            ("temperature", labels, Aggregation::LastValue { value, .. }) => {
+                assert_eq!(labels, &get);
+                assert_eq!(value, &Number::F64(21.5));
+            }
+            other => panic!("unexpected record {:?}", other),
+        }
+    }
+
+    #[test]
+    fn delta_export_forgets_idle_label_sets() {
+        let meter = Meter::builder("test")
+            .with_export_kind(ExportKind::Delta)
+            .build();
+        let counter = meter.new_i64_counter("requests", api::MetricOptions::default());
+        let labels = meter.labels(vec![]);
+
+        counter.add(1, &labels);
+        assert_eq!(meter.collect().records.len(), 1);
+        assert!(meter.collect().records.is_empty());
+
+        counter.add(2, &labels);
+        let checkpoint_set = meter.collect();
+        assert_eq!(
+            checkpoint_set.records[0].aggregation,
+            Aggregation::Sum(Number::I64(2))
+        );
+    }
+
+    #[test]
+    fn instruments_are_shared_by_name() {
+        let meter = Meter::new("test");
+        let labels = meter.labels(vec![]);
+        meter
+            .new_i64_counter("requests", api::MetricOptions::default())
+            .add(1, &labels);
+        meter
+            .new_i64_counter("requests", api::MetricOptions::default())
+            .add(1, &labels);
+        // Conflicting instruments are not collected
+        meter
+            .new_f64_gauge("requests", api::MetricOptions::default())
+            .set(10.0, &labels);
+
+        let checkpoint_set = meter.collect();
+        assert_eq!(checkpoint_set.records.len(), 1);
+        assert_eq!(
+            checkpoint_set.records[0].aggregation,
+            Aggregation::Sum(Number::I64(2))
+        );
+    }
+
+    /// Aggregates every instrument into min, max, sum and count.
+    #[derive(Debug)]
+    struct MinMaxSumCountSelector;
+
+    impl AggregatorSelector for MinMaxSumCountSelector {
+        fn aggregator_for(&self, descriptor: &Descriptor) -> Arc<dyn Aggregator> {
+            Arc::new(
+                crate::sdk::metrics::aggregators::MinMaxSumCountAggregator::new(
+                    descriptor.number_kind,
+                ),
+            )
+        }
+    }
+
+    #[test]
+    fn rejects_lower_values_of_monotonic_gauges_with_any_selector() {
+        let meter = Meter::builder("test")
+            .with_aggregator_selector(MinMaxSumCountSelector)
+            .with_view(View::new("view.*").with_aggregator_selector(Selector::Simple))
+            .build();
+        let labels = meter.labels(vec![]);
+        let other = meter.labels(vec![api::KeyValue::new("host", "b")]);
+        for name in &["uptime", "view.uptime"] {
+            let gauge =
+                meter.new_i64_gauge(*name, api::MetricOptions::default().with_monotonic(true));
+            assert_eq!(gauge.try_set(10, &labels), Ok(()));
+            assert_eq!(
+                gauge.try_set(5, &labels),
+                Err(api::MetricsError::NonMonotoneInput)
+            );
+            let handle = gauge.acquire_handle(&labels);
+            assert_eq!(handle.try_set(5), Err(api::MetricsError::NonMonotoneInput));
+            assert_eq!(handle.try_set(12), Ok(()));
+            // Values of other label sets are not compared
+            assert_eq!(gauge.try_set(1, &other), Ok(()));
+        }
+
+        let gauge = meter.new_i64_gauge("temperature", api::MetricOptions::default());
+        assert_eq!(gauge.try_set(10, &labels), Ok(()));
+        assert_eq!(gauge.try_set(5, &labels), Ok(()));
+    }
+
+    #[test]
+    fn rejects_values_against_instrument_semantics() {
+        let meter = Meter::new("test");
+        let labels = meter.labels(vec![]);
+        let counter = meter.new_i64_counter("requests", api::MetricOptions::default());
+        let up_down = meter.new_i64_counter(
+            "connections",
+            api::MetricOptions::default().with_monotonic(false),
+        );
+        let gauge =
+            meter.new_f64_gauge("uptime", api::MetricOptions::default().with_monotonic(true));
+        let measure = meter.new_f64_measure("latency", api::MetricOptions::default());
+        let signed =
+            meter.new_f64_measure("drift", api::MetricOptions::default().with_absolute(false));
+
+        counter.add(2, &labels);
+        counter.add(-1, &labels);
+        assert_eq!(
+            counter.try_add(-1, &labels),
+            Err(api::MetricsError::NegativeInput)
+        );
+        assert_eq!(
+            counter.acquire_handle(&labels).try_add(-1),
+            Err(api::MetricsError::NegativeInput)
+        );
+        assert_eq!(up_down.try_add(-3, &labels), Ok(()));
+        assert_eq!(gauge.try_set(10.0, &labels), Ok(()));
+        assert_eq!(
+            gauge.try_set(5.0, &labels),
+            Err(api::MetricsError::NonMonotoneInput)
+        );
+        assert_eq!(
+            measure.try_record(-0.5, &labels),
+            Err(api::MetricsError::NegativeInput)
+        );
+        assert_eq!(
+            measure.try_record(f64::NAN, &labels),
+            Err(api::MetricsError::NaNInput)
+        );
+        assert_eq!(signed.try_record(-0.5, &labels), Ok(()));
+
+        let checkpoint_set = meter.collect();
+        let aggregations = aggregations(&checkpoint_set);
+        assert_eq!(aggregations.len(), 4);
+        assert_eq!(aggregations[0].2, &Aggregation::Sum(Number::I64(-3)));
+        assert_eq!(
+            aggregations[1].2,
+            &Aggregation::MinMaxSumCount {
+                min: Number::F64(-0.5),
+                max: Number::F64(-0.5),
+                sum: Number::F64(-0.5),
+                count: 1,
+            }
+        );
+        assert_eq!(aggregations[2].2, &Aggregation::Sum(Number::I64(2)));
+        match aggregations[3].2 {
+            Aggregation::LastValue { value, .. } => assert_eq!(value, &Number::F64(10.0)),
+            other => panic!("unexpected aggregation {:?}", other),
+        }
+    }
+
+    #[test]
+    fn observers_are_invoked_on_collection() {
+        let meter = Meter::builder("test")
+            .with_export_kind(ExportKind::Delta)
+            .build();
+        let queue = Arc::new(Mutex::new(vec![1, 2, 3]));
+        let total = Arc::new(Mutex::new(10.0));
+
+        let observed_queue = queue.clone();
+        let labels = meter.labels(vec![api::KeyValue::new("queue", "jobs")]);
+        meter.register_i64_value_observer(
+            "queue_depth",
+            api::MetricOptions::default(),
+            move |result| result.observe(observed_queue.lock().unwrap().len() as i64, &labels),
+        );
+        let observed_total = total.clone();
+        let labels = meter.labels(vec![]);
+        meter.register_f64_sum_observer("cpu_time", api::MetricOptions::default(), move |result| {
+            result.observe(*observed_total.lock().unwrap(), &labels)
+        });
+        let labels = meter.labels(vec![]);
+        meter.register_i64_up_down_sum_observer(
+            "connections",
+            api::MetricOptions::default(),
+            move |result| result.observe(-2, &labels),
+        );
+
+        let checkpoint_set = meter.collect();
+        let observed = aggregations(&checkpoint_set);
+        assert_eq!(observed.len(), 3);
+        assert_eq!(observed[0].2, &Aggregation::Sum(Number::I64(-2)));
+        assert_eq!(observed[1].2, &Aggregation::Sum(Number::F64(10.0)));
+        match observed[2].2 {
+            Aggregation::LastValue { value, .. } => assert_eq!(value, &Number::I64(3)),
+            other => panic!("unexpected aggregation {:?}", other),
+        }
+
+        queue.lock().unwrap().clear();
+        *total.lock().unwrap() = 12.5;
+        let checkpoint_set = meter.collect();
+        let observed = aggregations(&checkpoint_set);
+        assert_eq!(observed[1].2, &Aggregation::Sum(Number::F64(2.5)));
+        match observed[2].2 {
+            Aggregation::LastValue { value, .. } => assert_eq!(value, &Number::I64(0)),
+            other => panic!("unexpected aggregation {:?}", other),
+        }
+    }
+
+    #[test]
+    fn batches_are_collected_atomically() {
+        let meter = Meter::new("test");
+        let requests = meter.new_i64_counter("requests", api::MetricOptions::default());
+        let responses = meter.new_i64_counter("responses", api::MetricOptions::default());
+        let labels = meter.labels(vec![]);
+
+        let recorder = {
+            let meter = meter.clone();
+            std::thread::spawn(move || {
+                for _ in 0..1000 {
+                    meter.record_batch(
+                        &labels,
+                        vec![requests.measurement(1), responses.measurement(1)],
+                    );
+                }
+            })
+        };
+        for _ in 0..100 {
+            let checkpoint_set = meter.collect();
+            let sums = aggregations(&checkpoint_set)
+                .into_iter()
+                .map(|(_, _, aggregation)| aggregation.clone())
+                .collect::<Vec<_>>();
+            if sums.len() == 2 {
+                assert_eq!(sums[0], sums[1]);
+            }
+        }
+        recorder.join().unwrap();
+    }
+
+    #[test]
+    fn histogram_selector() {
+        let meter = Meter::builder("test")
+            .with_aggregator_selector(Selector::Histogram(vec![1.0, 10.0]))
+            .build();
+        let measure = meter.new_i64_measure("size", api::MetricOptions::default());
+        let labels = meter.labels(vec![]);
+        for value in &[1, 5, 50] {
+            measure.record(*value, &labels);
+        }
+
+        assert_eq!(
+            meter.collect().records[0].aggregation,
+            Aggregation::Histogram {
+                boundaries: vec![1.0, 10.0],
+                counts: vec![1, 1, 1],
+                exemplars: vec![None; 3],
+                sum: Number::I64(56),
+                count: 3,
+            }
+        );
+    }
+
+    #[test]
+    fn measure_aggregation_overrides_selector() {
+        let meter = Meter::builder("test")
+            .with_aggregator_selector(Selector::Histogram(vec![1.0, 10.0]))
+            .build();
+        let labels = meter.labels(vec![]);
+        meter
+            .new_i64_measure(
+                "size",
+                api::MetricOptions::default().with_linear_buckets(0.0, 20.0, 3),
+            )
+            .record(30, &labels);
+        meter
+            .new_f64_measure("latency", api::MetricOptions::default().with_sketch(0.01))
+            .record(0.25, &labels);
+
+        let records = meter.collect().records;
+        let aggregation = |name: &str| {
+            records
+                .iter()
+                .find(|record| record.descriptor.name == name)
+                .map(|record| record.aggregation.clone())
+                .unwrap()
+        };
+        assert_eq!(
+            aggregation("size"),
+            Aggregation::Histogram {
+                boundaries: vec![0.0, 20.0, 40.0],
+                counts: vec![0, 0, 1, 0],
+                exemplars: vec![None; 4],
+                sum: Number::I64(30),
+                count: 1,
+            }
+        );
+        match aggregation("latency") {
+            Aggregation::Sketch(sketch) => assert_eq!(sketch.quantile(0.5), Some(0.25)),
+            other => panic!("unexpected aggregation {:?}", other),
+        }
+    }
+
+    #[test]
+    fn views_rename_filter_and_drop_instruments() {
+        let meter = Meter::builder("test")
+            .with_view(
+                View::new("http.*")
+                    .with_instrument_kind(InstrumentKind::Counter)
+                    .with_name("requests")
+                    .with_label_keys(vec![api::Key::new("method")]),
+            )
+            .with_view(View::new("debug.*").with_drop(true))
+            .with_view(
+                View::new("latency").with_aggregator_selector(Selector::Histogram(vec![1.0])),
+            )
+            .build();
+        let labels = |path| {
+            meter.labels(vec![
+                api::KeyValue::new("method", "GET"),
+                api::KeyValue::new("path", path),
+            ])
+        };
+
+        let requests = meter.new_i64_counter(
+            "http.requests",
+            api::MetricOptions::default()
+                .with_keys(vec![api::Key::new("method"), api::Key::new("path")]),
+        );
+        requests.add(1, &labels("/a"));
+        requests.add(2, &labels("/b"));
+        {
+            // Each recorded label set is filtered once
+            let records = requests.core.records.lock().unwrap();
+            assert_eq!(records.filtered.len(), 2);
+            assert_eq!(records.aggregators.len(), 1);
+        }
+        let debug = meter.new_i64_counter("debug.allocations", api::MetricOptions::default());
+        debug.add(1, &labels("/a"));
+        debug.acquire_handle(&labels("/a")).add(1);
+        assert_eq!(
+            debug.try_add(-1, &labels("/a")),
+            Err(api::MetricsError::NegativeInput)
+        );
+        meter
+            .new_f64_measure("latency", api::MetricOptions::default().with_sketch(0.01))
+            .record(0.5, &labels("/a"));
+
+        let checkpoint_set = meter.collect();
+        let method = meter.labels(vec![api::KeyValue::new("method", "GET")]);
+        assert_eq!(
+            aggregations(&checkpoint_set),
+            vec![
+                (
+                    "latency",
+                    &labels("/a"),
+                    &Aggregation::Histogram {
+                        boundaries: vec![1.0],
+                        counts: vec![1, 0],
+                        exemplars: vec![None; 2],
+                        sum: Number::F64(0.5),
+                        count: 1,
+                    }
+                ),
+                ("requests", &method, &Aggregation::Sum(Number::I64(3))),
+            ]
+        );
+        let requests = checkpoint_set
+            .records
+            .iter()
+            .find(|record| record.descriptor.name == "requests")
+            .unwrap();
+        assert_eq!(requests.descriptor.keys, vec![api::Key::new("method")]);
+    }
+
+    #[test]
+    fn cardinality_limit_overflows_label_sets() {
+        let meter = Meter::builder("test")
+            .with_cardinality_limit(2)
+            .with_view(View::new("sessions").with_cardinality_limit(1))
+            .build();
+        let user = |id: i64| meter.labels(vec![api::KeyValue::new("user", id)]);
+        let requests = meter.new_i64_counter("requests", api::MetricOptions::default());
+        let sessions = meter.new_i64_counter("sessions", api::MetricOptions::default());
+        for id in 0..5 {
+            requests.add(1, &user(id));
+            sessions.add(1, &user(id));
+        }
+        requests.add(1, &user(0));
+        // Label sets are counted once when dropped repeatedly
+        requests.add(1, &user(3));
+        let handle = requests.acquire_handle(&user(6));
+        handle.add(1);
+
+        let overflow = meter.labels(vec![api::KeyValue::new(OVERFLOW_LABEL_KEY, "true")]);
+        assert_eq!(
+            aggregations(&meter.collect()),
+            vec![
+                (
+                    DROPPED_LABEL_SETS_METRIC,
+                    &meter.labels(vec![]),
+                    &Aggregation::Sum(Number::I64(8))
+                ),
+                ("requests", &overflow, &Aggregation::Sum(Number::I64(5))),
+                ("requests", &user(0), &Aggregation::Sum(Number::I64(2))),
+                ("requests", &user(1), &Aggregation::Sum(Number::I64(1))),
+                ("sessions", &overflow, &Aggregation::Sum(Number::I64(4))),
+                ("sessions", &user(0), &Aggregation::Sum(Number::I64(1))),
+            ]
+        );
+        assert_eq!(meter.dropped_label_sets(), 8);
+    }
+
+    #[test]
+    fn overflow_label_set_is_not_counted_in_limit() {
+        let meter = Meter::builder("test")
+            .with_export_kind(ExportKind::Delta)
+            .with_cardinality_limit(1)
+            .build();
+        let user = |id: i64| meter.labels(vec![api::KeyValue::new("user", id)]);
+        let requests = meter.new_i64_counter("requests", api::MetricOptions::default());
+        requests.add(1, &user(0));
+        requests.add(1, &user(1));
+        meter.collect();
+        // The label set of user 0 is forgotten, the overflow label set is kept
+        requests.add(1, &user(2));
+        meter.collect();
+
+        requests.add(1, &user(3));
+        let checkpoint_set = meter.collect();
+        assert!(aggregations(&checkpoint_set).contains(&(
+            "requests",
+            &user(3),
+            &Aggregation::Sum(Number::I64(1))
+        )));
+    }
+
+    #[test]
+    fn measures_keep_exemplars_of_sampled_spans() {
+        let meter = Meter::builder("test")
+            .with_aggregator_selector(Selector::Histogram(vec![1.0]))
+            .build();
+        let measure = meter.new_f64_measure("latency", api::MetricOptions::default());
+        let labels = meter.labels(vec![]);
+        measure.record(0.5, &labels);
+        let tracer = sdk::Provider::default().get_tracer("test");
+        let span_context = tracer.in_span("request", |cx| {
+            measure.record(2.0, &labels);
+            cx.span().span_context()
+        });
+
+        match &meter.collect().records[0].aggregation {
+            Aggregation::Histogram { exemplars, .. } => {
+                assert_eq!(exemplars[0], None);
+                let exemplar = exemplars[1].as_ref().unwrap();
+                assert_eq!(exemplar.value, Number::F64(2.0));
+                assert_eq!(exemplar.trace_id, span_context.trace_id());
+                assert_eq!(exemplar.span_id, span_context.span_id());
+            }
+            other => panic!("unexpected aggregation {:?}", other),
+        }
+    }
+}
@@ -9,202 +9,188 @@
 //! `Meter` struct, used for recording metrics. There are three distinct
 //! instruments in the Metrics API, commonly known as `Counter`s, `Gauge`s,
 //! and `Measure`s.
+//!
+//! Instruments created by the SDK [`Meter`] feed their measurements into
+//! [`Aggregator`]s, one per instrument and label set. Collecting the meter
+//! checkpoints the aggregators for export, either periodically by a
+//! [`PushController`] driving a [`MetricExporter`], or on demand by pull
+//! based exporters such as Prometheus.
+//!
+//! Meters are usually obtained by name from a [`MeterProvider`], which shares
+//! its configuration with all of its meters and collects them together.
+//! [`View`]s change how the instruments they match are exported.
+//!
+//! [`Meter`]: meter/struct.Meter.html
+//! [`MeterProvider`]: provider/struct.MeterProvider.html
+//! [`View`]: view/struct.View.html
+//! [`Aggregator`]: aggregators/trait.Aggregator.html
+//! [`PushController`]: controller/struct.PushController.html
+//! [`MetricExporter`]: ../../exporter/metrics/trait.MetricExporter.html
 use crate::api;
-use crate::exporter::metrics::prometheus;
+use crate::exporter::metrics::{CheckpointSet, ExportKind};
 use std::borrow::Cow;
-use std::collections::HashMap;
-
-/// Collection of label key and value types.
-pub type LabelSet = HashMap<Cow<'static, str>, Cow<'static, str>>;
-impl api::LabelSet for LabelSet {}
+use std::cmp::Ordering;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{btree_map, BTreeMap};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::FromIterator;
+use std::sync::Arc;
+
+pub mod aggregators;
+pub mod controller;
+pub mod meter;
+pub mod provider;
+pub mod sketch;
+pub mod view;
+
+pub use aggregators::{Aggregator, AggregatorSelector, Selector};
+pub use controller::PushController;
+pub use meter::{Counter, Gauge, Measure, Meter};
+pub use provider::MeterProvider;
+pub use sketch::DDSketch;
+pub use view::View;
+
+/// A source of metric checkpoints, collected by controllers and pull based
+/// exporters. Implemented by `Meter` and `MeterProvider`.
+pub trait Checkpointer: Send + Sync + fmt::Debug {
+    /// Checkpoint the aggregations of all instruments.
+    fn collect(&self) -> CheckpointSet;
+
+    /// Whether checkpoints are cumulative or deltas since the previous
+    /// checkpoint.
+    fn export_kind(&self) -> ExportKind;
+}
 
-/// `Meter` implementation to create manage metric instruments and record
-/// batch measurements
-#[allow(missing_debug_implementations)]
-pub struct Meter {
-    registry: &'static prometheus::Registry,
-    component: &'static str,
+/// Collection of label key and value types, ordered by key.
+///
+/// The labels are hashed once when the set is created, so recording
+/// measurements with the same set does not hash every label again.
+#[derive(Clone, Debug)]
+pub struct LabelSet {
+    labels: Arc<BTreeMap<Cow<'static, str>, Cow<'static, str>>>,
+    hash: u64,
 }
 
-impl Meter {
-    /// Create a new `Meter` instance with a component name and empty registry.
-    pub fn new(component: &'static str) -> Self {
-        Meter {
-            registry: prometheus::default_registry(),
-            component,
-        }
+impl LabelSet {
+    /// Returns the value of the label with the given key.
+    pub fn get(&self, key: &str) -> Option<&Cow<'static, str>> {
+        self.labels.get(key)
     }
 
-    /// Build prometheus `Opts` from `name` and `description`.
-    fn build_opts(
-        &self,
-        mut name: String,
-        unit: api::Unit,
-        description: String,
-    ) -> prometheus::Opts {
-        if !unit.as_str().is_empty() {
-            name.push_str(&format!("_{}", unit.as_str()));
-        }
-        // Prometheus cannot have empty help strings
-        let help = if !description.is_empty() {
-            description
-        } else {
-            format!("{} metric", name)
-        };
-        prometheus::Opts::new(name, help).namespace(self.component)
+    /// An iterator over the labels, ordered by key.
+    pub fn iter(&self) -> btree_map::Iter<'_, Cow<'static, str>, Cow<'static, str>> {
+        self.labels.iter()
+    }
+
+    /// The number of labels in the set.
+    pub fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    /// Whether the set has no labels.
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
     }
 }
 
-impl api::Meter for Meter {
-    /// The label set used by this `Meter`.
-    type LabelSet = LabelSet;
-    /// This implementation of `api::Meter` produces `prometheus::IntCounterVec;` instances.
-    type I64Counter = prometheus::IntCounterVec;
-    /// This implementation of `api::Meter` produces `prometheus::CounterVec;` instances.
-    type F64Counter = prometheus::CounterVec;
-    /// This implementation of `api::Meter` produces `prometheus::IntGaugeVec;` instances.
-    type I64Gauge = prometheus::IntGaugeVec;
-    /// This implementation of `api::Meter` produces `prometheus::GaugeVec;` instances.
-    type F64Gauge = prometheus::GaugeVec;
-    /// This implementation of `api::Meter` produces `prometheus::IntMeasure;` instances.
-    type I64Measure = prometheus::IntMeasure;
-    /// This implementation of `api::Meter` produces `prometheus::HistogramVec;` instances.
-    type F64Measure = prometheus::HistogramVec;
-
-    /// Builds a `LabelSet` from `KeyValue`s.
-    fn labels(&self, key_values: Vec<api::KeyValue>) -> Self::LabelSet {
-        let mut label_set: Self::LabelSet = Default::default();
-
-        for api::KeyValue { key, value } in key_values.into_iter() {
-            label_set.insert(Cow::Owned(key.into()), Cow::Owned(value.into()));
-        }
+impl api::LabelSet for LabelSet {}
 
-        label_set
+impl Default for LabelSet {
+    fn default() -> Self {
+        LabelSet::from(BTreeMap::new())
     }
+}
 
-    /// Creates a new `i64` counter with a given name and customized with passed options.
-    fn new_i64_counter<S: Into<String>>(
-        &self,
-        name: S,
-        opts: api::MetricOptions,
-    ) -> Self::I64Counter {
-        let api::MetricOptions {
-            description,
-            unit,
-            keys,
-            alternate: _alternative,
-        } = opts;
-        let counter_opts = self.build_opts(name.into(), unit, description);
-        let labels = prometheus::convert_labels(&keys);
-        let counter = prometheus::IntCounterVec::new(counter_opts, &labels).unwrap();
-        self.registry.register(Box::new(counter.clone())).unwrap();
-
-        counter
+impl From<BTreeMap<Cow<'static, str>, Cow<'static, str>>> for LabelSet {
+    fn from(labels: BTreeMap<Cow<'static, str>, Cow<'static, str>>) -> Self {
+        let mut hasher = DefaultHasher::new();
+        labels.hash(&mut hasher);
+
+        LabelSet {
+            labels: Arc::new(labels),
+            hash: hasher.finish(),
+        }
     }
+}
+
+impl FromIterator<(Cow<'static, str>, Cow<'static, str>)> for LabelSet {
+    /// Later values replace earlier values with the same key.
+    fn from_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = (Cow<'static, str>, Cow<'static, str>)>,
+    {
+        LabelSet::from(iter.into_iter().collect::<BTreeMap<_, _>>())
+    }
+}
+
+impl<'a> IntoIterator for &'a LabelSet {
+    type Item = (&'a Cow<'static, str>, &'a Cow<'static, str>);
+    type IntoIter = btree_map::Iter<'a, Cow<'static, str>, Cow<'static, str>>;
 
-    /// Creates a new `f64` counter with a given name and customized with passed options.
-    fn new_f64_counter<S: Into<String>>(
-        &self,
-        name: S,
-        opts: api::MetricOptions,
-    ) -> Self::F64Counter {
-        let api::MetricOptions {
-            description,
-            unit,
-            keys,
-            alternate: _alternative,
-        } = opts;
-        let counter_opts = self.build_opts(name.into(), unit, description);
-        let labels = prometheus::convert_labels(&keys);
-        let counter = prometheus::CounterVec::new(counter_opts, &labels).unwrap();
-        self.registry.register(Box::new(counter.clone())).unwrap();
-
-        counter
+    fn into_iter(self) -> Self::IntoIter {
+        self.labels.iter()
     }
+}
 
-    /// Creates a new `i64` gauge with a given name and customized with passed options.
-    fn new_i64_gauge<S: Into<String>>(&self, name: S, opts: api::MetricOptions) -> Self::I64Gauge {
-        let api::MetricOptions {
-            description,
-            unit,
-            keys,
-            alternate: _alternative,
-        } = opts;
-        let gauge_opts = self.build_opts(name.into(), unit, description);
-        let labels = prometheus::convert_labels(&keys);
-        let gauge = prometheus::IntGaugeVec::new(gauge_opts, &labels).unwrap();
-        self.registry.register(Box::new(gauge.clone())).unwrap();
-
-        gauge
+impl PartialEq for LabelSet {
+    fn eq(&self, other: &Self) -> bool {
+        // Clones of a set share their labels
+        self.hash == other.hash
+            && (Arc::ptr_eq(&self.labels, &other.labels) || self.labels == other.labels)
     }
+}
 
-    /// Creates a new `f64` gauge with a given name and customized with passed options.
-    fn new_f64_gauge<S: Into<String>>(&self, name: S, opts: api::MetricOptions) -> Self::F64Gauge {
-        let api::MetricOptions {
-            description,
-            unit,
-            keys,
-            alternate: _alternative,
-        } = opts;
-        let gauge_opts = self.build_opts(name.into(), unit, description);
-        let labels = prometheus::convert_labels(&keys);
-        let gauge = prometheus::GaugeVec::new(gauge_opts, &labels).unwrap();
-        self.registry.register(Box::new(gauge.clone())).unwrap();
-
-        gauge
+impl Eq for LabelSet {}
+
+impl Hash for LabelSet {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.hash)
     }
+}
 
-    /// Creates a new `i64` measure with a given name and customized with passed options.
-    fn new_i64_measure<S: Into<String>>(
-        &self,
-        name: S,
-        opts: api::MetricOptions,
-    ) -> Self::I64Measure {
-        let api::MetricOptions {
-            description,
-            unit,
-            keys,
-            alternate: _alternative,
-        } = opts;
-        let common_opts = self.build_opts(name.into(), unit, description);
-        let labels = prometheus::convert_labels(&keys);
-        let histogram_opts = prometheus::HistogramOpts::from(common_opts);
-        let histogram = prometheus::HistogramVec::new(histogram_opts, &labels).unwrap();
-        self.registry.register(Box::new(histogram.clone())).unwrap();
-
-        prometheus::IntMeasure::new(histogram)
+impl PartialOrd for LabelSet {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
     }
+}
 
-    /// Creates a new `f64` measure with a given name and customized with passed options.
-    fn new_f64_measure<S: Into<String>>(
-        &self,
-        name: S,
-        opts: api::MetricOptions,
-    ) -> Self::F64Measure {
-        let api::MetricOptions {
-            description,
-            unit,
-            keys,
-            alternate: _alternative,
-        } = opts;
-        let common_opts = self.build_opts(name.into(), unit, description);
-        let labels = prometheus::convert_labels(&keys);
-        let histogram_opts = prometheus::HistogramOpts::from(common_opts);
-        let histogram = prometheus::HistogramVec::new(histogram_opts, &labels).unwrap();
-        self.registry.register(Box::new(histogram.clone())).unwrap();
-
-        histogram
+impl Ord for LabelSet {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.labels.cmp(&other.labels)
     }
+}
 
-    /// Records a batch of measurements.
-    fn record_batch<M: IntoIterator<Item = api::Measurement<Self::LabelSet>>>(
-        &self,
-        label_set: &Self::LabelSet,
-        measurements: M,
-    ) {
-        for measure in measurements.into_iter() {
-            let instrument = measure.instrument();
-            instrument.record_one(measure.into_value(), &label_set);
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn label_sets_are_ordered_by_key() {
+        let label_set = vec![("b", "2"), ("a", "1"), ("b", "3")]
+            .into_iter()
+            .map(|(key, value)| (Cow::Borrowed(key), Cow::Borrowed(value)))
+            .collect::<LabelSet>();
+        let reordered = vec![("a", "1"), ("b", "3")]
+            .into_iter()
+            .map(|(key, value)| (Cow::Borrowed(key), Cow::Borrowed(value)))
+            .collect::<LabelSet>();
+
+        assert_eq!(label_set, reordered);
+        assert_eq!(label_set.len(), 2);
+        assert_eq!(label_set.get("b").map(|value| value.as_ref()), Some("3"));
+        assert_eq!(
+            label_set
+                .iter()
+                .map(|(key, _)| key.as_ref())
+                .collect::<Vec<_>>(),
+            vec!["a", "b"]
+        );
+        let set = vec![label_set, reordered]
+            .into_iter()
+            .collect::<HashSet<_>>();
+        assert_eq!(set.len(), 1);
+        assert!(LabelSet::default().is_empty());
     }
 }
@@ -0,0 +1,242 @@
+//! # Meter Provider SDK
+//!
+//! New `Meter` instances are created through a [`MeterProvider`], which
+//! configures them with its aggregator selector, export kind, views and
+//! resource. Meters are cached by name, so libraries asking for the same
+//! meter share its instruments.
+//!
+//! ```
+//! use opentelemetry::api::{self, Counter, Meter, MeterProvider};
+//! use opentelemetry::{global, sdk};
+//!
+//! let provider = sdk::MeterProvider::default();
+//! global::set_meter_provider(provider.clone());
+//!
+//! // Libraries record measurements through the global provider
+//! let meter = global::meter("my-library");
+//! let counter = meter.new_i64_counter("requests", api::MetricOptions::default());
+//! counter.add(1, &meter.labels(vec![]));
+//!
+//! // And the application collects all meters of the provider
+//! assert_eq!(provider.collect().records.len(), 1);
+//! ```
+//!
+//! [`MeterProvider`]: struct.MeterProvider.html
+use crate::exporter::metrics::{CheckpointSet, ExportKind};
+use crate::sdk::metrics::{meter, AggregatorSelector, Checkpointer};
+use crate::{api, sdk};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::SystemTime;
+
+/// Default meter name if empty string is provided.
+const DEFAULT_COMPONENT_NAME: &str = "rust.opentelemetry.io/sdk/meter";
+
+#[derive(Debug)]
+struct MeterProviderInner {
+    named_meters: RwLock<HashMap<&'static str, sdk::Meter>>,
+    meter_builder: meter::Builder,
+    resource: Arc<sdk::Resource>,
+}
+
+/// Creator and registry of named `Meter` instances.
+#[derive(Clone, Debug)]
+pub struct MeterProvider {
+    inner: Arc<MeterProviderInner>,
+}
+
+impl Default for MeterProvider {
+    fn default() -> Self {
+        MeterProvider::builder().build()
+    }
+}
+
+impl MeterProvider {
+    /// Create a new `MeterProvider` builder.
+    pub fn builder() -> Builder {
+        Builder {
+            meter_builder: sdk::Meter::builder(DEFAULT_COMPONENT_NAME),
+            resource: sdk::Resource::default(),
+        }
+    }
+
+    /// The number of label sets replaced by the overflow label set, in all
+    /// meters created by this provider.
+    pub fn dropped_label_sets(&self) -> u64 {
+        self.inner
+            .named_meters
+            .read()
+            .map(|meters| meters.values().map(sdk::Meter::dropped_label_sets).sum())
+            .unwrap_or(0)
+    }
+
+    /// Whether the meters of this provider collect cumulative aggregations or
+    /// deltas since the previous collection.
+    pub fn export_kind(&self) -> ExportKind {
+        self.inner.meter_builder.export_kind()
+    }
+
+    /// Checkpoint the aggregations of all meters created by this provider.
+    pub fn collect(&self) -> CheckpointSet {
+        let mut records = Vec::new();
+        if let Ok(meters) = self.inner.named_meters.read() {
+            for meter in meters.values() {
+                records.extend(meter.collect().records);
+            }
+        }
+
+        CheckpointSet {
+            resource: self.inner.resource.clone(),
+            timestamp: SystemTime::now(),
+            export_kind: self.export_kind(),
+            records,
+        }
+    }
+}
+
+impl Checkpointer for MeterProvider {
+    fn collect(&self) -> CheckpointSet {
+        MeterProvider::collect(self)
+    }
+
+    fn export_kind(&self) -> ExportKind {
+        MeterProvider::export_kind(self)
+    }
+}
+
+impl api::MeterProvider for MeterProvider {
+    /// This implementation of `api::MeterProvider` produces `sdk::Meter` instances.
+    type Meter = sdk::Meter;
+
+    /// Find or create `Meter` instance by name.
+    fn get_meter(&self, name: &'static str) -> Self::Meter {
+        // Use default value if name is invalid empty string
+        let component_name = if name.is_empty() {
+            DEFAULT_COMPONENT_NAME
+        } else {
+            name
+        };
+
+        // Return named meter if already initialized
+        if let Some(meter) = self
+            .inner
+            .named_meters
+            .read()
+            .expect("RwLock poisoned")
+            .get(&component_name)
+        {
+            return meter.clone();
+        };
+
+        // Else construct new named meter, unless another thread just did
+        let mut meters = self.inner.named_meters.write().expect("RwLock poisoned");
+        meters
+            .entry(component_name)
+            .or_insert_with(|| {
+                self.inner
+                    .meter_builder
+                    .clone()
+                    .with_name(component_name)
+                    .build()
+            })
+            .clone()
+    }
+}
+
+/// Builder for meter provider attributes.
+#[derive(Debug)]
+pub struct Builder {
+    meter_builder: meter::Builder,
+    resource: sdk::Resource,
+}
+
+impl Builder {
+    /// Assign the selector choosing the aggregator of each instrument.
+    /// Defaults to `Selector::Simple`.
+    pub fn with_aggregator_selector<T: AggregatorSelector + 'static>(self, selector: T) -> Self {
+        Builder {
+            meter_builder: self.meter_builder.with_aggregator_selector(selector),
+            ..self
+        }
+    }
+
+    /// Assign whether collected aggregations are cumulative or deltas since
+    /// the previous collection. Defaults to `ExportKind::Cumulative`.
+    pub fn with_export_kind(self, export_kind: ExportKind) -> Self {
+        Builder {
+            meter_builder: self.meter_builder.with_export_kind(export_kind),
+            ..self
+        }
+    }
+
+    /// Assign the resource exported with the collected metrics.
+    pub fn with_resource(self, resource: sdk::Resource) -> Self {
+        Builder {
+            meter_builder: self.meter_builder.with_resource(resource.clone()),
+            resource,
+        }
+    }
+
+    /// Assign the maximum number of label sets aggregated by each instrument.
+    /// Defaults to `DEFAULT_CARDINALITY_LIMIT`.
+    pub fn with_cardinality_limit(self, cardinality_limit: usize) -> Self {
+        Builder {
+            meter_builder: self.meter_builder.with_cardinality_limit(cardinality_limit),
+            ..self
+        }
+    }
+
+    /// Add a view configuring the matching instruments of all meters.
+    /// Instruments use the first matching view, in the order views are added.
+    pub fn with_view(self, view: sdk::metrics::View) -> Self {
+        Builder {
+            meter_builder: self.meter_builder.with_view(view),
+            ..self
+        }
+    }
+
+    /// Create a new provider from this configuration.
+    pub fn build(self) -> MeterProvider {
+        MeterProvider {
+            inner: Arc::new(MeterProviderInner {
+                named_meters: Default::default(),
+                meter_builder: self.meter_builder,
+                resource: Arc::new(self.resource),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{Counter, Meter, MeterProvider as _};
+    use crate::exporter::metrics::{Aggregation, Number};
+
+    #[test]
+    fn meters_are_cached_by_name() {
+        let provider = MeterProvider::default();
+        let labels = provider.get_meter("a").labels(vec![]);
+        provider
+            .get_meter("a")
+            .new_i64_counter("requests", api::MetricOptions::default())
+            .add(1, &labels);
+        provider
+            .get_meter("a")
+            .new_i64_counter("requests", api::MetricOptions::default())
+            .add(2, &labels);
+        provider
+            .get_meter("b")
+            .new_i64_counter("requests", api::MetricOptions::default())
+            .add(4, &labels);
+        assert_eq!(provider.get_meter("").name(), DEFAULT_COMPONENT_NAME);
+
+        let mut records = provider.collect().records;
+        records.sort_by_key(|record| record.descriptor.meter_name);
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].descriptor.meter_name, "a");
+        assert_eq!(records[0].aggregation, Aggregation::Sum(Number::I64(3)));
+        assert_eq!(records[1].descriptor.meter_name, "b");
+        assert_eq!(records[1].aggregation, Aggregation::Sum(Number::I64(4)));
+    }
+}
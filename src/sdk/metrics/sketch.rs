@@ -0,0 +1,203 @@
+//! # DDSketch
+//!
+//! A [DDSketch] estimates quantiles of a distribution with a bounded
+//! relative error. Values are counted in logarithmically sized buckets, so
+//! that any quantile estimate is within the configured relative accuracy of
+//! the actual value, whatever the range of the recorded values.
+//!
+//! [DDSketch]: https://arxiv.org/abs/1908.10693
+use std::collections::BTreeMap;
+
+/// Relative accuracy used when the requested accuracy is not in `(0, 1)`.
+pub const DEFAULT_RELATIVE_ACCURACY: f64 = 0.01;
+
+/// A quantile sketch with relative error guarantees.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DDSketch {
+    relative_accuracy: f64,
+    gamma: f64,
+    gamma_ln: f64,
+    positive: BTreeMap<i32, u64>,
+    negative: BTreeMap<i32, u64>,
+    zero_count: u64,
+    min: f64,
+    max: f64,
+    sum: f64,
+    count: u64,
+}
+
+impl DDSketch {
+    /// Create a new empty sketch. Quantile estimates are within
+    /// `relative_accuracy` of the actual values.
+    pub fn new(relative_accuracy: f64) -> Self {
+        let relative_accuracy = if relative_accuracy > 0.0 && relative_accuracy < 1.0 {
+            relative_accuracy
+        } else {
+            DEFAULT_RELATIVE_ACCURACY
+        };
+        let gamma = (1.0 + relative_accuracy) / (1.0 - relative_accuracy);
+
+        DDSketch {
+            relative_accuracy,
+            gamma,
+            gamma_ln: gamma.ln(),
+            positive: BTreeMap::new(),
+            negative: BTreeMap::new(),
+            zero_count: 0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+            sum: 0.0,
+            count: 0,
+        }
+    }
+
+    /// Add a value to the sketch. `NaN` values are ignored.
+    pub fn add(&mut self, value: f64) {
+        if value.is_nan() {
+            return;
+        }
+
+        if value.abs() < f64::MIN_POSITIVE {
+            self.zero_count += 1;
+        } else {
+            let index = self.index(value.abs());
+            let store = if value > 0.0 {
+                &mut self.positive
+            } else {
+                &mut self.negative
+            };
+            *store.entry(index).or_insert(0) += 1;
+        }
+
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.sum += value;
+        self.count += 1;
+    }
+
+    /// Estimate the value at quantile `q`, between 0 and 1. Returns `None`
+    /// if the sketch is empty or the quantile is out of range.
+    pub fn quantile(&self, q: f64) -> Option<f64> {
+        if self.count == 0 || !(0.0..=1.0).contains(&q) {
+            return None;
+        }
+
+        let rank = (q * (self.count - 1) as f64) as u64;
+        let mut seen = 0;
+        // Negative values in increasing order have decreasing magnitudes
+        for (index, count) in self.negative.iter().rev() {
+            seen += count;
+            if seen > rank {
+                return Some(self.clamp(-self.value(*index)));
+            }
+        }
+        seen += self.zero_count;
+        if seen > rank {
+            return Some(0.0);
+        }
+        for (index, count) in &self.positive {
+            seen += count;
+            if seen > rank {
+                return Some(self.clamp(self.value(*index)));
+            }
+        }
+
+        Some(self.max)
+    }
+
+    /// The relative accuracy of the quantile estimates.
+    pub fn relative_accuracy(&self) -> f64 {
+        self.relative_accuracy
+    }
+
+    /// The number of values added to the sketch.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// The sum of the values added to the sketch.
+    pub fn sum(&self) -> f64 {
+        self.sum
+    }
+
+    /// The smallest value added to the sketch.
+    pub fn min(&self) -> Option<f64> {
+        if self.count > 0 {
+            Some(self.min)
+        } else {
+            None
+        }
+    }
+
+    /// The largest value added to the sketch.
+    pub fn max(&self) -> Option<f64> {
+        if self.count > 0 {
+            Some(self.max)
+        } else {
+            None
+        }
+    }
+
+    /// The index of the bucket counting a positive value.
+    fn index(&self, value: f64) -> i32 {
+        (value.ln() / self.gamma_ln).ceil() as i32
+    }
+
+    /// The estimate of the values counted in a bucket, equally distant from
+    /// the bucket bounds relative to them.
+    fn value(&self, index: i32) -> f64 {
+        2.0 * (index as f64 * self.gamma_ln).exp() / (self.gamma + 1.0)
+    }
+
+    fn clamp(&self, value: f64) -> f64 {
+        value.max(self.min).min(self.max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_relative_error(estimate: f64, actual: f64, accuracy: f64) {
+        assert!(
+            (estimate - actual).abs() <= actual.abs() * accuracy,
+            "estimate {} is not within {} of {}",
+            estimate,
+            accuracy,
+            actual
+        );
+    }
+
+    #[test]
+    fn quantiles_are_within_relative_accuracy() {
+        let mut sketch = DDSketch::new(0.01);
+        for i in 1..=10_000 {
+            sketch.add(i as f64 / 1_000_000.0);
+        }
+
+        assert_eq!(sketch.count(), 10_000);
+        assert_eq!(sketch.min(), Some(0.000_001));
+        assert_eq!(sketch.max(), Some(0.01));
+        for &q in &[0.0, 0.25, 0.5, 0.9, 0.99, 1.0] {
+            let actual = (q * 9_999.0) as u64 as f64 / 1_000_000.0 + 0.000_001;
+            assert_relative_error(sketch.quantile(q).unwrap(), actual, 0.01);
+        }
+    }
+
+    #[test]
+    fn negative_and_zero_values() {
+        let mut sketch = DDSketch::new(0.02);
+        for &value in &[-100.0, -3.0, 0.0, 0.0, 3.0, 100.0, f64::NAN] {
+            sketch.add(value);
+        }
+
+        assert_eq!(sketch.count(), 6);
+        assert_eq!(sketch.sum(), 0.0);
+        assert_eq!(sketch.quantile(0.0), Some(-100.0));
+        assert_relative_error(sketch.quantile(0.2).unwrap(), -3.0, 0.02);
+        assert_eq!(sketch.quantile(0.5), Some(0.0));
+        assert_eq!(sketch.quantile(1.0), Some(100.0));
+        assert_eq!(sketch.quantile(1.5), None);
+        assert_eq!(DDSketch::new(0.01).quantile(0.5), None);
+    }
+}
@@ -0,0 +1,156 @@
+//! # Metric Views
+//!
+//! A [`View`] changes what the SDK `Meter` exports for the instruments it
+//! matches, without changing the code creating them. Views can rename
+//! instruments, keep only some of their label keys, change their
+//! cardinality limit, replace their aggregator, or drop them altogether.
+//!
+//! ```
+//! use opentelemetry::api;
+//! use opentelemetry::sdk::{self, metrics::View};
+//!
+//! let meter = sdk::Meter::builder("example")
+//!     // Only keep the method of http requests
+//!     .with_view(View::new("http.*").with_label_keys(vec![api::Key::new("method")]))
+//!     // And do not export debug instruments
+//!     .with_view(View::new("debug.*").with_drop(true))
+//!     .build();
+//! ```
+//!
+//! Each instrument uses the first matching view, in the order they were
+//! added, and is left unchanged if no view matches it.
+//!
+//! [`View`]: struct.View.html
+use crate::api;
+use crate::exporter::metrics::InstrumentKind;
+use crate::sdk::metrics::AggregatorSelector;
+use crate::sdk::pattern::matches_pattern;
+use std::sync::Arc;
+
+/// Configures the instruments whose name matches a pattern.
+#[derive(Clone, Debug)]
+pub struct View {
+    pattern: String,
+    instrument_kind: Option<InstrumentKind>,
+    pub(crate) name: Option<String>,
+    pub(crate) description: Option<String>,
+    pub(crate) label_keys: Option<Vec<api::Key>>,
+    pub(crate) cardinality_limit: Option<usize>,
+    pub(crate) selector: Option<Arc<dyn AggregatorSelector>>,
+    pub(crate) drop: bool,
+}
+
+impl View {
+    /// Create a new view for the instruments whose name matches `pattern`.
+    /// The pattern is matched against the whole name, and `*` matches any
+    /// sequence of characters.
+    pub fn new<S: Into<String>>(pattern: S) -> Self {
+        View {
+            pattern: pattern.into(),
+            instrument_kind: None,
+            name: None,
+            description: None,
+            label_keys: None,
+            cardinality_limit: None,
+            selector: None,
+            drop: false,
+        }
+    }
+
+    /// Only match instruments of the given kind.
+    pub fn with_instrument_kind(self, instrument_kind: InstrumentKind) -> Self {
+        View {
+            instrument_kind: Some(instrument_kind),
+            ..self
+        }
+    }
+
+    /// Export the matched instruments with the given name.
+    pub fn with_name<S: Into<String>>(self, name: S) -> Self {
+        View {
+            name: Some(name.into()),
+            ..self
+        }
+    }
+
+    /// Export the matched instruments with the given description.
+    pub fn with_description<S: Into<String>>(self, description: S) -> Self {
+        View {
+            description: Some(description.into()),
+            ..self
+        }
+    }
+
+    /// Only keep the labels with the given keys. Measurements whose label
+    /// sets only differ by other labels are aggregated together.
+    pub fn with_label_keys(self, label_keys: Vec<api::Key>) -> Self {
+        View {
+            label_keys: Some(label_keys),
+            ..self
+        }
+    }
+
+    /// Assign the maximum number of label sets aggregated by the matched
+    /// instruments, instead of the limit of the `Meter`.
+    pub fn with_cardinality_limit(self, cardinality_limit: usize) -> Self {
+        View {
+            cardinality_limit: Some(cardinality_limit),
+            ..self
+        }
+    }
+
+    /// Aggregate the matched instruments with the given selector instead of
+    /// the selector of the `Meter` and the aggregation requested by the
+    /// instrument options.
+    pub fn with_aggregator_selector<T: AggregatorSelector + 'static>(self, selector: T) -> Self {
+        View {
+            selector: Some(Arc::new(selector)),
+            ..self
+        }
+    }
+
+    /// Discard the measurements of the matched instruments, which are not
+    /// exported.
+    pub fn with_drop(self, drop: bool) -> Self {
+        View { drop, ..self }
+    }
+
+    /// Whether this view applies to the given instrument.
+    pub fn matches(&self, name: &str, instrument_kind: InstrumentKind) -> bool {
+        (self.instrument_kind.is_none() || self.instrument_kind == Some(instrument_kind))
+            && matches_pattern(&self.pattern, name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_names_and_kinds() {
+        for (pattern, name, expected) in &[
+            ("requests", "requests", true),
+            ("requests", "requests.total", false),
+            ("*", "anything", true),
+            ("http.*", "http.requests", true),
+            ("http.*", "grpc.requests", false),
+            ("*.latency", "http.server.latency", true),
+            ("http.*.latency", "http.server.latency", true),
+            ("http.*.latency", "http.latency", false),
+            ("a*b*c", "abc", true),
+            ("a*b*c", "acb", false),
+        ] {
+            assert_eq!(
+                View::new(*pattern).matches(name, InstrumentKind::Counter),
+                *expected,
+                "{} matching {}",
+                pattern,
+                name
+            );
+        }
+
+        let view = View::new("*").with_instrument_kind(InstrumentKind::Measure);
+        assert!(view.matches("latency", InstrumentKind::Measure));
+        assert!(!view.matches("latency", InstrumentKind::Counter));
+    }
+}
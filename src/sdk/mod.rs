@@ -8,12 +8,14 @@
 //! `Meter` creation.
 #[cfg(feature = "metrics")]
 pub mod metrics;
+#[cfg(any(feature = "metrics", feature = "trace"))]
+mod pattern;
 pub mod resource;
 #[cfg(feature = "trace")]
 pub mod trace;
 
 #[cfg(feature = "metrics")]
-pub use metrics::{LabelSet, Meter};
+pub use metrics::{LabelSet, Meter, MeterProvider, PushController};
 pub use resource::Resource;
 #[cfg(feature = "trace")]
 pub use trace::{
@@ -21,9 +23,11 @@ pub use trace::{
     evicted_hash_map::EvictedHashMap,
     evicted_queue::EvictedQueue,
     id_generator::IdGenerator,
-    provider::Provider,
-    sampler::Sampler,
+    provider::{Provider, SpanProcessorId},
+    sampler::{RateLimitingSampler, Sampler, SamplingRule},
     span::Span,
+    span_metrics::SpanMetricsProcessor,
     span_processor::{BatchSpanProcessor, SimpleSpanProcessor},
+    tail_sampling::TailSamplingProcessor,
     tracer::Tracer,
 };
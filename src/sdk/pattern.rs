@@ -0,0 +1,31 @@
+//! # Name Patterns
+//!
+//! Glob patterns matching instrument and span names, where `*` matches any
+//! sequence of characters.
+
+/// Match a name against a pattern where `*` matches any sequence of
+/// characters.
+pub(crate) fn matches_pattern(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let mut rest = match name.strip_prefix(first) {
+        Some(rest) => rest,
+        None => return false,
+    };
+    let parts = parts.collect::<Vec<_>>();
+    let last = match parts.split_last() {
+        Some((last, middle)) => {
+            for part in middle {
+                match rest.find(part) {
+                    Some(index) => rest = &rest[index + part.len()..],
+                    None => return false,
+                }
+            }
+            last
+        }
+        // Without wildcards, the pattern must match the whole name
+        None => return rest.is_empty(),
+    };
+
+    rest.ends_with(last)
+}
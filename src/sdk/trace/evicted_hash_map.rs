@@ -57,6 +57,16 @@ impl EvictedHashMap {
         self.map.is_empty()
     }
 
+    /// Returns the value of a key, if present.
+    pub fn get(&self, key: &api::Key) -> Option<&api::Value> {
+        self.map.get(key)
+    }
+
+    /// Returns the number of entries that have been dropped due to capacity.
+    pub fn dropped_count(&self) -> u32 {
+        self.dropped_count
+    }
+
     /// Returns a front-to-back iterator.
     pub fn iter(&self) -> std::collections::hash_map::Iter<api::Key, api::Value> {
         self.map.iter()
@@ -54,6 +54,11 @@ impl<T> EvictedQueue<T> {
     pub fn len(&self) -> usize {
         self.queue.len()
     }
+
+    /// Returns the number of elements that have been dropped due to capacity.
+    pub fn dropped_count(&self) -> u32 {
+        self.dropped_count
+    }
 }
 
 impl<T> IntoIterator for EvictedQueue<T> {
@@ -13,5 +13,9 @@ pub mod id_generator;
 pub mod provider;
 pub mod sampler;
 pub mod span;
+pub mod span_metrics;
 pub mod span_processor;
+pub mod tail_sampling;
+#[cfg(test)]
+pub(crate) mod testing;
 pub mod tracer;
@@ -20,18 +20,64 @@ const DEFAULT_COMPONENT_NAME: &str = "rust.opentelemetry.io/sdk/tracer";
 #[derive(Debug)]
 struct ProviderInner {
     named_tracers: RwLock<HashMap<&'static str, sdk::Tracer>>,
-    processors: Vec<Box<dyn api::SpanProcessor>>,
-    config: sdk::Config,
+    processors: RwLock<Processors>,
+    config: RwLock<Arc<sdk::Config>>,
 }
 
 impl Drop for ProviderInner {
+    /// Shuts down all span processors. Processors such as the
+    /// `BatchSpanProcessor` block the dropping thread until their pending
+    /// spans are exported, so on a current-thread executor shut them down
+    /// asynchronously before the last provider handle is dropped.
     fn drop(&mut self) {
-        for processor in &self.processors {
-            processor.shutdown();
+        if let Ok(processors) = self.processors.read() {
+            for processor in processors.processors.iter() {
+                processor.shutdown();
+            }
         }
     }
 }
 
+/// The span processors of a provider, replaced as a whole when processors
+/// are added or removed. Spans only hold the read lock while cloning the
+/// current list, and call the processors after releasing it.
+#[derive(Debug, Default)]
+struct Processors {
+    ids: Vec<SpanProcessorId>,
+    processors: Arc<Vec<Arc<dyn api::SpanProcessor>>>,
+    next_id: u64,
+}
+
+impl Processors {
+    fn add(&mut self, processor: Arc<dyn api::SpanProcessor>) -> SpanProcessorId {
+        let id = SpanProcessorId(self.next_id);
+        self.next_id += 1;
+        let mut processors = self.processors.as_ref().clone();
+        processors.push(processor);
+        self.processors = Arc::new(processors);
+        self.ids.push(id);
+
+        id
+    }
+
+    fn remove(&mut self, id: SpanProcessorId) -> Option<Arc<dyn api::SpanProcessor>> {
+        let index = self
+            .ids
+            .iter()
+            .position(|processor_id| *processor_id == id)?;
+        let mut processors = self.processors.as_ref().clone();
+        let processor = processors.remove(index);
+        self.processors = Arc::new(processors);
+        self.ids.remove(index);
+
+        Some(processor)
+    }
+}
+
+/// Identifies a span processor added to a running `Provider`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SpanProcessorId(u64);
+
 /// Creator and registry of named `Tracer` instances.
 #[derive(Clone, Debug)]
 pub struct Provider {
@@ -51,13 +97,71 @@ impl Provider {
     }
 
     /// Span processors associated with this provider
-    pub fn span_processors(&self) -> &Vec<Box<dyn api::SpanProcessor>> {
-        &self.inner.processors
+    pub fn span_processors(&self) -> Arc<Vec<Arc<dyn api::SpanProcessor>>> {
+        self.inner
+            .processors
+            .read()
+            .expect("RwLock poisoned")
+            .processors
+            .clone()
+    }
+
+    /// Add a `SpanProcessor` to this running provider, invoked after the
+    /// existing processors for the spans started and ended from now on.
+    pub fn add_span_processor<T: api::SpanProcessor + 'static>(
+        &self,
+        processor: T,
+    ) -> SpanProcessorId {
+        self.inner
+            .processors
+            .write()
+            .expect("RwLock poisoned")
+            .add(Arc::new(processor))
+    }
+
+    /// Remove a processor added with `add_span_processor`, then flush and
+    /// shut it down. Returns `false` if the processor was already removed.
+    ///
+    /// Spans starting or ending concurrently may still be using the list of
+    /// processors taken before the removal, so the processor can receive
+    /// `on_start` and `on_end` calls during and after its shutdown. The
+    /// batch and tail sampling processors drop these spans, the simple
+    /// processor hands them to its shut down exporter.
+    pub fn remove_span_processor(&self, id: SpanProcessorId) -> bool {
+        let processor = self
+            .inner
+            .processors
+            .write()
+            .expect("RwLock poisoned")
+            .remove(id);
+        match processor {
+            Some(processor) => {
+                processor.force_flush();
+                processor.shutdown();
+                true
+            }
+            None => false,
+        }
     }
 
     /// Config associated with this tracer
-    pub fn config(&self) -> &sdk::Config {
-        &self.inner.config
+    pub fn config(&self) -> Arc<sdk::Config> {
+        self.inner.config.read().expect("RwLock poisoned").clone()
+    }
+
+    /// Replace the config of this provider and all of its tracers, for
+    /// example to change the sampler or the span limits of a running
+    /// application. Spans started before the call keep the previous config.
+    pub fn set_config(&self, config: sdk::Config) {
+        *self.inner.config.write().expect("RwLock poisoned") = Arc::new(config);
+    }
+
+    /// Export all ended spans that have not yet been exported by this
+    /// provider's span processors.
+    pub fn force_flush(&self) {
+        for processor in self.span_processors().iter() {
+            processor.force_flush();
+        }
     }
 }
 
@@ -118,6 +222,15 @@ impl Builder {
         Builder { processors, ..self }
     }
 
+    /// Add a `SpanProcessor` to this provider. Processors are invoked in the
+    /// order they were added.
+    pub fn with_span_processor<T: api::SpanProcessor + 'static>(self, processor: T) -> Self {
+        let mut processors = self.processors;
+        processors.push(Box::new(processor));
+
+        Builder { processors, ..self }
+    }
+
     /// The sdk `Config` that this provider will use.
     pub fn with_config(self, config: sdk::Config) -> Self {
         Builder { config, ..self }
@@ -125,12 +238,47 @@ impl Builder {
 
     /// Create a new provider from this configuration.
     pub fn build(self) -> Provider {
+        let mut processors = Processors::default();
+        for processor in self.processors {
+            processors.add(Arc::from(processor));
+        }
+
         Provider {
             inner: Arc::new(ProviderInner {
                 named_tracers: Default::default(),
-                processors: self.processors,
-                config: self.config,
+                processors: RwLock::new(processors),
+                config: RwLock::new(Arc::new(self.config)),
             }),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{Provider as _, Tracer};
+    use crate::sdk::trace::testing::TestProcessor;
+
+    #[test]
+    fn adds_and_removes_span_processors() {
+        let provider = Provider::default();
+        let tracer = provider.get_tracer("test");
+        tracer.start("before");
+
+        let processor = TestProcessor::default();
+        let events = processor.events.clone();
+        let id = provider.add_span_processor(processor);
+        assert_eq!(provider.span_processors().len(), 1);
+        tracer.start("attached");
+
+        assert!(provider.remove_span_processor(id));
+        assert!(!provider.remove_span_processor(id));
+        assert!(provider.span_processors().is_empty());
+        tracer.start("after");
+
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec!["attached", "flush", "shutdown"]
+        );
+    }
+}
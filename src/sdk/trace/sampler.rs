@@ -1,5 +1,15 @@
 //! # Sampler
 use crate::api;
+use crate::sdk::pattern::matches_pattern;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// The key of the attribute recording the effective sampling rate of spans
+/// sampled by a `RateLimitingSampler`.
+pub const SAMPLING_RATE_KEY: &str = "sampling.rate";
+
+const NANOS_PER_SECOND: u64 = 1_000_000_000;
 
 /// Sampling options
 #[derive(Clone, Debug)]
@@ -14,17 +24,276 @@ pub enum Sampler {
     /// sampled, then it's child spans will automatically be sampled. Fractions < 0 are treated as
     /// zero, but spans may still be sampled if their parent is.
     Probability(f64),
+    /// Sample at most a given number of new traces per second. If the parent
+    /// span is sampled, then its child spans will automatically be sampled,
+    /// and child spans of a local parent follow its decision.
+    RateLimited(RateLimitingSampler),
+    /// Delegate to a sampler depending on the parent span: whether there is
+    /// one, whether it is remote, and whether it is sampled.
+    ParentBased {
+        /// Sampler for spans without a parent
+        root: Box<Sampler>,
+        /// Sampler for spans with a sampled remote parent
+        remote_sampled: Box<Sampler>,
+        /// Sampler for spans with a remote parent that is not sampled
+        remote_not_sampled: Box<Sampler>,
+        /// Sampler for spans with a sampled local parent
+        local_sampled: Box<Sampler>,
+        /// Sampler for spans with a local parent that is not sampled
+        local_not_sampled: Box<Sampler>,
+    },
+    /// Delegate to the sampler of the first rule matching the span, or to the
+    /// fallback sampler if no rule matches. Child spans of a local parent
+    /// follow its decision, so that a trace is not split between rules.
+    RuleBased {
+        /// Rules, in the order they are tried
+        rules: Vec<SamplingRule>,
+        /// Sampler for spans matching no rule
+        fallback: Box<Sampler>,
+    },
 }
 
-impl api::Sampler for Sampler {
+impl Sampler {
+    /// A `ParentBased` sampler using `root` for spans without a parent, and
+    /// following the decision of the parent span otherwise.
+    pub fn parent_based(root: Sampler) -> Self {
+        Sampler::ParentBased {
+            root: Box::new(root),
+            remote_sampled: Box::new(Sampler::Always),
+            remote_not_sampled: Box::new(Sampler::Never),
+            local_sampled: Box::new(Sampler::Always),
+            local_not_sampled: Box::new(Sampler::Never),
+        }
+    }
+}
+
+/// Samples at most a given number of new traces per second, with a budget of
+/// one second of traces for bursts.
+///
+/// Sampled root spans are given a `sampling.rate` attribute, the fraction of
+/// root spans sampled during the previous second, which can be used to
+/// weight them in aggregations. Clones share the same budget.
+#[derive(Clone, Debug)]
+pub struct RateLimitingSampler {
+    limiter: Arc<RateLimiter>,
+}
+
+impl RateLimitingSampler {
+    /// Create a new sampler admitting at most `traces_per_second` new
+    /// traces per second. Rates <= 0 never sample new traces.
+    pub fn new(traces_per_second: f64) -> Self {
+        let interval = if traces_per_second > 0.0 {
+            Some((NANOS_PER_SECOND as f64 / traces_per_second).max(1.0) as u64)
+        } else {
+            None
+        };
+
+        RateLimitingSampler {
+            limiter: Arc::new(RateLimiter {
+                origin: Instant::now(),
+                interval,
+                theoretical_arrival: AtomicU64::new(0),
+                window_start: AtomicU64::new(0),
+                seen: AtomicU64::new(0),
+                admitted: AtomicU64::new(0),
+                rate: AtomicU64::new(1f64.to_bits()),
+            }),
+        }
+    }
+}
+
+impl api::Sampler for RateLimitingSampler {
     fn should_sample(
         &self,
         parent_context: Option<&api::SpanContext>,
-        trace_id: api::TraceId,
+        _trace_id: api::TraceId,
         _name: &str,
         _span_kind: &api::SpanKind,
         _attributes: &[api::KeyValue],
         _links: &[api::Link],
+    ) -> api::SamplingResult {
+        // Follow sampled parents and local parents without spending the
+        // budget of new traces
+        if let Some(parent) = parent_context.filter(|ctx| ctx.is_sampled() || !ctx.is_remote()) {
+            let decision = if parent.is_sampled() {
+                api::SamplingDecision::RecordAndSampled
+            } else {
+                api::SamplingDecision::NotRecord
+            };
+            return api::SamplingResult {
+                decision,
+                attributes: Vec::new(),
+            };
+        }
+
+        let now = self.limiter.origin.elapsed().as_nanos() as u64;
+        let admitted = self.limiter.try_admit(now);
+        let rate = self.limiter.record(now, admitted);
+        if admitted {
+            api::SamplingResult {
+                decision: api::SamplingDecision::RecordAndSampled,
+                attributes: vec![api::KeyValue::new(SAMPLING_RATE_KEY, rate)],
+            }
+        } else {
+            api::SamplingResult {
+                decision: api::SamplingDecision::NotRecord,
+                attributes: Vec::new(),
+            }
+        }
+    }
+}
+
+/// A lock-free token bucket, implemented as a generic cell rate algorithm:
+/// the bucket is full when the theoretical arrival time of the next trace is
+/// in the past, and empty when it is one second ahead.
+#[derive(Debug)]
+struct RateLimiter {
+    origin: Instant,
+    // Nanoseconds between two traces, `None` to admit no trace
+    interval: Option<u64>,
+    theoretical_arrival: AtomicU64,
+    window_start: AtomicU64,
+    seen: AtomicU64,
+    admitted: AtomicU64,
+    // Sampling rate of the previous window, as `f64` bits
+    rate: AtomicU64,
+}
+
+impl RateLimiter {
+    /// Take a token, `now` nanoseconds after the origin, if there is one.
+    fn try_admit(&self, now: u64) -> bool {
+        let interval = match self.interval {
+            Some(interval) => interval,
+            None => return false,
+        };
+        let burst = interval.max(NANOS_PER_SECOND);
+        let mut arrival = self.theoretical_arrival.load(Ordering::Relaxed);
+        loop {
+            let next = arrival.max(now).saturating_add(interval);
+            if next - now > burst {
+                return false;
+            }
+            match self.theoretical_arrival.compare_exchange_weak(
+                arrival,
+                next,
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return true,
+                Err(current) => arrival = current,
+            }
+        }
+    }
+
+    /// Count a sampling decision, returning the sampling rate of the previous
+    /// one second window.
+    fn record(&self, now: u64, admitted: bool) -> f64 {
+        let window_start = self.window_start.load(Ordering::Relaxed);
+        if now.saturating_sub(window_start) >= NANOS_PER_SECOND
+            && self
+                .window_start
+                .compare_exchange(window_start, now, Ordering::Relaxed, Ordering::Relaxed)
+                .is_ok()
+        {
+            let seen = self.seen.swap(0, Ordering::Relaxed);
+            let admitted = self.admitted.swap(0, Ordering::Relaxed);
+            if seen > 0 {
+                let rate = admitted as f64 / seen as f64;
+                self.rate.store(rate.to_bits(), Ordering::Relaxed);
+            }
+        }
+
+        self.seen.fetch_add(1, Ordering::Relaxed);
+        if admitted {
+            self.admitted.fetch_add(1, Ordering::Relaxed);
+        }
+        f64::from_bits(self.rate.load(Ordering::Relaxed))
+    }
+}
+
+/// Selects the sampler of spans by name, kind and attributes, for the
+/// `RuleBased` sampler.
+///
+/// A rule matches the spans matching all of its conditions, and a rule
+/// without conditions matches all spans. Only the attributes given when
+/// starting a span are known to the sampler.
+#[derive(Clone, Debug)]
+pub struct SamplingRule {
+    name_pattern: Option<String>,
+    span_kind: Option<api::SpanKind>,
+    attributes: Vec<api::KeyValue>,
+    sampler: Sampler,
+}
+
+impl SamplingRule {
+    /// Create a new rule sampling the matched spans with `sampler`.
+    pub fn new(sampler: Sampler) -> Self {
+        SamplingRule {
+            name_pattern: None,
+            span_kind: None,
+            attributes: Vec::new(),
+            sampler,
+        }
+    }
+
+    /// Only match spans whose name matches `pattern`. The pattern is matched
+    /// against the whole name, and `*` matches any sequence of characters.
+    pub fn with_name_pattern<S: Into<String>>(self, pattern: S) -> Self {
+        SamplingRule {
+            name_pattern: Some(pattern.into()),
+            ..self
+        }
+    }
+
+    /// Only match spans of the given kind.
+    pub fn with_span_kind(self, span_kind: api::SpanKind) -> Self {
+        SamplingRule {
+            span_kind: Some(span_kind),
+            ..self
+        }
+    }
+
+    /// Only match spans with the given attribute. Rules with several
+    /// attributes match spans having all of them.
+    pub fn with_attribute(mut self, attribute: api::KeyValue) -> Self {
+        self.attributes.push(attribute);
+        self
+    }
+
+    /// Whether this rule applies to the given span.
+    pub fn matches(
+        &self,
+        name: &str,
+        span_kind: &api::SpanKind,
+        attributes: &[api::KeyValue],
+    ) -> bool {
+        self.name_pattern
+            .as_ref()
+            .map(|pattern| matches_pattern(pattern, name))
+            .unwrap_or(true)
+            && self
+                .span_kind
+                .as_ref()
+                .map(|kind| kind == span_kind)
+                .unwrap_or(true)
+            && self
+                .attributes
+                .iter()
+                .all(|attribute| attributes.contains(attribute))
+    }
+}
+
+impl api::Sampler for Sampler {
+    // Links are only passed on to delegate samplers
+    #[allow(clippy::only_used_in_recursion)]
+    fn should_sample(
+        &self,
+        parent_context: Option<&api::SpanContext>,
+        trace_id: api::TraceId,
+        name: &str,
+        span_kind: &api::SpanKind,
+        attributes: &[api::KeyValue],
+        links: &[api::Link],
     ) -> api::SamplingResult {
         let decision = match self {
             // Always sample the trace
@@ -55,6 +324,65 @@ impl api::Sampler for Sampler {
                     }
                 }
             }
+            // Admit new traces within the budget
+            Sampler::RateLimited(sampler) => {
+                return sampler.should_sample(
+                    parent_context,
+                    trace_id,
+                    name,
+                    span_kind,
+                    attributes,
+                    links,
+                );
+            }
+            // Delegate depending on the parent span
+            Sampler::ParentBased {
+                root,
+                remote_sampled,
+                remote_not_sampled,
+                local_sampled,
+                local_not_sampled,
+            } => {
+                let delegate = match parent_context {
+                    None => root,
+                    Some(ctx) => match (ctx.is_remote(), ctx.is_sampled()) {
+                        (true, true) => remote_sampled,
+                        (true, false) => remote_not_sampled,
+                        (false, true) => local_sampled,
+                        (false, false) => local_not_sampled,
+                    },
+                };
+                return delegate.should_sample(
+                    parent_context,
+                    trace_id,
+                    name,
+                    span_kind,
+                    attributes,
+                    links,
+                );
+            }
+            // Follow local parents, or delegate to the first matching rule
+            Sampler::RuleBased { rules, fallback } => {
+                match parent_context.filter(|ctx| !ctx.is_remote()) {
+                    Some(parent) if parent.is_sampled() => api::SamplingDecision::RecordAndSampled,
+                    Some(_) => api::SamplingDecision::NotRecord,
+                    None => {
+                        let delegate = rules
+                            .iter()
+                            .find(|rule| rule.matches(name, span_kind, attributes))
+                            .map(|rule| &rule.sampler)
+                            .unwrap_or(fallback);
+                        return delegate.should_sample(
+                            parent_context,
+                            trace_id,
+                            name,
+                            span_kind,
+                            attributes,
+                            links,
+                        );
+                    }
+                }
+            }
         };
 
         api::SamplingResult {
@@ -67,9 +395,13 @@ impl api::Sampler for Sampler {
 
 #[cfg(test)]
 mod tests {
+    use super::*;
     use crate::api::{self, Sampler as _};
-    use crate::sdk::Sampler;
+    use crate::api::{Provider, Span, Tracer};
+    use crate::sdk::{self, Sampler, SamplingRule};
     use rand::Rng;
+    use std::thread;
+    use std::time::Duration;
 
     #[rustfmt::skip]
     fn sampler_data() -> Vec<(&'static str, Sampler, f64, bool, bool)> {
@@ -159,4 +491,227 @@ mod tests {
             );
         }
     }
+
+    fn is_sampled(
+        sampler: &Sampler,
+        parent_context: Option<&api::SpanContext>,
+        name: &str,
+        span_kind: api::SpanKind,
+        attributes: &[api::KeyValue],
+    ) -> bool {
+        sampler
+            .should_sample(
+                parent_context,
+                api::TraceId::from_u128(1),
+                name,
+                &span_kind,
+                attributes,
+                &[],
+            )
+            .decision
+            == api::SamplingDecision::RecordAndSampled
+    }
+
+    #[test]
+    fn parent_based_sampling() {
+        let sampler = Sampler::ParentBased {
+            root: Box::new(Sampler::Always),
+            remote_sampled: Box::new(Sampler::Always),
+            remote_not_sampled: Box::new(Sampler::Always),
+            local_sampled: Box::new(Sampler::Never),
+            local_not_sampled: Box::new(Sampler::Never),
+        };
+        let parent = |sampled: bool, remote: bool| {
+            let trace_flags = if sampled { api::TRACE_FLAG_SAMPLED } else { 0 };
+            api::SpanContext::new(
+                api::TraceId::from_u128(1),
+                api::SpanId::from_u64(1),
+                trace_flags,
+                remote,
+            )
+        };
+        let kind = || api::SpanKind::Internal;
+
+        assert!(is_sampled(&sampler, None, "root", kind(), &[]));
+        assert!(is_sampled(
+            &sampler,
+            Some(&parent(true, true)),
+            "span",
+            kind(),
+            &[]
+        ));
+        assert!(is_sampled(
+            &sampler,
+            Some(&parent(false, true)),
+            "span",
+            kind(),
+            &[]
+        ));
+        assert!(!is_sampled(
+            &sampler,
+            Some(&parent(true, false)),
+            "span",
+            kind(),
+            &[]
+        ));
+        assert!(!is_sampled(
+            &sampler,
+            Some(&parent(false, false)),
+            "span",
+            kind(),
+            &[]
+        ));
+
+        let sampler = Sampler::parent_based(Sampler::Never);
+        assert!(!is_sampled(&sampler, None, "root", kind(), &[]));
+        assert!(is_sampled(
+            &sampler,
+            Some(&parent(true, false)),
+            "span",
+            kind(),
+            &[]
+        ));
+        assert!(!is_sampled(
+            &sampler,
+            Some(&parent(false, true)),
+            "span",
+            kind(),
+            &[]
+        ));
+    }
+
+    #[test]
+    fn rule_based_sampling() {
+        let sampler = Sampler::RuleBased {
+            rules: vec![
+                SamplingRule::new(Sampler::Never).with_name_pattern("/healthz"),
+                SamplingRule::new(Sampler::Always)
+                    .with_name_pattern("/checkout*")
+                    .with_span_kind(api::SpanKind::Server),
+                SamplingRule::new(Sampler::Always)
+                    .with_attribute(api::KeyValue::new("debug", true)),
+            ],
+            fallback: Box::new(Sampler::Never),
+        };
+        let debug = [api::KeyValue::new("debug", true)];
+
+        for (name, span_kind, attributes, expected) in [
+            ("/healthz", api::SpanKind::Server, &debug[..], false),
+            ("/checkout", api::SpanKind::Server, &[][..], true),
+            ("/checkout/cart", api::SpanKind::Server, &[][..], true),
+            ("/checkout", api::SpanKind::Client, &[][..], false),
+            ("/products", api::SpanKind::Server, &debug[..], true),
+            ("/products", api::SpanKind::Server, &[][..], false),
+        ] {
+            assert_eq!(
+                is_sampled(&sampler, None, name, span_kind.clone(), attributes),
+                expected,
+                "{} {:?}",
+                name,
+                span_kind
+            );
+        }
+
+        // Child spans of a local parent follow it, whatever rule they match
+        let parent = |trace_flags: u8| {
+            api::SpanContext::new(
+                api::TraceId::from_u128(1),
+                api::SpanId::from_u64(1),
+                trace_flags,
+                false,
+            )
+        };
+        let kind = || api::SpanKind::Server;
+        let sampled = parent(api::TRACE_FLAG_SAMPLED);
+        assert!(is_sampled(
+            &sampler,
+            Some(&sampled),
+            "/healthz",
+            kind(),
+            &[]
+        ));
+        let not_sampled = parent(0);
+        assert!(!is_sampled(
+            &sampler,
+            Some(&not_sampled),
+            "/checkout",
+            kind(),
+            &[]
+        ));
+    }
+
+    #[test]
+    fn rate_limited_sampling() {
+        let sampler = Sampler::RateLimited(RateLimitingSampler::new(10.0));
+        let kind = || api::SpanKind::Internal;
+        let sampled = (0..100)
+            .filter(|_| is_sampled(&sampler, None, "root", kind(), &[]))
+            .count();
+        // A few more traces may be admitted if the loop takes over 100ms
+        assert!((10..=12).contains(&sampled), "sampled {}", sampled);
+
+        // Sampled parents are followed
+        let parent = api::SpanContext::new(
+            api::TraceId::from_u128(1),
+            api::SpanId::from_u64(1),
+            api::TRACE_FLAG_SAMPLED,
+            true,
+        );
+        assert!(is_sampled(&sampler, Some(&parent), "span", kind(), &[]));
+
+        // Rates <= 0 sample no new trace
+        let sampler = RateLimitingSampler::new(0.0);
+        let result =
+            sampler.should_sample(None, api::TraceId::from_u128(1), "root", &kind(), &[], &[]);
+        assert_eq!(result.decision, api::SamplingDecision::NotRecord);
+    }
+
+    #[test]
+    fn rate_limited_sampling_records_rate() {
+        let sampler = RateLimitingSampler::new(5.0);
+        let rates = || {
+            (0..20)
+                .map(|_| {
+                    sampler.should_sample(
+                        None,
+                        api::TraceId::from_u128(1),
+                        "root",
+                        &api::SpanKind::Internal,
+                        &[],
+                        &[],
+                    )
+                })
+                .filter(|result| result.decision == api::SamplingDecision::RecordAndSampled)
+                .map(|result| result.attributes)
+                .collect::<Vec<_>>()
+        };
+
+        let rate = |rate: f64| vec![api::KeyValue::new(SAMPLING_RATE_KEY, rate)];
+        assert_eq!(rates(), vec![rate(1.0); 5]);
+        thread::sleep(Duration::from_millis(1100));
+        // 5 of the 20 root spans of the previous second were sampled
+        assert_eq!(rates(), vec![rate(0.25); 5]);
+    }
+
+    #[test]
+    fn rate_limited_sampling_with_tracer() {
+        let provider = sdk::Provider::builder()
+            .with_config(sdk::Config {
+                default_sampler: Box::new(Sampler::RateLimited(RateLimitingSampler::new(2.0))),
+                ..Default::default()
+            })
+            .build();
+        let tracer = provider.get_tracer("test");
+
+        // Child spans follow their local root, and do not spend the budget
+        let recording = (0..5)
+            .map(|_| {
+                let span = tracer.start("root");
+                let child = tracer.with_span(span.clone(), |_| tracer.start("child"));
+                assert_eq!(span.is_recording(), child.is_recording());
+                span.is_recording()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(recording, vec![true, true, false, false, false]);
+    }
 }
@@ -15,7 +15,7 @@ use std::time::SystemTime;
 /// Single operation within a trace.
 #[derive(Clone, Debug)]
 pub struct Span {
-    id: api::SpanId,
+    span_context: api::SpanContext,
     inner: Arc<SpanInner>,
 }
 
@@ -28,12 +28,12 @@ struct SpanInner {
 
 impl Span {
     pub(crate) fn new(
-        id: api::SpanId,
+        span_context: api::SpanContext,
         data: Option<exporter::trace::SpanData>,
         tracer: sdk::Tracer,
     ) -> Self {
         Span {
-            id,
+            span_context,
             inner: Arc::new(SpanInner {
                 data: data.map(Mutex::new),
                 tracer,
@@ -41,17 +41,6 @@ impl Span {
         }
     }
 
-    /// Operate on reference to span inner
-    fn with_data<T, F>(&self, f: F) -> Option<T>
-    where
-        F: FnOnce(&exporter::trace::SpanData) -> T,
-    {
-        self.inner
-            .data
-            .as_ref()
-            .and_then(|inner| inner.lock().ok().map(|span_data| f(&span_data)))
-    }
-
     /// Operate on mutable reference to span inner
     fn with_data_mut<T, F>(&self, f: F) -> Option<T>
     where
@@ -84,10 +73,7 @@ impl api::Span for Span {
 
     /// Returns the `SpanContext` for the given `Span`.
     fn span_context(&self) -> api::SpanContext {
-        self.with_data(|data| data.span_context.clone())
-            .unwrap_or_else(|| {
-                api::SpanContext::new(api::TraceId::invalid(), api::SpanId::invalid(), 0, false)
-            })
+        self.span_context.clone()
     }
 
     /// Returns true if this `Span` is recording information like events with the `add_event`
@@ -140,7 +126,7 @@ impl Drop for SpanInner {
                     inner.end_time = SystemTime::now();
                 }
                 let exportable_span = Arc::new(inner.clone());
-                for processor in self.tracer.provider().span_processors() {
+                for processor in self.tracer.provider().span_processors().iter() {
                     processor.on_end(exportable_span.clone())
                 }
             }
@@ -0,0 +1,435 @@
+//! # Span Metrics Processor
+//!
+//! The [`SpanMetricsProcessor`] derives request, error and duration (RED)
+//! metrics from ended spans, so requests traced with a span do not need
+//! separate metric instrumentation. Metrics are recorded with any
+//! `api::Meter`, and labeled with the span name, the span kind, and the
+//! configured span attributes.
+//!
+//! Processors only see the spans the sampler records, see
+//! [`SpanMetricsProcessor`] for counting unsampled spans.
+//!
+//! ```
+//! use opentelemetry::{api, sdk};
+//!
+//! let meter = sdk::Meter::new("spans");
+//! let processor = sdk::SpanMetricsProcessor::builder(meter)
+//!     .with_attribute_keys(vec![api::Key::new("http.method")])
+//!     .build();
+//! let provider = sdk::Provider::builder()
+//!     .with_span_processor(processor)
+//!     .build();
+//! ```
+//!
+//! [`SpanMetricsProcessor`]: struct.SpanMetricsProcessor.html
+use crate::api::{self, Counter, Measure, Meter};
+use crate::exporter::trace::SpanData;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+
+/// Default prefix of the names of the derived metrics.
+const DEFAULT_PREFIX: &str = "span";
+
+/// Default upper bounds of the span duration histogram buckets, in seconds.
+const DEFAULT_DURATION_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// The maximum number of label sets cached by a processor. The labels of
+/// further spans are built for every span.
+const MAX_CACHED_LABEL_SETS: usize = 1000;
+
+/// A `SpanProcessor` recording the number of ended spans, the number of
+/// spans that ended with an error status, and the span durations.
+///
+/// With the default `span` prefix, the metrics are:
+///
+/// - `span.requests`: a counter of ended spans.
+/// - `span.errors`: a counter of spans ended with a status other than `OK`.
+/// - `span.duration`: a histogram of span durations in seconds, with buckets
+///   from 5ms to 10s unless configured otherwise.
+///
+/// # Sampling
+///
+/// Span processors are only called for spans the sampler records. With a
+/// sampler dropping a fraction of the spans, such as `Sampler::Probability`,
+/// the metrics only count the recorded fraction of the requests. To count all
+/// requests, configure a sampler that records every span, returning
+/// `SamplingDecision::Record` for the spans it does not sample. Exporting
+/// span processors skip these unsampled spans.
+pub struct SpanMetricsProcessor<M: Meter> {
+    meter: M,
+    attribute_keys: Vec<api::Key>,
+    requests: M::I64Counter,
+    errors: M::I64Counter,
+    duration: M::F64Measure,
+    label_sets: RwLock<LabelSets<M::LabelSet>>,
+}
+
+/// The label sets of previous spans, by span name.
+#[derive(Debug)]
+struct LabelSets<L> {
+    by_name: HashMap<String, Vec<CachedLabelSet<L>>>,
+    len: usize,
+}
+
+/// A label set of the spans with a kind and the values of the configured
+/// attributes.
+#[derive(Debug)]
+struct CachedLabelSet<L> {
+    span_kind: api::SpanKind,
+    attribute_values: Vec<Option<api::Value>>,
+    labels: L,
+}
+
+impl<M: Meter> fmt::Debug for SpanMetricsProcessor<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SpanMetricsProcessor")
+            .field("attribute_keys", &self.attribute_keys)
+            .finish()
+    }
+}
+
+impl<M: Meter> SpanMetricsProcessor<M> {
+    /// Create a new builder for a processor recording metrics with `meter`.
+    pub fn builder(meter: M) -> SpanMetricsProcessorBuilder<M> {
+        SpanMetricsProcessorBuilder {
+            meter,
+            prefix: DEFAULT_PREFIX.to_string(),
+            attribute_keys: Vec::new(),
+            duration_buckets: DEFAULT_DURATION_BUCKETS.to_vec(),
+        }
+    }
+
+    /// The labels of the metrics derived from a span.
+    fn labels(&self, span: &SpanData) -> M::LabelSet {
+        let mut key_values = vec![
+            api::KeyValue::new("span.name", span.name.clone()),
+            api::KeyValue::new("span.kind", span.span_kind.to_string()),
+        ];
+        for key in &self.attribute_keys {
+            if let Some(value) = span.attributes.get(key) {
+                key_values.push(api::KeyValue::new(key.clone(), value.clone()));
+            }
+        }
+
+        self.meter.labels(key_values)
+    }
+
+    /// Record the measurements of a span with its labels, built once for
+    /// each span name, kind and the values of the configured attributes.
+    fn record(&self, span: &SpanData, measurements: Vec<api::Measurement<M::LabelSet>>) {
+        let matches = |cached: &&CachedLabelSet<M::LabelSet>| {
+            cached.span_kind == span.span_kind
+                && cached
+                    .attribute_values
+                    .iter()
+                    .zip(&self.attribute_keys)
+                    .all(|(value, key)| value.as_ref() == span.attributes.get(key))
+        };
+        if let Ok(label_sets) = self.label_sets.read() {
+            if let Some(cached) = label_sets
+                .by_name
+                .get(&span.name)
+                .and_then(|cached| cached.iter().find(matches))
+            {
+                self.meter.record_batch(&cached.labels, measurements);
+                return;
+            }
+        }
+
+        let labels = self.labels(span);
+        self.meter.record_batch(&labels, measurements);
+        if let Ok(mut label_sets) = self.label_sets.write() {
+            if label_sets.len < MAX_CACHED_LABEL_SETS {
+                let attribute_values = self
+                    .attribute_keys
+                    .iter()
+                    .map(|key| span.attributes.get(key).cloned())
+                    .collect();
+                label_sets
+                    .by_name
+                    .entry(span.name.clone())
+                    .or_insert_with(Vec::new)
+                    .push(CachedLabelSet {
+                        span_kind: span.span_kind.clone(),
+                        attribute_values,
+                        labels,
+                    });
+                label_sets.len += 1;
+            }
+        }
+    }
+}
+
+impl<M> api::SpanProcessor for SpanMetricsProcessor<M>
+where
+    M: Meter + Send + Sync,
+    M::LabelSet: Send + Sync,
+    M::I64Counter: Send + Sync,
+    M::F64Measure: Send + Sync,
+{
+    fn on_start(&self, _span: Arc<SpanData>) {
+        // Ignored
+    }
+
+    fn on_end(&self, span: Arc<SpanData>) {
+        let duration = span
+            .end_time
+            .duration_since(span.start_time)
+            .unwrap_or_default();
+        let mut measurements = vec![
+            self.requests.measurement(1),
+            self.duration.measurement(duration.as_secs_f64()),
+        ];
+        if span.status_code != api::StatusCode::OK {
+            measurements.push(self.errors.measurement(1));
+        }
+
+        self.record(&span, measurements);
+    }
+
+    fn force_flush(&self) {
+        // Ignored, metrics are exported by the meter
+    }
+
+    fn shutdown(&self) {
+        // Ignored
+    }
+}
+
+/// Builder for configuring a `SpanMetricsProcessor`.
+#[derive(Debug)]
+pub struct SpanMetricsProcessorBuilder<M> {
+    meter: M,
+    prefix: String,
+    attribute_keys: Vec<api::Key>,
+    duration_buckets: Vec<f64>,
+}
+
+impl<M: Meter> SpanMetricsProcessorBuilder<M> {
+    /// Assign the prefix of the metric names, separated by a dot. Defaults to
+    /// `span`.
+    pub fn with_prefix<T: Into<String>>(self, prefix: T) -> Self {
+        SpanMetricsProcessorBuilder {
+            prefix: prefix.into(),
+            ..self
+        }
+    }
+
+    /// Assign the keys of the span attributes added to the metric labels.
+    /// Spans without an attribute are recorded without its label.
+    pub fn with_attribute_keys(self, attribute_keys: Vec<api::Key>) -> Self {
+        SpanMetricsProcessorBuilder {
+            attribute_keys,
+            ..self
+        }
+    }
+
+    /// Assign the upper bounds of the span duration histogram buckets, in
+    /// seconds. Defaults to buckets from 5ms to 10s.
+    pub fn with_duration_buckets(self, duration_buckets: Vec<f64>) -> Self {
+        SpanMetricsProcessorBuilder {
+            duration_buckets,
+            ..self
+        }
+    }
+
+    /// Create the processor and its instruments.
+    pub fn build(self) -> SpanMetricsProcessor<M> {
+        let name = |suffix| format!("{}.{}", self.prefix, suffix);
+        let requests = self.meter.new_i64_counter(
+            name("requests"),
+            api::MetricOptions::default().with_description("Number of ended spans"),
+        );
+        let errors = self.meter.new_i64_counter(
+            name("errors"),
+            api::MetricOptions::default().with_description("Number of spans ended with an error"),
+        );
+        let duration = self.meter.new_f64_measure(
+            name("duration"),
+            api::MetricOptions::default()
+                .with_description("Duration of spans")
+                .with_unit(api::Unit::new("s"))
+                .with_buckets(self.duration_buckets),
+        );
+
+        SpanMetricsProcessor {
+            meter: self.meter,
+            attribute_keys: self.attribute_keys,
+            requests,
+            errors,
+            duration,
+            label_sets: RwLock::new(LabelSets {
+                by_name: HashMap::new(),
+                len: 0,
+            }),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "metrics"))]
+mod tests {
+    use super::*;
+    use crate::api::{Provider, Span, Tracer};
+    use crate::exporter::metrics::{Aggregation, Number};
+    use crate::sdk;
+
+    #[test]
+    fn records_red_metrics_of_ended_spans() {
+        let meter = sdk::Meter::new("spans");
+        let processor = SpanMetricsProcessor::builder(meter.clone())
+            .with_attribute_keys(vec![api::Key::new("http.method")])
+            .build();
+        let provider = sdk::Provider::builder()
+            .with_span_processor(processor)
+            .build();
+        let tracer = provider.get_tracer("test");
+
+        for status in &[api::StatusCode::OK, api::StatusCode::Internal] {
+            let span = tracer
+                .span_builder("request")
+                .with_kind(api::SpanKind::Server)
+                .with_attributes(vec![
+                    api::KeyValue::new("http.method", "GET"),
+                    api::KeyValue::new("http.path", "/"),
+                ])
+                .start(&tracer);
+            span.set_status(status.clone(), String::new());
+            span.end();
+        }
+        tracer.start("internal").end();
+
+        let labels = meter.labels(vec![
+            api::KeyValue::new("span.name", "request"),
+            api::KeyValue::new("span.kind", "server"),
+            api::KeyValue::new("http.method", "GET"),
+        ]);
+        let internal = meter.labels(vec![
+            api::KeyValue::new("span.name", "internal"),
+            api::KeyValue::new("span.kind", "internal"),
+        ]);
+        let records = meter.collect().records;
+        let aggregation = |name: &str, labels: &sdk::LabelSet| {
+            records
+                .iter()
+                .find(|record| record.descriptor.name == name && &record.labels == labels)
+                .map(|record| record.aggregation.clone())
+        };
+        assert_eq!(
+            aggregation("span.requests", &labels),
+            Some(Aggregation::Sum(Number::I64(2)))
+        );
+        assert_eq!(
+            aggregation("span.errors", &labels),
+            Some(Aggregation::Sum(Number::I64(1)))
+        );
+        assert_eq!(
+            aggregation("span.requests", &internal),
+            Some(Aggregation::Sum(Number::I64(1)))
+        );
+        assert_eq!(aggregation("span.errors", &internal), None);
+        match aggregation("span.duration", &labels) {
+            Some(Aggregation::Histogram {
+                boundaries, counts, ..
+            }) => {
+                assert_eq!(boundaries, DEFAULT_DURATION_BUCKETS);
+                assert_eq!(counts.iter().sum::<u64>(), 2);
+            }
+            other => panic!("unexpected aggregation {:?}", other),
+        }
+    }
+
+    #[test]
+    fn records_durations_in_configured_buckets() {
+        let meter = sdk::Meter::new("spans");
+        let processor = SpanMetricsProcessor::builder(meter.clone())
+            .with_duration_buckets(vec![60.0])
+            .build();
+        let provider = sdk::Provider::builder()
+            .with_span_processor(processor)
+            .build();
+        provider.get_tracer("test").start("request").end();
+
+        let records = meter.collect().records;
+        let record = records
+            .iter()
+            .find(|record| record.descriptor.name == "span.duration")
+            .unwrap();
+        match &record.aggregation {
+            Aggregation::Histogram {
+                boundaries, counts, ..
+            } => {
+                assert_eq!(boundaries, &[60.0]);
+                assert_eq!(counts, &[1, 0]);
+            }
+            other => panic!("unexpected aggregation {:?}", other),
+        }
+    }
+
+    /// Records every span, without sampling any.
+    #[derive(Debug)]
+    struct RecordOnly;
+
+    impl api::Sampler for RecordOnly {
+        fn should_sample(
+            &self,
+            _parent_context: Option<&api::SpanContext>,
+            _trace_id: api::TraceId,
+            _name: &str,
+            _span_kind: &api::SpanKind,
+            _attributes: &[api::KeyValue],
+            _links: &[api::Link],
+        ) -> api::SamplingResult {
+            api::SamplingResult {
+                decision: api::SamplingDecision::Record,
+                attributes: Vec::new(),
+            }
+        }
+    }
+
+    #[test]
+    fn records_unsampled_spans_and_caches_their_labels() {
+        let meter = sdk::Meter::new("spans");
+        let processor = Arc::new(
+            SpanMetricsProcessor::builder(meter.clone())
+                .with_attribute_keys(vec![api::Key::new("http.method")])
+                .build(),
+        );
+        let provider = sdk::Provider::builder()
+            .with_config(sdk::Config {
+                default_sampler: Box::new(RecordOnly),
+                ..Default::default()
+            })
+            .with_span_processor(processor.clone())
+            .build();
+        let tracer = provider.get_tracer("test");
+
+        for method in &["GET", "GET", "POST"] {
+            let span = tracer
+                .span_builder("request")
+                .with_attributes(vec![api::KeyValue::new("http.method", *method)])
+                .start(&tracer);
+            assert!(!span.span_context().is_sampled());
+            span.end();
+        }
+        tracer.start("request").end();
+
+        let label_sets = processor.label_sets.read().unwrap();
+        assert_eq!(label_sets.len, 3);
+        assert_eq!(label_sets.by_name["request"].len(), 3);
+        let get = meter.labels(vec![
+            api::KeyValue::new("span.name", "request"),
+            api::KeyValue::new("span.kind", "internal"),
+            api::KeyValue::new("http.method", "GET"),
+        ]);
+        let requests = meter
+            .collect()
+            .records
+            .into_iter()
+            .find(|record| record.descriptor.name == "span.requests" && record.labels == get)
+            .map(|record| record.aggregation);
+        assert_eq!(requests, Some(Aggregation::Sum(Number::I64(2))));
+    }
+}
@@ -55,6 +55,10 @@
 //! can pass in their respective `spawn` and `interval` functions to have batching performed in
 //! those contexts.
 //!
+//! The batch processor awaits exports through the [`AsyncSpanExporter`] interface so that
+//! exporters using non-blocking clients do not block the executor. Synchronous exporters can be
+//! used by wrapping them in a [`SyncSpanExporterAdapter`].
+//!
 //! ```
 //! use futures::{stream};
 //! use opentelemetry::{api, sdk, global};
@@ -81,23 +85,52 @@
 //! }
 //! ```
 //!
+//! #### Retrying failed exports:
+//!
+//! Batches that fail with [`ExportResult::FailedRetryable`] are re-queued and
+//! retried on later ticks with exponential backoff, so a failing exporter
+//! never blocks new spans from being buffered. Retries are only attempted on
+//! scheduled ticks, so backoffs shorter than the scheduled delay are rounded
+//! up to the next tick.
+//!
+//! ```
+//! use opentelemetry::{api, sdk};
+//! use std::time::Duration;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let batch = sdk::BatchSpanProcessor::builder(api::NoopSpanExporter {}, tokio::spawn, tokio::time::interval)
+//!     .with_max_export_attempts(3)
+//!     .with_initial_backoff(Duration::from_secs(1))
+//!     .with_max_backoff(Duration::from_secs(10))
+//!     .with_retry_deadline(Duration::from_secs(30))
+//!     .build();
+//! # }
+//! ```
+//!
 //! [`is_recording`]: ../../../api/trace/span/trait.Span.html#tymethod.is_recording
 //! [`Provider`]: ../../../api/trace/provider/trait.Provider.html
 //! [`Tracer`]: ../../../api/trace/tracer/trait.Tracer.html
 //! [`SpanProcessor`]: ../../../api/trace/span_processor/trait.SpanProcessor.html
 //! [`SimpleSpanProcessor`]: struct.SimpleSpanProcessor.html
 //! [`BatchSpanProcessor`]: struct.BatchSpanProcessor.html
+//! [`AsyncSpanExporter`]: ../../../exporter/trace/trait.AsyncSpanExporter.html
+//! [`SyncSpanExporterAdapter`]: ../../../exporter/trace/struct.SyncSpanExporterAdapter.html
+//! [`ExportResult::FailedRetryable`]: ../../../exporter/trace/enum.ExportResult.html#variant.FailedRetryable
 //! [`executor`]: https://docs.rs/futures/0.3.4/futures/executor/index.html
 //! [`tokio`]: https://tokio.rs
 //! [`async-std`]: https://async.rs
 use crate::{api, exporter};
 use futures::{
-    channel::mpsc,
+    channel::{mpsc, oneshot},
+    future::BoxFuture,
     task::{Context, Poll},
     Future, Stream, StreamExt,
 };
+use rand::Rng;
+use std::collections::VecDeque;
 use std::pin::Pin;
-use std::sync::{Arc, Mutex};
+use std::sync::{mpsc as std_mpsc, Arc, Mutex};
 use std::time;
 
 /// A [`SpanProcessor`] that exports synchronously when spans are finished.
@@ -125,6 +158,10 @@ impl api::SpanProcessor for SimpleSpanProcessor {
         }
     }
 
+    fn force_flush(&self) {
+        // Ignored since all spans in Simple Processor will be exported as they ended.
+    }
+
     fn shutdown(&self) {
         self.exporter.shutdown();
     }
@@ -133,10 +170,79 @@ impl api::SpanProcessor for SimpleSpanProcessor {
 /// A [`SpanProcessor`] that asynchronously buffers finished spans and reports
 /// them at a preconfigured interval.
 ///
+/// `force_flush` and `shutdown`, including the `shutdown` called when the
+/// [`Provider`] is dropped, block the calling thread until the worker has
+/// exported all pending spans or the flush timeout elapses. If they are
+/// called from the only thread of a current-thread executor that also runs
+/// the worker, the worker cannot make progress: the call stalls for the
+/// full timeout and the pending spans are lost. On such executors await
+/// [`force_flush_async`] or [`shutdown_async`] instead, for example on a
+/// processor shared with the provider through an `Arc`, before the provider
+/// is dropped.
+///
 /// [`SpanProcessor`]: ../../../api/trace/span_processor/trait.SpanProcessor.html
+/// [`Provider`]: struct.Provider.html
+/// [`force_flush_async`]: struct.BatchSpanProcessor.html#method.force_flush_async
+/// [`shutdown_async`]: struct.BatchSpanProcessor.html#method.shutdown_async
 #[derive(Debug)]
 pub struct BatchSpanProcessor {
     message_sender: Mutex<mpsc::Sender<BatchMessage>>,
+    flush_timeout: time::Duration,
+}
+
+impl BatchSpanProcessor {
+    /// Export all pending spans without blocking the calling thread. The
+    /// returned future resolves once the worker has exported them.
+    pub fn force_flush_async(&self) -> BoxFuture<'static, ()> {
+        self.send_async(BatchMessage::Flush)
+    }
+
+    /// Export all pending spans and shut down the exporter without blocking
+    /// the calling thread. The returned future resolves once the worker has
+    /// finished, after which the blocking `shutdown` returns immediately.
+    pub fn shutdown_async(&self) -> BoxFuture<'static, ()> {
+        self.send_async(BatchMessage::Shutdown)
+    }
+
+    /// Send a control message to the worker.
+    fn send<F>(&self, message: F, done: Done) -> bool
+    where
+        F: FnOnce(Done) -> BatchMessage,
+    {
+        // A fresh sender always has a slot in the channel, so control messages
+        // are not dropped when the queue is full of spans.
+        match self.message_sender.lock() {
+            Ok(sender) => sender.clone().try_send(message(done)).is_ok(),
+            Err(_) => false,
+        }
+    }
+
+    /// Send a control message to the worker and block until it has been
+    /// handled or the flush timeout has elapsed.
+    fn send_and_wait<F>(&self, message: F)
+    where
+        F: FnOnce(Done) -> BatchMessage,
+    {
+        let (done_sender, done_receiver) = std_mpsc::sync_channel(1);
+        if self.send(message, Done::Blocking(done_sender)) {
+            let _ = done_receiver.recv_timeout(self.flush_timeout);
+        }
+    }
+
+    /// Send a control message to the worker, resolving once it has been
+    /// handled.
+    fn send_async<F>(&self, message: F) -> BoxFuture<'static, ()>
+    where
+        F: FnOnce(Done) -> BatchMessage,
+    {
+        let (done_sender, done_receiver) = oneshot::channel();
+        let sent = self.send(message, Done::Async(done_sender));
+        Box::pin(async move {
+            if sent {
+                let _ = done_receiver.await;
+            }
+        })
+    }
 }
 
 impl api::SpanProcessor for BatchSpanProcessor {
@@ -150,10 +256,12 @@ impl api::SpanProcessor for BatchSpanProcessor {
         }
     }
 
+    fn force_flush(&self) {
+        self.send_and_wait(BatchMessage::Flush);
+    }
+
     fn shutdown(&self) {
-        if let Ok(mut sender) = self.message_sender.try_lock() {
-            let _ = sender.try_send(BatchMessage::Shutdown);
-        }
+        self.send_and_wait(BatchMessage::Shutdown);
     }
 }
 
@@ -164,44 +272,149 @@ impl api::SpanProcessor for BatchSpanProcessor {
 /// process the work in the background without requiring dedicated system threads.
 #[allow(missing_debug_implementations)]
 pub struct BatchSpanProcessorWorker {
-    exporter: Box<dyn exporter::trace::SpanExporter>,
-    messages: Pin<Box<dyn Stream<Item = BatchMessage> + Send>>,
-    config: BatchConfig,
-    buffer: Vec<Arc<exporter::trace::SpanData>>,
+    worker: Pin<Box<dyn Future<Output = ()> + Send>>,
 }
 
 impl Future for BatchSpanProcessorWorker {
     type Output = ();
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        loop {
-            match futures::ready!(self.messages.poll_next_unpin(cx)) {
+        self.worker.as_mut().poll(cx)
+    }
+}
+
+/// State owned by the worker future.
+#[derive(Debug)]
+struct WorkerState {
+    exporter: Box<dyn exporter::trace::AsyncSpanExporter>,
+    config: BatchConfig,
+    buffer: Vec<Arc<exporter::trace::SpanData>>,
+    retries: VecDeque<RetryBatch>,
+}
+
+/// A batch that failed to export with a retryable error.
+#[derive(Debug)]
+struct RetryBatch {
+    spans: Vec<Arc<exporter::trace::SpanData>>,
+    attempts: usize,
+    next_attempt: time::Instant,
+    deadline: time::Instant,
+}
+
+impl WorkerState {
+    /// Process messages until the processor is shut down. New spans keep
+    /// queueing in the message channel while an export is awaited.
+    async fn run(mut self, mut messages: Pin<Box<dyn Stream<Item = BatchMessage> + Send>>) {
+        while let Some(message) = messages.next().await {
+            match message {
                 // Span has finished, add to buffer of pending spans.
-                Some(BatchMessage::ExportSpan(span)) => {
+                BatchMessage::ExportSpan(span) => {
                     if self.buffer.len() < self.config.max_queue_size {
                         self.buffer.push(span);
                     }
                 }
                 // Span batch interval time reached, export current spans.
-                Some(BatchMessage::Tick) => {
-                    if !self.buffer.is_empty() {
-                        let mut spans = std::mem::replace(&mut self.buffer, Vec::new());
-                        while !spans.is_empty() {
-                            let batch_idx = spans
-                                .len()
-                                .saturating_sub(self.config.max_export_batch_size);
-                            let batch = spans.split_off(batch_idx);
-                            self.exporter.export(batch);
-                        }
-                    }
+                BatchMessage::Tick => {
+                    let now = time::Instant::now();
+                    self.export_retries(now).await;
+                    self.export_buffer(now).await;
+                }
+                // Flush requested, export all pending spans and notify the caller.
+                BatchMessage::Flush(done) => {
+                    self.flush().await;
+                    done.notify();
                 }
-                // Stream has terminated or processor is shutdown, return to finish execution.
-                None | Some(BatchMessage::Shutdown) => {
+                // Processor is shutdown, export all pending spans before finishing execution.
+                BatchMessage::Shutdown(done) => {
+                    self.flush().await;
                     self.exporter.shutdown();
-                    return Poll::Ready(());
+                    done.notify();
+                    return;
                 }
             }
         }
+
+        // Stream has terminated, return to finish execution.
+        self.exporter.shutdown();
+    }
+
+    /// Export a batch, re-queueing it if the export failed with a retryable
+    /// error and the retry policy allows another attempt.
+    async fn export_batch(
+        &mut self,
+        spans: Vec<Arc<exporter::trace::SpanData>>,
+        attempts: usize,
+        deadline: time::Instant,
+    ) {
+        let attempts = attempts + 1;
+        if attempts >= self.config.max_export_attempts {
+            self.exporter.export(spans).await;
+            return;
+        }
+
+        let result = self.exporter.export(spans.clone()).await;
+        if result != exporter::trace::ExportResult::FailedRetryable {
+            return;
+        }
+
+        let next_attempt = time::Instant::now() + self.config.backoff(attempts);
+        if next_attempt > deadline {
+            return;
+        }
+        self.retries.push_back(RetryBatch {
+            spans,
+            attempts,
+            next_attempt,
+            deadline,
+        });
+
+        // Retried spans are bounded like buffered spans, dropping the oldest
+        // batches first.
+        let mut queued: usize = self.retries.iter().map(|retry| retry.spans.len()).sum();
+        while queued > self.config.max_queue_size {
+            match self.retries.pop_front() {
+                Some(retry) => queued -= retry.spans.len(),
+                None => break,
+            }
+        }
+    }
+
+    /// Export all buffered spans in batches of at most `max_export_batch_size`.
+    async fn export_buffer(&mut self, now: time::Instant) {
+        let deadline = now + self.config.retry_deadline;
+        let mut spans = std::mem::take(&mut self.buffer);
+        while !spans.is_empty() {
+            let batch_idx = spans
+                .len()
+                .saturating_sub(self.config.max_export_batch_size);
+            let batch = spans.split_off(batch_idx);
+            self.export_batch(batch, 0, deadline).await;
+        }
+    }
+
+    /// Export all buffered spans and retry all failed batches immediately.
+    async fn flush(&mut self) {
+        let now = time::Instant::now();
+        for retry in self.retries.iter_mut() {
+            retry.next_attempt = now;
+        }
+        self.export_retries(now).await;
+        self.export_buffer(now).await;
+    }
+
+    /// Retry previously failed batches whose backoff has elapsed.
+    async fn export_retries(&mut self, now: time::Instant) {
+        let pending = std::mem::take(&mut self.retries);
+        for retry in pending {
+            if retry.deadline < now {
+                continue;
+            } else if retry.next_attempt <= now {
+                self.export_batch(retry.spans, retry.attempts, retry.deadline)
+                    .await;
+            } else {
+                self.retries.push_back(retry);
+            }
+        }
     }
 }
 
@@ -209,12 +422,33 @@ impl Future for BatchSpanProcessorWorker {
 enum BatchMessage {
     ExportSpan(Arc<exporter::trace::SpanData>),
     Tick,
-    Shutdown,
+    Flush(Done),
+    Shutdown(Done),
+}
+
+/// Notifies the caller of a flush or shutdown that the worker handled it.
+#[derive(Debug)]
+enum Done {
+    Blocking(std_mpsc::SyncSender<()>),
+    Async(oneshot::Sender<()>),
+}
+
+impl Done {
+    fn notify(self) {
+        match self {
+            Done::Blocking(sender) => {
+                let _ = sender.send(());
+            }
+            Done::Async(sender) => {
+                let _ = sender.send(());
+            }
+        }
+    }
 }
 
 impl BatchSpanProcessor {
     pub(crate) fn new<S, SO, I, IS, ISI>(
-        exporter: Box<dyn exporter::trace::SpanExporter>,
+        exporter: Box<dyn exporter::trace::AsyncSpanExporter>,
         spawn: S,
         interval: I,
         config: BatchConfig,
@@ -225,19 +459,25 @@ impl BatchSpanProcessor {
         IS: Stream<Item = ISI> + Send + 'static,
     {
         let (message_sender, message_receiver) = mpsc::channel(config.max_queue_size);
+        let flush_timeout = config.flush_timeout;
         let ticker = interval(config.scheduled_delay).map(|_| BatchMessage::Tick);
 
         // Spawn worker process via user-defined spawn function.
-        spawn(BatchSpanProcessorWorker {
+        let state = WorkerState {
             exporter,
-            messages: Box::pin(futures::stream::select(message_receiver, ticker)),
             config,
             buffer: Vec::new(),
+            retries: VecDeque::new(),
+        };
+        let messages = Box::pin(futures::stream::select(message_receiver, ticker));
+        spawn(BatchSpanProcessorWorker {
+            worker: Box::pin(state.run(messages)),
         });
 
         // Return batch processor with link to worker
         BatchSpanProcessor {
             message_sender: Mutex::new(message_sender),
+            flush_timeout,
         }
     }
 
@@ -248,7 +488,7 @@ impl BatchSpanProcessor {
         interval: I,
     ) -> BatchSpanProcessorBuilder<E, S, I>
     where
-        E: exporter::trace::SpanExporter,
+        E: exporter::trace::AsyncSpanExporter,
         S: Fn(BatchSpanProcessorWorker) -> SO,
         I: Fn(time::Duration) -> IO,
     {
@@ -277,6 +517,51 @@ pub struct BatchConfig {
     /// of spans one batch after the other without any delay. The default value
     /// is 512.
     max_export_batch_size: usize,
+
+    /// The maximum number of times a batch is exported, including the first
+    /// attempt, while the exporter returns `FailedRetryable`. A value of 1
+    /// disables retries. The default value is 5.
+    max_export_attempts: usize,
+
+    /// The delay before the first retry of a failed batch, doubled for every
+    /// following retry. The default value is 1 second.
+    initial_backoff: time::Duration,
+
+    /// The maximum delay between two retries of a failed batch. The default
+    /// value is 30 seconds.
+    max_backoff: time::Duration,
+
+    /// The fraction of each backoff that is randomized to avoid retries of
+    /// many processes lining up, between 0.0 and 1.0. The default value is
+    /// 0.2.
+    backoff_jitter: f64,
+
+    /// The maximum time after the first attempt during which a batch may be
+    /// retried, after which it is dropped. The default value is 60 seconds.
+    retry_deadline: time::Duration,
+
+    /// The maximum time `force_flush` and `shutdown` block waiting for the
+    /// worker to export all pending spans. The default value is 30 seconds.
+    flush_timeout: time::Duration,
+}
+
+impl BatchConfig {
+    /// The delay before the given retry attempt of a failed batch.
+    fn backoff(&self, attempts: usize) -> time::Duration {
+        let exponent = attempts.saturating_sub(1).min(31) as u32;
+        let backoff = self
+            .initial_backoff
+            .checked_mul(1 << exponent)
+            .unwrap_or(self.max_backoff)
+            .min(self.max_backoff);
+
+        if self.backoff_jitter > 0.0 {
+            let jitter = rand::thread_rng().gen_range(0.0, self.backoff_jitter);
+            backoff.mul_f64(1.0 - jitter)
+        } else {
+            backoff
+        }
+    }
 }
 
 impl Default for BatchConfig {
@@ -285,6 +570,12 @@ impl Default for BatchConfig {
             max_queue_size: 2048,
             scheduled_delay: time::Duration::from_secs(5),
             max_export_batch_size: 512,
+            max_export_attempts: 5,
+            initial_backoff: time::Duration::from_secs(1),
+            max_backoff: time::Duration::from_secs(30),
+            backoff_jitter: 0.2,
+            retry_deadline: time::Duration::from_secs(60),
+            flush_timeout: time::Duration::from_secs(30),
         }
     }
 }
@@ -302,7 +593,7 @@ pub struct BatchSpanProcessorBuilder<E, S, I> {
 
 impl<E, S, SO, I, IS, ISI> BatchSpanProcessorBuilder<E, S, I>
 where
-    E: exporter::trace::SpanExporter + 'static,
+    E: exporter::trace::AsyncSpanExporter + 'static,
     S: Fn(BatchSpanProcessorWorker) -> SO,
     I: Fn(time::Duration) -> IS,
     IS: Stream<Item = ISI> + Send + 'static,
@@ -331,6 +622,56 @@ where
         BatchSpanProcessorBuilder { config, ..self }
     }
 
+    /// Set the maximum number of export attempts for batches that fail with a
+    /// retryable error, including the first attempt
+    pub fn with_max_export_attempts(self, attempts: usize) -> Self {
+        let mut config = self.config;
+        config.max_export_attempts = attempts;
+
+        BatchSpanProcessorBuilder { config, ..self }
+    }
+
+    /// Set the backoff before the first retry of a failed batch
+    pub fn with_initial_backoff(self, backoff: time::Duration) -> Self {
+        let mut config = self.config;
+        config.initial_backoff = backoff;
+
+        BatchSpanProcessorBuilder { config, ..self }
+    }
+
+    /// Set the maximum backoff between retries of a failed batch
+    pub fn with_max_backoff(self, backoff: time::Duration) -> Self {
+        let mut config = self.config;
+        config.max_backoff = backoff;
+
+        BatchSpanProcessorBuilder { config, ..self }
+    }
+
+    /// Set the randomized fraction of each backoff, clamped to `0.0..=1.0`
+    pub fn with_backoff_jitter(self, jitter: f64) -> Self {
+        let mut config = self.config;
+        config.backoff_jitter = jitter.clamp(0.0, 1.0);
+
+        BatchSpanProcessorBuilder { config, ..self }
+    }
+
+    /// Set the total time during which a failed batch may be retried
+    pub fn with_retry_deadline(self, deadline: time::Duration) -> Self {
+        let mut config = self.config;
+        config.retry_deadline = deadline;
+
+        BatchSpanProcessorBuilder { config, ..self }
+    }
+
+    /// Set the maximum time `force_flush` and `shutdown` block waiting for
+    /// pending spans to be exported
+    pub fn with_flush_timeout(self, timeout: time::Duration) -> Self {
+        let mut config = self.config;
+        config.flush_timeout = timeout;
+
+        BatchSpanProcessorBuilder { config, ..self }
+    }
+
     /// Build a batch processor
     pub fn build(self) -> BatchSpanProcessor {
         BatchSpanProcessor::new(
@@ -341,3 +682,157 @@ where
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sdk::trace::testing::{span_data, TestExporter};
+    use exporter::trace::ExportResult;
+
+    /// Runs a worker over one exported span followed by `ticks` ticks,
+    /// returning the number of export calls.
+    fn run_worker(results: Vec<ExportResult>, ticks: usize, config: BatchConfig) -> usize {
+        let exporter = TestExporter::with_results(results);
+        let exports = exporter.exports.clone();
+        let mut messages = vec![BatchMessage::ExportSpan(span_data())];
+        messages.extend((0..ticks).map(|_| BatchMessage::Tick));
+        let (done, _) = std_mpsc::sync_channel(1);
+        messages.push(BatchMessage::Shutdown(Done::Blocking(done)));
+
+        let state = WorkerState {
+            exporter: Box::new(exporter::trace::SyncSpanExporterAdapter::new(exporter)),
+            config,
+            buffer: Vec::new(),
+            retries: VecDeque::new(),
+        };
+        futures::executor::block_on(state.run(Box::pin(futures::stream::iter(messages))));
+
+        let exports = *exports.lock().unwrap();
+        exports
+    }
+
+    fn retry_config() -> BatchConfig {
+        BatchConfig {
+            initial_backoff: time::Duration::from_secs(0),
+            backoff_jitter: 0.0,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn retries_retryable_failures() {
+        let results = vec![ExportResult::FailedRetryable, ExportResult::Success];
+        assert_eq!(run_worker(results, 4, retry_config()), 2);
+    }
+
+    #[test]
+    fn does_not_retry_non_retryable_failures() {
+        let results = vec![ExportResult::FailedNotRetryable];
+        assert_eq!(run_worker(results, 4, retry_config()), 1);
+    }
+
+    #[test]
+    fn stops_retrying_after_max_attempts() {
+        let results = vec![ExportResult::FailedRetryable; 4];
+        let config = BatchConfig {
+            max_export_attempts: 2,
+            ..retry_config()
+        };
+        assert_eq!(run_worker(results, 4, config), 2);
+    }
+
+    #[test]
+    fn stops_retrying_after_deadline() {
+        let results = vec![ExportResult::FailedRetryable; 4];
+        let config = BatchConfig {
+            initial_backoff: time::Duration::from_secs(60),
+            retry_deadline: time::Duration::from_secs(30),
+            ..retry_config()
+        };
+        assert_eq!(run_worker(results, 4, config), 1);
+    }
+
+    /// Builds a batch processor whose worker runs on its own thread and only
+    /// exports when flushed or shut down.
+    fn threaded_processor() -> (BatchSpanProcessor, Arc<Mutex<usize>>) {
+        let exporter = TestExporter::default();
+        let exports = exporter.exports.clone();
+        let processor = BatchSpanProcessor::builder(
+            exporter::trace::SyncSpanExporterAdapter::new(exporter),
+            |worker| std::thread::spawn(move || futures::executor::block_on(worker)),
+            |_| futures::stream::pending::<()>(),
+        )
+        .with_flush_timeout(time::Duration::from_secs(5))
+        .build();
+
+        (processor, exports)
+    }
+
+    #[test]
+    fn force_flush_exports_buffered_spans() {
+        use api::SpanProcessor;
+
+        let (processor, exports) = threaded_processor();
+        processor.on_end(span_data());
+        processor.force_flush();
+        assert_eq!(*exports.lock().unwrap(), 1);
+    }
+
+    #[test]
+    fn shutdown_exports_buffered_spans() {
+        use api::SpanProcessor;
+
+        let (processor, exports) = threaded_processor();
+        processor.on_end(span_data());
+        processor.on_end(span_data());
+        processor.shutdown();
+        assert_eq!(*exports.lock().unwrap(), 1);
+
+        // Subsequent shutdowns return immediately
+        processor.shutdown();
+    }
+
+    #[test]
+    fn shutdown_async_on_current_thread_executor() {
+        use api::SpanProcessor;
+
+        let mut runtime = tokio::runtime::Builder::new()
+            .basic_scheduler()
+            .enable_time()
+            .build()
+            .unwrap();
+        let exporter = TestExporter::default();
+        let exports = exporter.exports.clone();
+
+        runtime.block_on(async {
+            let processor = BatchSpanProcessor::builder(
+                exporter::trace::SyncSpanExporterAdapter::new(exporter),
+                tokio::spawn,
+                tokio::time::interval,
+            )
+            .build();
+            processor.on_end(span_data());
+            processor.shutdown_async().await;
+            assert_eq!(*exports.lock().unwrap(), 1);
+
+            // The worker has finished, so the blocking shutdown does not wait
+            let start = time::Instant::now();
+            processor.shutdown();
+            assert!(start.elapsed() < time::Duration::from_secs(1));
+        });
+    }
+
+    #[test]
+    fn backoff_is_exponential_and_capped() {
+        let config = BatchConfig {
+            initial_backoff: time::Duration::from_millis(100),
+            max_backoff: time::Duration::from_millis(350),
+            backoff_jitter: 0.0,
+            ..Default::default()
+        };
+        assert_eq!(config.backoff(1), time::Duration::from_millis(100));
+        assert_eq!(config.backoff(2), time::Duration::from_millis(200));
+        assert_eq!(config.backoff(3), time::Duration::from_millis(350));
+        assert_eq!(config.backoff(64), time::Duration::from_millis(350));
+    }
+}
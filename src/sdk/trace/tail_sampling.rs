@@ -0,0 +1,592 @@
+//! # Tail Sampling Processor
+//!
+//! Samplers decide whether to record a trace when its root span starts,
+//! before knowing whether the trace will be slow or fail. The
+//! [`TailSamplingProcessor`] instead buffers the finished spans of each trace
+//! for a decision window, then exports the whole trace if one of its
+//! [`TailSamplingPolicy`]s keeps it.
+//!
+//! ```
+//! use opentelemetry::{api, sdk};
+//! use opentelemetry::exporter::trace::stdout;
+//! use opentelemetry::sdk::trace::tail_sampling::{TailSamplingPolicy, TailSamplingProcessor};
+//! use std::time::Duration;
+//!
+//! let exporter = stdout::Builder::default().init();
+//! let processor = TailSamplingProcessor::builder(exporter)
+//!     .with_decision_wait(Duration::from_secs(10))
+//!     // Keep failed and slow traces
+//!     .with_policy(TailSamplingPolicy::Error)
+//!     .with_policy(TailSamplingPolicy::Latency(Duration::from_millis(500)))
+//!     // And one percent of the others
+//!     .with_policy(TailSamplingPolicy::Probabilistic(0.01))
+//!     .build();
+//! let provider = sdk::Provider::builder()
+//!     .with_span_processor(processor)
+//!     .build();
+//! ```
+//!
+//! [`TailSamplingProcessor`]: struct.TailSamplingProcessor.html
+//! [`TailSamplingPolicy`]: enum.TailSamplingPolicy.html
+use crate::api;
+use crate::exporter::trace::{SpanData, SpanExporter};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Default time to wait for the spans of a trace before deciding.
+const DEFAULT_DECISION_WAIT: Duration = Duration::from_secs(30);
+/// Default maximum number of traces waiting for a decision.
+const DEFAULT_MAX_TRACES: usize = 50_000;
+/// Default maximum number of buffered spans of a trace.
+const DEFAULT_MAX_SPANS_PER_TRACE: usize = 1_000;
+
+/// A reason to keep a trace. A trace is exported if any policy keeps it.
+#[derive(Clone, Debug)]
+pub enum TailSamplingPolicy {
+    /// Keep traces with a span ended with a status other than `OK`.
+    Error,
+    /// Keep traces whose root span lasted longer than the threshold. Spans
+    /// whose parent is not part of the buffered trace, such as spans with a
+    /// remote parent, are considered roots.
+    Latency(Duration),
+    /// Keep traces with a span having the given attribute.
+    Attribute(api::KeyValue),
+    /// Keep a given fraction of traces, based on their trace id. Added last,
+    /// it samples the traces not kept by other policies.
+    Probabilistic(f64),
+}
+
+impl TailSamplingPolicy {
+    /// Whether this policy keeps the trace made of `spans`.
+    fn keeps(&self, trace_id: api::TraceId, spans: &[Arc<SpanData>]) -> bool {
+        match self {
+            TailSamplingPolicy::Error => spans
+                .iter()
+                .any(|span| span.status_code != api::StatusCode::OK),
+            TailSamplingPolicy::Latency(threshold) => {
+                let span_ids = spans
+                    .iter()
+                    .map(|span| span.span_context.span_id())
+                    .collect::<HashSet<_>>();
+                spans
+                    .iter()
+                    .filter(|span| !span_ids.contains(&span.parent_span_id))
+                    .any(|span| {
+                        span.end_time
+                            .duration_since(span.start_time)
+                            .map(|duration| duration > *threshold)
+                            .unwrap_or(false)
+                    })
+            }
+            TailSamplingPolicy::Attribute(attribute) => spans.iter().any(|span| {
+                span.attributes
+                    .iter()
+                    .any(|(key, value)| *key == attribute.key && *value == attribute.value)
+            }),
+            TailSamplingPolicy::Probabilistic(ratio) => {
+                // Same sampling as `Sampler::Probability`, so both agree on a trace
+                let upper_bound = (ratio.max(0.0) * (1u64 << 63) as f64) as u64;
+                *ratio >= 1.0 || (trace_id.to_u128() as u64) >> 1 < upper_bound
+            }
+        }
+    }
+}
+
+/// The finished spans of a trace waiting for a decision.
+#[derive(Debug)]
+struct PendingTrace {
+    first_ended: Instant,
+    spans: Vec<Arc<SpanData>>,
+}
+
+/// The buffered traces, and the recent decisions applied to their late spans.
+#[derive(Debug, Default)]
+struct Traces {
+    pending: HashMap<api::TraceId, PendingTrace>,
+    // Pending trace ids, in the order of their first ended span
+    pending_order: VecDeque<api::TraceId>,
+    decisions: HashMap<api::TraceId, bool>,
+    decision_order: VecDeque<api::TraceId>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    policies: Vec<TailSamplingPolicy>,
+    decision_wait: Duration,
+    max_traces: usize,
+    max_spans_per_trace: usize,
+    traces: Mutex<Traces>,
+}
+
+impl Inner {
+    /// Buffer an ended span, returning the spans to export right away.
+    fn add(&self, span: Arc<SpanData>) -> Vec<Arc<SpanData>> {
+        let mut batch = Vec::new();
+        let trace_id = span.span_context.trace_id();
+        if let Ok(mut traces) = self.traces.lock() {
+            // Late spans of decided traces follow the decision
+            if let Some(keep) = traces.decisions.get(&trace_id) {
+                if *keep {
+                    batch.push(span);
+                }
+                return batch;
+            }
+
+            if !traces.pending.contains_key(&trace_id) {
+                // Decide the oldest traces early to make room for the new one
+                while traces.pending.len() >= self.max_traces.max(1) {
+                    match traces.pending_order.pop_front() {
+                        Some(oldest) => self.decide_pending(&mut traces, oldest, &mut batch),
+                        None => break,
+                    }
+                }
+                traces.pending_order.push_back(trace_id);
+            }
+            let trace = traces
+                .pending
+                .entry(trace_id)
+                .or_insert_with(|| PendingTrace {
+                    first_ended: Instant::now(),
+                    spans: Vec::new(),
+                });
+            trace.spans.push(span);
+            if trace.spans.len() >= self.max_spans_per_trace {
+                self.decide_pending(&mut traces, trace_id, &mut batch);
+            }
+        }
+
+        batch
+    }
+
+    /// Decide the traces whose decision window is over, or all pending
+    /// traces if `all` is set, returning the spans to export.
+    fn decide(&self, all: bool) -> Vec<Arc<SpanData>> {
+        let mut batch = Vec::new();
+        if let Ok(mut traces) = self.traces.lock() {
+            while let Some(trace_id) = traces.pending_order.front().copied() {
+                let waiting = traces
+                    .pending
+                    .get(&trace_id)
+                    .map(|trace| trace.first_ended.elapsed() < self.decision_wait)
+                    .unwrap_or(false);
+                if waiting && !all {
+                    break;
+                }
+                traces.pending_order.pop_front();
+                self.decide_pending(&mut traces, trace_id, &mut batch);
+            }
+        }
+
+        batch
+    }
+
+    /// Apply the policies to a pending trace, moving its spans to `batch` if
+    /// it is kept.
+    fn decide_pending(
+        &self,
+        traces: &mut Traces,
+        trace_id: api::TraceId,
+        batch: &mut Vec<Arc<SpanData>>,
+    ) {
+        let trace = match traces.pending.remove(&trace_id) {
+            Some(trace) => trace,
+            // Already decided early
+            None => return,
+        };
+        let keep = self
+            .policies
+            .iter()
+            .any(|policy| policy.keeps(trace_id, &trace.spans));
+        if keep {
+            batch.extend(trace.spans);
+        }
+
+        traces.decisions.insert(trace_id, keep);
+        traces.decision_order.push_back(trace_id);
+        while traces.decision_order.len() > self.max_traces {
+            if let Some(oldest) = traces.decision_order.pop_front() {
+                traces.decisions.remove(&oldest);
+            }
+        }
+    }
+}
+
+/// Messages handled by the worker thread, the only thread exporting spans.
+#[derive(Debug)]
+enum Message {
+    /// Export the spans of decided traces.
+    Export(Vec<Arc<SpanData>>),
+    /// Decide and export all pending traces, then notify the sender.
+    Flush(mpsc::SyncSender<()>),
+    /// Decide and export all pending traces, then shut the exporter down.
+    Shutdown,
+}
+
+/// Export the spans of decided traces, unless there are none.
+fn export(exporter: &dyn SpanExporter, batch: Vec<Arc<SpanData>>) {
+    if !batch.is_empty() {
+        exporter.export(batch);
+    }
+}
+
+/// A `SpanProcessor` buffering the finished spans of each trace, and
+/// exporting the traces kept by its policies once their decision window is
+/// over.
+///
+/// The decision window of a trace starts when its first span ends. Traces
+/// are decided early when more than the maximum number of traces are
+/// waiting, or when a trace reaches the maximum number of spans. Spans
+/// ending after the decision of their trace are exported if it was kept.
+///
+/// All exports happen on the background thread deciding the traces, so the
+/// exporter is never called concurrently.
+#[derive(Debug)]
+pub struct TailSamplingProcessor {
+    inner: Arc<Inner>,
+    sender: Mutex<mpsc::Sender<Message>>,
+    worker: Mutex<Option<thread::JoinHandle<()>>>,
+}
+
+impl TailSamplingProcessor {
+    /// Create a new builder for a processor exporting kept traces to
+    /// `exporter`.
+    pub fn builder<E: SpanExporter + 'static>(exporter: E) -> TailSamplingProcessorBuilder {
+        TailSamplingProcessorBuilder {
+            exporter: Box::new(exporter),
+            policies: Vec::new(),
+            decision_wait: DEFAULT_DECISION_WAIT,
+            max_traces: DEFAULT_MAX_TRACES,
+            max_spans_per_trace: DEFAULT_MAX_SPANS_PER_TRACE,
+        }
+    }
+
+    /// Send a message to the worker, returning whether it is still running.
+    fn send(&self, message: Message) -> bool {
+        self.sender
+            .lock()
+            .map(|sender| sender.send(message).is_ok())
+            .unwrap_or(false)
+    }
+}
+
+impl api::SpanProcessor for TailSamplingProcessor {
+    fn on_start(&self, _span: Arc<SpanData>) {
+        // Ignored
+    }
+
+    fn on_end(&self, span: Arc<SpanData>) {
+        if span.span_context.is_sampled() {
+            let batch = self.inner.add(span);
+            if !batch.is_empty() {
+                self.send(Message::Export(batch));
+            }
+        }
+    }
+
+    fn force_flush(&self) {
+        let (done_sender, done_receiver) = mpsc::sync_channel(1);
+        if self.send(Message::Flush(done_sender)) {
+            let _ = done_receiver.recv();
+        }
+    }
+
+    fn shutdown(&self) {
+        let worker = self.worker.lock().ok().and_then(|mut worker| worker.take());
+        if let Some(worker) = worker {
+            self.send(Message::Shutdown);
+            let _ = worker.join();
+        }
+    }
+}
+
+/// Builder for configuring a `TailSamplingProcessor`.
+#[derive(Debug)]
+pub struct TailSamplingProcessorBuilder {
+    exporter: Box<dyn SpanExporter>,
+    policies: Vec<TailSamplingPolicy>,
+    decision_wait: Duration,
+    max_traces: usize,
+    max_spans_per_trace: usize,
+}
+
+impl TailSamplingProcessorBuilder {
+    /// Add a policy keeping traces. Traces kept by no policy are dropped.
+    pub fn with_policy(mut self, policy: TailSamplingPolicy) -> Self {
+        self.policies.push(policy);
+        self
+    }
+
+    /// Assign the time to wait for the spans of a trace, from the end of its
+    /// first span until its decision. Defaults to 30 seconds.
+    pub fn with_decision_wait(self, decision_wait: Duration) -> Self {
+        TailSamplingProcessorBuilder {
+            decision_wait,
+            ..self
+        }
+    }
+
+    /// Assign the maximum number of traces waiting for a decision, and of
+    /// recent decisions remembered for late spans. Defaults to 50000.
+    pub fn with_max_traces(self, max_traces: usize) -> Self {
+        TailSamplingProcessorBuilder { max_traces, ..self }
+    }
+
+    /// Assign the maximum number of buffered spans of a trace, after which
+    /// it is decided early. Defaults to 1000.
+    pub fn with_max_spans_per_trace(self, max_spans_per_trace: usize) -> Self {
+        TailSamplingProcessorBuilder {
+            max_spans_per_trace,
+            ..self
+        }
+    }
+
+    /// Create the processor, deciding traces on a background thread.
+    pub fn build(self) -> TailSamplingProcessor {
+        let (sender, receiver) = mpsc::channel();
+        let exporter = self.exporter;
+        let inner = Arc::new(Inner {
+            policies: self.policies,
+            decision_wait: self.decision_wait,
+            max_traces: self.max_traces,
+            max_spans_per_trace: self.max_spans_per_trace,
+            traces: Mutex::new(Traces::default()),
+        });
+
+        // Check a few times per window, so traces are decided soon after it
+        let tick = (self.decision_wait / 4).max(Duration::from_millis(1));
+        let worker_inner = inner.clone();
+        let worker = thread::Builder::new()
+            .name("opentelemetry-tail-sampling".to_string())
+            .spawn(move || {
+                let mut next_tick = Instant::now() + tick;
+                loop {
+                    let timeout = next_tick.saturating_duration_since(Instant::now());
+                    match receiver.recv_timeout(timeout) {
+                        Ok(Message::Export(batch)) => export(exporter.as_ref(), batch),
+                        Ok(Message::Flush(done)) => {
+                            export(exporter.as_ref(), worker_inner.decide(true));
+                            let _ = done.send(());
+                        }
+                        Ok(Message::Shutdown) | Err(mpsc::RecvTimeoutError::Disconnected) => {
+                            export(exporter.as_ref(), worker_inner.decide(true));
+                            exporter.shutdown();
+                            break;
+                        }
+                        Err(mpsc::RecvTimeoutError::Timeout) => {}
+                    }
+                    // Decide on time even while spans keep being exported
+                    if Instant::now() >= next_tick {
+                        export(exporter.as_ref(), worker_inner.decide(false));
+                        next_tick = Instant::now() + tick;
+                    }
+                }
+            })
+            .ok();
+
+        TailSamplingProcessor {
+            inner,
+            sender: Mutex::new(sender),
+            worker: Mutex::new(worker),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{Provider, Span, SpanProcessor, Tracer};
+    use crate::exporter::trace::ExportResult;
+    use crate::sdk;
+    use crate::sdk::trace::testing::TestExporter;
+    use std::time::SystemTime;
+
+    fn exported_names(spans: &Mutex<Vec<Arc<SpanData>>>) -> Vec<String> {
+        let mut names = spans
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|span| span.name.clone())
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    /// The names of the exported spans, once `count` spans were exported by
+    /// the worker or after a second.
+    fn exported_names_after(spans: &Mutex<Vec<Arc<SpanData>>>, count: usize) -> Vec<String> {
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while spans.lock().unwrap().len() < count && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(1));
+        }
+        exported_names(spans)
+    }
+
+    #[test]
+    fn exports_whole_traces_kept_by_policies() {
+        let exporter = TestExporter::default();
+        let spans = exporter.spans.clone();
+        let processor = TailSamplingProcessor::builder(exporter)
+            .with_policy(TailSamplingPolicy::Error)
+            .with_policy(TailSamplingPolicy::Latency(Duration::from_secs(1)))
+            .with_policy(TailSamplingPolicy::Attribute(api::KeyValue::new(
+                "debug", true,
+            )))
+            .build();
+        let provider = sdk::Provider::builder()
+            .with_span_processor(processor)
+            .build();
+        let tracer = provider.get_tracer("test");
+
+        tracer.in_span("boring", |_| tracer.start("boring.child").end());
+        tracer.in_span("failed", |_| {
+            let child = tracer.start("failed.child");
+            child.set_status(api::StatusCode::Internal, String::new());
+            child.end();
+        });
+        tracer.in_span("debugged", |_| {
+            tracer
+                .start("debugged.child")
+                .set_attribute(api::KeyValue::new("debug", true));
+        });
+        let slow = tracer
+            .span_builder("slow")
+            .with_start_time(SystemTime::now() - Duration::from_secs(2))
+            .start(&tracer);
+        tracer.with_span(slow, |_| tracer.start("slow.child").end());
+
+        assert!(spans.lock().unwrap().is_empty());
+        provider.force_flush();
+        assert_eq!(
+            exported_names(&spans),
+            vec![
+                "debugged",
+                "debugged.child",
+                "failed",
+                "failed.child",
+                "slow",
+                "slow.child"
+            ]
+        );
+    }
+
+    #[test]
+    fn decides_after_window_and_limits() {
+        let exporter = TestExporter::default();
+        let spans = exporter.spans.clone();
+        let processor = TailSamplingProcessor::builder(exporter)
+            .with_policy(TailSamplingPolicy::Probabilistic(1.0))
+            .with_decision_wait(Duration::from_millis(20))
+            .with_max_traces(2)
+            .with_max_spans_per_trace(2)
+            .build();
+        let provider = sdk::Provider::builder()
+            .with_span_processor(processor)
+            .build();
+        let tracer = provider.get_tracer("test");
+
+        // Decided once the window is over
+        tracer.start("windowed").end();
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(exported_names(&spans), vec!["windowed"]);
+
+        let exporter = TestExporter::default();
+        let spans = exporter.spans.clone();
+        let processor = TailSamplingProcessor::builder(exporter)
+            .with_policy(TailSamplingPolicy::Probabilistic(1.0))
+            .with_decision_wait(Duration::from_secs(60))
+            .with_max_traces(2)
+            .with_max_spans_per_trace(2)
+            .build();
+        let provider = sdk::Provider::builder()
+            .with_span_processor(processor)
+            .build();
+        let tracer = provider.get_tracer("test");
+
+        // Decided early when reaching the maximum number of spans, with late
+        // spans following the decision
+        tracer.in_span("full", |_| {
+            tracer.start("full.1").end();
+            tracer.start("full.2").end();
+        });
+        assert_eq!(
+            exported_names_after(&spans, 3),
+            vec!["full", "full.1", "full.2"]
+        );
+
+        // Decided early when reaching the maximum number of traces
+        spans.lock().unwrap().clear();
+        for name in &["first", "second", "third"] {
+            tracer.start(name).end();
+        }
+        assert_eq!(exported_names_after(&spans, 1), vec!["first"]);
+    }
+
+    #[derive(Debug, Default)]
+    struct ThreadExporter {
+        threads: Arc<Mutex<Vec<Option<String>>>>,
+    }
+
+    impl SpanExporter for ThreadExporter {
+        fn export(&self, _batch: Vec<Arc<SpanData>>) -> ExportResult {
+            let thread = thread::current().name().map(str::to_string);
+            self.threads.lock().unwrap().push(thread);
+            ExportResult::Success
+        }
+
+        fn shutdown(&self) {}
+    }
+
+    #[test]
+    fn exports_on_the_worker_thread() {
+        let exporter = ThreadExporter::default();
+        let threads = exporter.threads.clone();
+        let processor = TailSamplingProcessor::builder(exporter)
+            .with_policy(TailSamplingPolicy::Probabilistic(1.0))
+            .with_max_spans_per_trace(1)
+            .build();
+        let provider = sdk::Provider::builder()
+            .with_span_processor(processor)
+            .build();
+
+        let ending = (0..4)
+            .map(|_| {
+                let tracer = provider.get_tracer("test");
+                thread::spawn(move || tracer.start("decided early").end())
+            })
+            .collect::<Vec<_>>();
+        for thread in ending {
+            thread.join().unwrap();
+        }
+        provider.get_tracer("test").start("flushed").end();
+        provider.force_flush();
+
+        let threads = threads.lock().unwrap();
+        assert_eq!(threads.len(), 5);
+        assert!(threads
+            .iter()
+            .all(|thread| thread.as_deref() == Some("opentelemetry-tail-sampling")));
+    }
+
+    #[test]
+    fn drops_traces_kept_by_no_policy() {
+        let exporter = TestExporter::default();
+        let spans = exporter.spans.clone();
+        let processor = TailSamplingProcessor::builder(exporter)
+            .with_policy(TailSamplingPolicy::Probabilistic(0.0))
+            .build();
+        let provider = sdk::Provider::builder()
+            .with_span_processor(processor)
+            .build();
+
+        provider.get_tracer("test").start("dropped").end();
+        provider.force_flush();
+        assert!(spans.lock().unwrap().is_empty());
+
+        // `shutdown` decides pending traces and stops the worker
+        let processor = TailSamplingProcessor::builder(TestExporter::default()).build();
+        processor.shutdown();
+        processor.shutdown();
+    }
+}
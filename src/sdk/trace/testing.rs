@@ -0,0 +1,88 @@
+//! Span processors, exporters and spans shared by the tests of the trace SDK.
+use crate::api;
+use crate::exporter::trace::{ExportResult, SpanData, SpanExporter};
+use crate::sdk;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+/// A processor recording the ended spans, and the names of the ended spans
+/// along with its flushes and shutdowns.
+#[derive(Debug, Default)]
+pub(crate) struct TestProcessor {
+    pub(crate) ended: Arc<Mutex<Vec<Arc<SpanData>>>>,
+    pub(crate) events: Arc<Mutex<Vec<String>>>,
+}
+
+impl api::SpanProcessor for TestProcessor {
+    fn on_start(&self, _span: Arc<SpanData>) {}
+
+    fn on_end(&self, span: Arc<SpanData>) {
+        self.events.lock().unwrap().push(span.name.clone());
+        self.ended.lock().unwrap().push(span);
+    }
+
+    fn force_flush(&self) {
+        self.events.lock().unwrap().push("flush".to_string());
+    }
+
+    fn shutdown(&self) {
+        self.events.lock().unwrap().push("shutdown".to_string());
+    }
+}
+
+/// An exporter recording the exported spans and the number of exports,
+/// returning the queued results before succeeding.
+#[derive(Debug, Default)]
+pub(crate) struct TestExporter {
+    pub(crate) results: Mutex<VecDeque<ExportResult>>,
+    pub(crate) spans: Arc<Mutex<Vec<Arc<SpanData>>>>,
+    pub(crate) exports: Arc<Mutex<usize>>,
+}
+
+impl TestExporter {
+    /// An exporter returning `results` for its first exports.
+    pub(crate) fn with_results(results: Vec<ExportResult>) -> Self {
+        TestExporter {
+            results: Mutex::new(results.into()),
+            ..Default::default()
+        }
+    }
+}
+
+impl SpanExporter for TestExporter {
+    fn export(&self, batch: Vec<Arc<SpanData>>) -> ExportResult {
+        *self.exports.lock().unwrap() += 1;
+        self.spans.lock().unwrap().extend(batch);
+        self.results
+            .lock()
+            .unwrap()
+            .pop_front()
+            .unwrap_or(ExportResult::Success)
+    }
+
+    fn shutdown(&self) {}
+}
+
+/// A sampled span without attributes, events or links.
+pub(crate) fn span_data() -> Arc<SpanData> {
+    Arc::new(SpanData {
+        span_context: api::SpanContext::new(
+            api::TraceId::from_u128(1),
+            api::SpanId::from_u64(1),
+            api::TRACE_FLAG_SAMPLED,
+            false,
+        ),
+        parent_span_id: api::SpanId::invalid(),
+        span_kind: api::SpanKind::Internal,
+        name: "span".to_string(),
+        start_time: SystemTime::now(),
+        end_time: SystemTime::now(),
+        attributes: sdk::EvictedHashMap::new(0),
+        message_events: sdk::EvictedQueue::new(0),
+        links: sdk::EvictedQueue::new(0),
+        status_code: api::StatusCode::OK,
+        status_message: String::new(),
+        resource: Arc::new(sdk::Resource::default()),
+    })
+}
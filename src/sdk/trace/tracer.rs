@@ -44,6 +44,7 @@ impl Tracer {
     #[allow(clippy::too_many_arguments)]
     fn make_sampling_decision(
         &self,
+        config: &sdk::Config,
         parent_context: Option<&api::SpanContext>,
         trace_id: api::TraceId,
         name: &str,
@@ -51,9 +52,14 @@ impl Tracer {
         attributes: &[api::KeyValue],
         links: &[api::Link],
     ) -> Option<(u8, Vec<api::KeyValue>)> {
-        let sampler = &self.provider.config().default_sampler;
-        let sampling_result =
-            sampler.should_sample(parent_context, trace_id, name, span_kind, attributes, links);
+        let sampling_result = config.default_sampler.should_sample(
+            parent_context,
+            trace_id,
+            name,
+            span_kind,
+            attributes,
+            links,
+        );
 
         self.process_sampling_result(sampling_result, parent_context)
     }
@@ -93,7 +99,7 @@ impl api::Tracer for Tracer {
     /// Returns a span with an inactive `SpanContext`. Used by functions that
     /// need to return a default span like `get_active_span` if no span is present.
     fn invalid(&self) -> Self::Span {
-        sdk::Span::new(api::SpanId::invalid(), None, self.clone())
+        sdk::Span::new(api::SpanContext::empty_context(), None, self.clone())
     }
 
     /// Starts a new `Span` in a given context.
@@ -124,11 +130,12 @@ impl api::Tracer for Tracer {
     /// trace includes a single root span, which is the shared ancestor of all other
     /// spans in the trace.
     fn build_with_context(&self, mut builder: api::SpanBuilder, cx: &Context) -> Self::Span {
+        // Use the same config for the whole span, even if the provider config changes
         let config = self.provider.config();
         let span_id = builder
             .span_id
             .take()
-            .unwrap_or_else(|| self.provider().config().id_generator.new_span_id());
+            .unwrap_or_else(|| config.id_generator.new_span_id());
 
         let span_kind = builder.span_kind.take().unwrap_or(api::SpanKind::Internal);
         let mut attribute_options = builder.attributes.take().unwrap_or_else(Vec::new);
@@ -141,37 +148,33 @@ impl api::Tracer for Tracer {
             .or_else(|| cx.remote_span_context().cloned())
             .filter(|cx| cx.is_valid());
         // Build context for sampling decision
-        let (no_parent, trace_id, parent_span_id, remote_parent, parent_trace_flags) =
-            parent_span_context
-                .as_ref()
-                .map(|ctx| {
-                    (
-                        false,
-                        ctx.trace_id(),
-                        ctx.span_id(),
-                        ctx.is_remote(),
-                        ctx.trace_flags(),
-                    )
-                })
-                .unwrap_or((
-                    true,
+        let (trace_id, parent_span_id) = parent_span_context
+            .as_ref()
+            .map(|ctx| (ctx.trace_id(), ctx.span_id()))
+            .unwrap_or_else(|| {
+                (
                     builder
                         .trace_id
-                        .unwrap_or_else(|| self.provider().config().id_generator.new_trace_id()),
+                        .unwrap_or_else(|| config.id_generator.new_trace_id()),
                     api::SpanId::invalid(),
-                    false,
-                    0,
-                ));
+                )
+            });
 
-        // There are 3 paths for sampling.
+        // Child spans inherit the parent's trace state
+        let trace_state = parent_span_context
+            .as_ref()
+            .map(|ctx| ctx.trace_state().clone())
+            .unwrap_or_default();
+
+        // There are 2 paths for sampling.
         //
         // * Sampling has occurred elsewhere and is already stored in the builder
-        // * There is no parent or a remote parent, in which case make decision now
-        // * There is a local parent, in which case defer to the parent's decision
+        // * Otherwise the sampler decides, following the parent if it chooses to
         let sampling_decision = if let Some(sampling_result) = builder.sampling_result.take() {
             self.process_sampling_result(sampling_result, parent_span_context.as_ref())
-        } else if no_parent || remote_parent {
+        } else {
             self.make_sampling_decision(
+                &config,
                 parent_span_context.as_ref(),
                 trace_id,
                 &builder.name,
@@ -179,15 +182,24 @@ impl api::Tracer for Tracer {
                 &attribute_options,
                 &link_options,
             )
-        } else {
-            // has parent that is local: use parent if sampled, or don't record.
-            parent_span_context
-                .filter(|span_context| span_context.is_sampled())
-                .map(|_| (parent_trace_flags, Vec::new()))
         };
 
+        // Spans that are not recording keep their context, so that their
+        // children stay in the same trace and follow the decision.
+        let trace_flags = sampling_decision
+            .as_ref()
+            .map(|(trace_flags, _)| *trace_flags)
+            .unwrap_or_else(|| {
+                parent_span_context
+                    .as_ref()
+                    .map(|ctx| ctx.trace_flags() & !api::TRACE_FLAG_SAMPLED)
+                    .unwrap_or(0)
+            });
+        let span_context = api::SpanContext::new(trace_id, span_id, trace_flags, false)
+            .with_trace_state(trace_state);
+
         // Build optional inner context, `None` if not recording.
-        let inner = sampling_decision.map(move |(trace_flags, mut extra_attrs)| {
+        let inner = sampling_decision.map(|(_, mut extra_attrs)| {
             attribute_options.append(&mut extra_attrs);
             let mut attributes = sdk::EvictedHashMap::new(config.max_attributes_per_span);
             for attribute in attribute_options {
@@ -206,7 +218,7 @@ impl api::Tracer for Tracer {
             let resource = config.resource.clone();
 
             exporter::trace::SpanData {
-                span_context: api::SpanContext::new(trace_id, span_id, trace_flags, false),
+                span_context: span_context.clone(),
                 parent_span_id,
                 span_kind,
                 name: builder.name,
@@ -224,11 +236,126 @@ impl api::Tracer for Tracer {
         // Call `on_start` for all processors
         if let Some(inner) = inner.as_ref().cloned() {
             let inner_data = Arc::new(inner);
-            for processor in self.provider.span_processors() {
+            for processor in self.provider.span_processors().iter() {
                 processor.on_start(inner_data.clone())
             }
         }
 
-        sdk::Span::new(span_id, inner, self.clone())
+        sdk::Span::new(span_context, inner, self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::{self, Provider, Span, TraceContextExt, Tracer};
+    use crate::sdk;
+    use crate::sdk::trace::testing::TestProcessor;
+
+    #[test]
+    fn propagates_trace_state_to_children() {
+        let provider = sdk::Provider::default();
+        let tracer = provider.get_tracer("test");
+        let trace_state: api::TraceState = "rojo=00f067aa0ba902b7".parse().unwrap();
+        let parent = api::SpanContext::new(
+            api::TraceId::from_u128(128),
+            api::SpanId::from_u64(64),
+            api::TRACE_FLAG_SAMPLED,
+            true,
+        )
+        .with_trace_state(trace_state.clone());
+
+        let cx = api::Context::new().with_remote_span_context(parent);
+        let child = tracer.start_from_context("child", &cx);
+        assert_eq!(child.span_context().trace_state(), &trace_state);
+
+        let root = tracer.start_from_context("root", &api::Context::new());
+        assert!(root.span_context().trace_state().is_empty());
+    }
+
+    #[test]
+    fn samples_spans_with_local_parent() {
+        let provider = sdk::Provider::builder()
+            .with_config(sdk::Config {
+                default_sampler: Box::new(sdk::Sampler::ParentBased {
+                    root: Box::new(sdk::Sampler::Always),
+                    remote_sampled: Box::new(sdk::Sampler::Always),
+                    remote_not_sampled: Box::new(sdk::Sampler::Never),
+                    local_sampled: Box::new(sdk::Sampler::Never),
+                    local_not_sampled: Box::new(sdk::Sampler::Never),
+                }),
+                ..Default::default()
+            })
+            .build();
+        let tracer = provider.get_tracer("test");
+
+        let parent = tracer.start("parent");
+        assert!(parent.span_context().is_sampled());
+        let child = tracer.with_span(parent, |_| tracer.start("child"));
+        assert!(!child.is_recording());
+        assert!(!child.span_context().is_sampled());
+    }
+
+    #[test]
+    fn follows_local_parent_of_rule_based_sampler() {
+        let provider = sdk::Provider::builder()
+            .with_config(sdk::Config {
+                default_sampler: Box::new(sdk::Sampler::RuleBased {
+                    rules: vec![
+                        sdk::SamplingRule::new(sdk::Sampler::Never).with_name_pattern("/healthz")
+                    ],
+                    fallback: Box::new(sdk::Sampler::Always),
+                }),
+                ..Default::default()
+            })
+            .build();
+        let tracer = provider.get_tracer("test");
+
+        // Children of a dropped root are dropped too
+        let root = tracer.start("/healthz");
+        assert!(!root.is_recording());
+        let child = tracer.with_span(root, |_| tracer.start("db query"));
+        assert!(!child.is_recording());
+        assert!(!child.span_context().is_sampled());
+
+        let root = tracer.start("/checkout");
+        assert!(root.span_context().is_sampled());
+        let child = tracer.with_span(root, |_| tracer.start("/healthz"));
+        assert!(child.span_context().is_sampled());
+    }
+
+    #[test]
+    fn spans_use_config_at_start() {
+        let processor = TestProcessor::default();
+        let ended = processor.ended.clone();
+        let provider = sdk::Provider::builder()
+            .with_span_processor(processor)
+            .build();
+        let tracer = provider.get_tracer("test");
+
+        let in_flight = tracer.start("in_flight");
+        provider.set_config(sdk::Config {
+            default_sampler: Box::new(sdk::Sampler::Never),
+            max_attributes_per_span: 1,
+            ..Default::default()
+        });
+        assert!(!tracer.start("unsampled").is_recording());
+
+        // Started spans keep the limits of the previous config
+        for key in &["a", "b"] {
+            in_flight.set_attribute(api::Key::new(*key).bool(true));
+        }
+        drop(in_flight);
+        assert_eq!(ended.lock().unwrap()[0].attributes.len(), 2);
+
+        provider.set_config(sdk::Config {
+            max_attributes_per_span: 1,
+            ..Default::default()
+        });
+        let span = provider.get_tracer("test").start("limited");
+        for key in &["a", "b"] {
+            span.set_attribute(api::Key::new(*key).bool(true));
+        }
+        drop(span);
+        assert_eq!(ended.lock().unwrap()[1].attributes.len(), 1);
     }
 }